@@ -0,0 +1,52 @@
+//! Generates bindings outside of a build script, for checking them in.
+//!
+//! ```text
+//! blackbird <header>... -o <dir> [-- <clang args>...]
+//! ```
+//!
+//! Writes `bindings.rs`, `glue.h` and `glue.cc` for all the headers to
+//! `<dir>`. The glue still has to be compiled and linked by whatever builds
+//! the crate using them.
+
+use std::path::PathBuf;
+use std::process::exit;
+
+const USAGE: &str = "usage: blackbird <header>... -o <dir> [-- <clang args>...]";
+
+fn main() {
+  let mut args = std::env::args().skip(1);
+  let mut headers = Vec::new();
+  let mut out = None;
+  let mut clang_args = Vec::new();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-o" | "--out" => match args.next() {
+        Some(dir) => out = Some(PathBuf::from(dir)),
+        None => fail(&format!("{} needs a directory", arg)),
+      },
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        return;
+      },
+      "--" => clang_args.extend(&mut args),
+      _ if arg.starts_with('-') => fail(&format!("unknown option {}", arg)),
+      _ => headers.push(PathBuf::from(arg)),
+    }
+  }
+
+  let (header, out) = match (headers.first(), out) {
+    (Some(header), Some(out)) => (header.clone(), out),
+    _ => fail(USAGE),
+  };
+
+  let builder = headers[1..].iter().fold(gen::Builder::new(header), |builder, x| builder.header(x));
+  if let Err(e) = builder.clang_args(clang_args).write_to(out) {
+    fail(&e.to_string());
+  }
+}
+
+fn fail(message: &str) -> ! {
+  eprintln!("{}", message);
+  exit(1);
+}
@@ -1,57 +1,37 @@
 use std::string::ToString;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::collections::{HashMap, HashSet};
 use clang::*;
-use rand::random;
-use proc_macro2::TokenStream;
+use proc_macro2::{Delimiter, Literal, TokenStream, TokenTree};
 use quote::{quote, format_ident, ToTokens};
 use std::fs;
-use std::io::prelude::*;
-
-trait ToTokenStream {
-  fn to_token_stream(&self) -> TokenStream;
-}
-
-impl ToTokenStream for Type<'_> {
-  fn to_token_stream(&self) -> TokenStream {
-    let root = self.get_canonical_type();
-
-    match root.get_kind() {
-      TypeKind::Pointer => {
-        let pointee = root.get_pointee_type().unwrap();
-        let tokens = pointee.to_token_stream();
-        if pointee.is_const_qualified() {
-          quote!(*const #tokens)
-        } else {
-          quote!(*mut #tokens)
-        }
-      },
-
-      TypeKind::Void => quote!(std::os::raw::c_void),
-      TypeKind::CharS => quote!(std::os::raw::c_char),
-      TypeKind::CharU => quote!(std::os::raw::c_char),
-      TypeKind::SChar => quote!(std::os::raw::c_schar),
-      TypeKind::UChar => quote!(std::os::raw::c_uchar),
-      TypeKind::Short => quote!(std::os::raw::c_short),
-      TypeKind::UShort => quote!(std::os::raw::c_ushort),
-      TypeKind::Int => quote!(std::os::raw::c_int),
-      TypeKind::UInt => quote!(std::os::raw::c_uint),
-      TypeKind::Long => quote!(std::os::raw::c_long),
-      TypeKind::ULong => quote!(std::os::raw::c_ulong),
-      TypeKind::LongLong => quote!(std::os::raw::c_longlong),
-      TypeKind::ULongLong => quote!(std::os::raw::c_ulonglong),
-      TypeKind::Float => quote!(std::os::raw::c_float),
-      TypeKind::Double => quote!(std::os::raw::c_double),
-      _ => panic!("invalid type: {}", root.get_display_name()),
-    }
-  }
-}
 
 #[derive(Debug)]
 enum Item {
   Mod(ItemMod),
   Fn(ItemFn),
-  Struct(ItemStruct),
+  Struct(Box<ItemStruct>),
+  Union(ItemUnion),
+  Enum(ItemEnum),
+  Opaque(ItemOpaque),
+  Float(ItemFloat),
+  LongDouble(ItemLongDouble),
+  Vector(ItemVector),
+  Const(ItemConst),
+  Alias(ItemAlias),
+  Var(ItemVar),
+  Cfg(ItemCfg),
+  Exception(ItemException),
+  CxxString(ItemCxxString),
+  CxxVector(ItemCxxVector),
+  Symbols(ItemSymbols),
+  Wide(ItemWide),
+  CxxBox(ItemCxxBox),
+  CxxFunction(ItemCxxFunction),
+  Trait(ItemTrait),
+  Generic(ItemGeneric),
+  RawMethods(ItemRawMethods),
 }
 
 impl ToTokens for Item {
@@ -60,6 +40,26 @@ impl ToTokens for Item {
       Self::Fn(item) => item.to_tokens(tokens),
       Self::Mod(item) => item.to_tokens(tokens),
       Self::Struct(item) => item.to_tokens(tokens),
+      Self::Union(item) => item.to_tokens(tokens),
+      Self::Enum(item) => item.to_tokens(tokens),
+      Self::Opaque(item) => item.to_tokens(tokens),
+      Self::Float(item) => item.to_tokens(tokens),
+      Self::LongDouble(item) => item.to_tokens(tokens),
+      Self::Vector(item) => item.to_tokens(tokens),
+      Self::Const(item) => item.to_tokens(tokens),
+      Self::Alias(item) => item.to_tokens(tokens),
+      Self::Var(item) => item.to_tokens(tokens),
+      Self::Cfg(item) => item.to_tokens(tokens),
+      Self::Exception(item) => item.to_tokens(tokens),
+      Self::CxxString(item) => item.to_tokens(tokens),
+      Self::CxxVector(item) => item.to_tokens(tokens),
+      Self::Symbols(item) => item.to_tokens(tokens),
+      Self::Wide(item) => item.to_tokens(tokens),
+      Self::CxxBox(item) => item.to_tokens(tokens),
+      Self::CxxFunction(item) => item.to_tokens(tokens),
+      Self::Trait(item) => item.to_tokens(tokens),
+      Self::Generic(item) => item.to_tokens(tokens),
+      Self::RawMethods(item) => item.to_tokens(tokens),
     }
   }
 }
@@ -68,16 +68,54 @@ impl ToTokens for Item {
 struct ItemMod {
   name: String,
   items: Vec<Item>,
-  comments: Vec<String>,
+  comments: Vec<std::rc::Rc<str>>,
 }
 
 impl ToTokens for ItemMod {
   fn to_tokens(&self, tokens: &mut TokenStream) {
     let name = format_ident!("{}", self.name);
     let items = &self.items;
-    let comments = self.comments.iter().map(|x| TokenStream::from_str(&x).unwrap());
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    quote!(
+      #(#comments)*
+      pub mod #name {
+        #[allow(unused_imports)]
+        use super::*;
+
+        #(#items)*
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug)]
+struct ItemCfg {
+  targets: Vec<String>,
+  items: Vec<Item>,
+}
+
+impl ToTokens for ItemCfg {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let targets = &self.targets;
+    let cfg = quote!(#[cfg(any(#(target_os = #targets),*))]);
+    let (mods, items): (Vec<_>, Vec<_>) = self.items.iter().partition(|x| matches!(x, Item::Mod(_)));
 
-    quote!(#(#comments)* mod #name { #(#items)* }).to_tokens(tokens);
+    quote!(#(#cfg #mods)*).to_tokens(tokens);
+
+    if !items.is_empty() {
+      let name = format_ident!("__cfg_{}", self.targets.join("_"));
+      quote!(
+        #cfg
+        mod #name {
+          #[allow(unused_imports)]
+          use super::*;
+          #(#items)*
+        }
+        #cfg
+        pub use self::#name::*;
+      ).to_tokens(tokens);
+    }
   }
 }
 
@@ -96,460 +134,9420 @@ impl ToTokens for Arg {
   }
 }
 
-#[derive(Debug)]
-struct ItemFn {
-  name: String,
-  symbol: String,
-  args: Vec<Arg>,
-  ret: TokenStream,
-  comments: Vec<String>,
-}
-
-impl ToTokens for ItemFn {
-  fn to_tokens(&self, tokens: &mut TokenStream) {
-    let name = format_ident!("{}", self.name);
-    let symbol = &self.symbol;
-    let ret = &self.ret;
-
-    let comments = self.comments.iter().map(|x| TokenStream::from_str(&x).unwrap());
-    let arguments = &self.args;
-
-    quote!(
-      extern {
-        #(#comments)*
-        #[link_name=#symbol]
-        pub fn #name(#(#arguments),*) -> #ret;
-      }
-    ).to_tokens(tokens);
+// In strict provenance mode objects are constructed in place from
+// uninitialized memory, and pointers to `self` are taken without going
+// through another reference.
+fn self_ptr(strict: bool, constant: bool, class: &proc_macro2::Ident) -> TokenStream {
+  match (strict, constant) {
+    (true, true) => quote!(std::ptr::addr_of!(*self)),
+    (true, false) => quote!(std::ptr::addr_of_mut!(*self)),
+    (false, true) => quote!(self as *const #class),
+    (false, false) => quote!(self as *mut #class),
   }
 }
 
-#[derive(Debug, Clone)]
-struct Field(bool, String, TokenStream);
-
-impl ToTokens for Field {
-  fn to_tokens(&self, tokens: &mut TokenStream) {
-    let vis = if self.0 {
-      quote!(pub)
-    } else {
-      TokenStream::new()
-    };
-    let name = format_ident!("{}", self.1);
-    let ty = &self.2;
-    quote!(#vis #name: #ty).to_tokens(tokens);
+// Constructors build objects in place through `this.as_mut_ptr()`, in
+// zeroed memory rather than over a `Default`, which classes with a vtable
+// don't have.
+fn this_init(strict: bool, class: &proc_macro2::Ident) -> (TokenStream, TokenStream) {
+  if strict {
+    (quote!(std::mem::MaybeUninit::<#class>::uninit()), quote!(this.assume_init()))
+  } else {
+    (quote!(std::mem::MaybeUninit::<#class>::zeroed()), quote!(this.assume_init()))
   }
 }
 
+// A pointer parameter and the parameter giving how many elements it points
+// to, taken together as a slice.
 #[derive(Debug, Clone)]
-struct Constructor {
-  name: String,
-  symbol: String,
-  args: Vec<Arg>,
-  comments: Vec<String>,
+struct SliceParam {
+  pointer: usize,
+  length: usize,
+  elem: TokenStream,
+  mutable: bool,
 }
 
-impl ToTokens for Constructor {
-  fn to_tokens(&self, tokens: &mut TokenStream) {
-    let class = format_ident!("{}", self.name);
-    let symbol = &self.symbol;
+// How the glue shim a function is called through differs from calling it
+// directly. One that `catch`es exceptions returns whether it threw, and one
+// returning `indirect`ly takes where to write the result after the
+// arguments, for classes like `std::string` that can't be returned across
+// `extern "C"`. With `strings`, `std::string`s are taken as `&str` and
+// returned as `String`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Shim {
+  catch: bool,
+  indirect: bool,
+  strings: bool,
+}
 
-    let id = format_ident!("_{:x}", random::<u64>());
+// A wrapper passing storage for the output parameters in `outs`, given as
+// the index of the argument and the type it points to, and returning them
+// after the result. The pointer and length pairs in `slices` are taken as
+// one slice parameter. With an `error` value meaning success, the result is
+// returned as the error of a `Result` instead. What a `shim` catching
+// exceptions throws is returned as a `CxxException`. Gives the parameters
+// left, the return type and the body given how to call the function with
+// the arguments.
+fn out_wrapper(
+  args: &[Arg],
+  outs: &[(usize, TokenStream)],
+  slices: &[SliceParam],
+  ret: &TokenStream,
+  error: Option<&TokenStream>,
+  shim: Shim,
+  call: impl FnOnce(Vec<TokenStream>) -> TokenStream,
+) -> (Vec<TokenStream>, TokenStream, TokenStream) {
+  let names = (0..outs.len()).map(|i| format_ident!("out{}", i)).collect::<Vec<_>>();
 
-    let args = &self.args;
+  let inputs = args.iter().enumerate().filter_map(|(i, arg)| {
+    if outs.iter().any(|x| x.0 == i) || slices.iter().any(|x| x.length == i) {
+      return None;
+    }
+    match slices.iter().find(|x| x.pointer == i) {
+      Some(slice) => {
+        let name = format_ident!("{}", arg.0.as_ref().unwrap());
+        let elem = &slice.elem;
+        Some(if slice.mutable { quote!(#name: &mut [#elem]) } else { quote!(#name: &[#elem]) })
+      },
+      None if is_cxx_str(&arg.1) => {
+        let name = format_ident!("{}", arg.0.as_ref().unwrap());
+        Some(quote!(#name: &str))
+      },
+      None => Some(arg.to_token_stream()),
+    }
+  }).collect();
+  let void = is_void(ret);
+  let mut passed: Vec<_> = args.iter().enumerate().map(|(i, arg)| {
+    if let Some(out) = outs.iter().position(|x| x.0 == i) {
+      let name = &names[out];
+      return quote!(#name.as_mut_ptr());
+    }
+    if let Some(slice) = slices.iter().find(|x| x.length == i) {
+      let name = format_ident!("{}", args[slice.pointer].0.as_ref().unwrap());
+      return quote!(#name.len() as _);
+    }
 
-    let mut raw_args = vec![Arg(Some("this".to_string()), quote!(*mut #class))];
-    raw_args.extend(args.clone());
+    let name = format_ident!("{}", arg.0.as_ref().unwrap());
+    match slices.iter().find(|x| x.pointer == i) {
+      Some(slice) if slice.mutable => quote!(#name.as_mut_ptr() as _),
+      Some(_) => quote!(#name.as_ptr() as _),
+      None if is_cxx_str(&arg.1) => quote!(CxxStr::from(#name)),
+      None => quote!(#name),
+    }
+  }).collect();
+  if shim.indirect && !void {
+    passed.push(quote!(__ret.as_mut_ptr()));
+  }
+  if shim.catch {
+    passed.push(quote!(&mut __what));
+  }
+  let call = call(passed);
 
-    let mut arg_names = vec![quote!(&mut this as *mut #class)];
-    arg_names.extend(self.args.iter().map(|x| {
-      let ident = format_ident!("{}", x.0.as_ref().unwrap());
-      quote!(#ident)
-    }));
+  let mut types = Vec::new();
+  let mut values = Vec::new();
+  if !void && error.is_none() {
+    if shim.strings && is_cxx_string(ret) {
+      types.push(quote!(String));
+      values.push(quote!(String::from_utf8_lossy(ret.as_bytes()).into_owned()));
+    } else {
+      types.push(ret.clone());
+      values.push(quote!(ret));
+    }
+  }
+  for (name, (_, ty)) in names.iter().zip(outs) {
+    types.push(ty.clone());
+    values.push(quote!(#name.assume_init()));
+  }
+  let (ty, value) = if types.len() == 1 {
+    (types.remove(0), values.remove(0))
+  } else {
+    (quote!((#(#types),*)), quote!((#(#values),*)))
+  };
 
-    let comments = self.comments.iter().map(|x| TokenStream::from_str(&x).unwrap());
+  // Outputs are only read on success, C++ being free not to write them
+  // otherwise.
+  let (ty, result) = match error {
+    Some(ok) => (quote!(Result<#ty, #ret>), quote!(if ret == #ok { Ok(#value) } else { Err(ret) })),
+    None => (ty, value),
+  };
+  let (ty, result) = if shim.catch { (quote!(Result<#ty, CxxException>), quote!(Ok(#result))) } else { (ty, result) };
 
+  let types = outs.iter().map(|x| &x.1);
+  let call = if shim.catch {
     quote!(
-      extern {
-        #[link_name=#symbol]
-        fn #id(#(#raw_args),*);
-      }
-      impl #class {
-        #(#comments)*
-        pub unsafe fn new(#(#args),*) -> #class {
-          let mut this = #class::default();
-          #id(#(#arg_names),*);
-          this
-        }
+      let mut __what = std::ptr::null_mut();
+      if #call != 0 {
+        return Err(CxxException::from_raw(__what));
       }
-    ).to_tokens(tokens);
-  }
+    )
+  } else if void || shim.indirect {
+    quote!(#call;)
+  } else {
+    quote!(let ret = #call;)
+  };
+  let call = if shim.indirect && !void {
+    quote!(
+      let mut __ret = std::mem::MaybeUninit::<#ret>::uninit();
+      #call
+      let ret = __ret.assume_init();
+    )
+  } else {
+    call
+  };
+  let body = quote!(
+    #(let mut #names = std::mem::MaybeUninit::<#types>::uninit();)*
+    #call
+    #result
+  );
+  (inputs, ty, body)
 }
 
-#[derive(Debug, Clone)]
-struct Destructor {
-  name: String,
-  symbol: String,
-  comments: Vec<String>,
+fn is_void(ty: &TokenStream) -> bool {
+  ty.to_string() == quote!(std::os::raw::c_void).to_string()
 }
 
-impl ToTokens for Destructor {
-  fn to_tokens(&self, tokens: &mut TokenStream) {
-    let class = format_ident!("{}", self.name);
-    let symbol = &self.symbol;
-
-    let id = format_ident!("_{:x}", random::<u64>());
+fn is_cxx_str(ty: &TokenStream) -> bool {
+  ty.to_string() == quote!(CxxStr<'_>).to_string()
+}
 
-    let comments = self.comments.iter().map(|x| TokenStream::from_str(&x).unwrap());
+fn is_cxx_string(ty: &TokenStream) -> bool {
+  ty.to_string() == quote!(CxxString).to_string()
+}
 
-    quote!(
-      extern {
-        #[link_name=#symbol]
-        fn #id(_: *mut #class);
-      }
-      impl Drop for #class {
-        #(#comments)*
-        fn drop(&mut self) {
-          unsafe { #id(self as *mut #class); }
-        }
-      }
-    ).to_tokens(tokens);
+// The parameters and return type the symbol a `shim` is called through
+// takes: where to write the result and the message of what it threw after
+// the arguments, returning whether it did.
+fn extern_signature(args: &[Arg], ret: &TokenStream, shim: Shim) -> (Vec<TokenStream>, TokenStream) {
+  let mut params = args.iter().map(|x| x.to_token_stream()).collect::<Vec<_>>();
+  if shim.indirect && !is_void(ret) {
+    params.push(quote!(__ret: *mut #ret));
+  }
+  if shim.catch {
+    params.push(quote!(__what: *mut *mut std::os::raw::c_char));
+    (params, quote!(std::os::raw::c_int))
+  } else if shim.indirect {
+    (params, quote!(std::os::raw::c_void))
+  } else {
+    (params, ret.clone())
   }
 }
 
-#[derive(Debug, Clone)]
-struct Method {
-  class: String,
+#[derive(Debug)]
+struct ItemFn {
   name: String,
   symbol: String,
   args: Vec<Arg>,
   ret: TokenStream,
-  comments: Vec<String>,
+  abi: Option<String>,
+  safe: bool,
+  outs: Vec<(usize, TokenStream)>,
+  slices: Vec<SliceParam>,
+  error: Option<TokenStream>,
+  shim: Shim,
+  preconditions: Vec<TokenStream>,
+  fuzz: Option<Vec<FuzzArg>>,
+  // The arguments taking `const wchar_t*`, also taken as `OsStr`s by a
+  // wrapper suffixed `_os`.
+  wide: Vec<usize>,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+impl ItemFn {
+  // The types of the Rust function's parameters and its result.
+  fn signature_types(&self) -> TokenStream {
+    if !self.outs.is_empty() || !self.slices.is_empty() || self.error.is_some() || self.shim != Shim::default() {
+      let (inputs, outputs, _) = out_wrapper(&self.args, &self.outs, &self.slices, &self.ret, self.error.as_ref(), self.shim, |_| quote!());
+      quote!(#(#inputs),* -> #outputs)
+    } else {
+      let args = &self.args;
+      let ret = &self.ret;
+      quote!(#(#args),* -> #ret)
+    }
+  }
 }
 
-impl ToTokens for Method {
+impl ToTokens for ItemFn {
   fn to_tokens(&self, tokens: &mut TokenStream) {
     let name = format_ident!("{}", self.name);
-    let class = format_ident!("{}", self.class);
     let symbol = &self.symbol;
     let ret = &self.ret;
 
-    let id = format_ident!("_{:x}", random::<u64>());
+    if !self.wide.is_empty() {
+      let wrapper = format_ident!("{}_os", self.name);
+      let inputs = self.args.iter().enumerate().map(|(i, x)| {
+        let name = format_ident!("{}", x.0.as_ref().unwrap());
+        if self.wide.contains(&i) {
+          quote!(#name: impl AsRef<std::ffi::OsStr>)
+        } else {
+          x.to_token_stream()
+        }
+      });
+      let conversions = self.wide.iter().map(|&i| {
+        let name = format_ident!("{}", self.args[i].0.as_ref().unwrap());
+        quote!(let #name = WideCString::new(#name)?;)
+      });
+      let arg_names = self.args.iter().enumerate().map(|(i, x)| {
+        let name = format_ident!("{}", x.0.as_ref().unwrap());
+        if self.wide.contains(&i) { quote!(#name.as_ptr()) } else { quote!(#name) }
+      });
+      let unsafety = if self.safe { None } else { Some(quote!(unsafe)) };
+      quote!(
+        pub #unsafety fn #wrapper(#(#inputs),*) -> Result<#ret, InteriorNul> {
+          #(#conversions)*
+          Ok(#name(#(#arg_names),*))
+        }
+      ).to_tokens(tokens);
+    }
 
-    let mut args = vec![Arg(None, quote!(&mut self))];
-    args.extend(self.args.clone());
+    let abi = self.abi.as_deref().map(Literal::string);
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+    let arguments = &self.args;
+    let checks = &self.preconditions;
 
-    let mut raw_args = vec![Arg(Some("this".to_string()), quote!(*mut #class))];
-    raw_args.extend(self.args.clone());
+    if !self.outs.is_empty() || !self.slices.is_empty() || self.error.is_some() || self.shim != Shim::default() {
+      let id = extern_ident(symbol);
+      let (inputs, outputs, body) = out_wrapper(&self.args, &self.outs, &self.slices, ret, self.error.as_ref(), self.shim, |args| quote!(#id(#(#args),*)));
+      let body = if self.safe {
+        quote!(pub fn #name(#(#inputs),*) -> #outputs { #(debug_assert!(#checks);)* unsafe { #body } })
+      } else {
+        quote!(pub unsafe fn #name(#(#inputs),*) -> #outputs { #(debug_assert!(#checks);)* #body })
+      };
 
-    let mut arg_names = vec![quote!(self as *mut #class)];
-    arg_names.extend(self.args.iter().map(|x| {
-      let ident = format_ident!("{}", x.0.as_ref().unwrap());
-      quote!(#ident)
-    }));
+      let (params, ret) = extern_signature(&self.args, ret, self.shim);
+      quote!(
+        extern #abi {
+          #[link_name=#symbol]
+          fn #id(#(#params),*) -> #ret;
+        }
 
-    let comments = self.comments.iter().map(|x| TokenStream::from_str(&x).unwrap());
+        #(#comments)*
+        #body
+      ).to_tokens(tokens);
+    } else if self.safe || !checks.is_empty() {
+      let id = extern_ident(symbol);
+      let arg_names = self.args.iter().map(|x| format_ident!("{}", x.0.as_ref().unwrap()));
+      let body = if self.safe {
+        quote!(pub fn #name(#(#arguments),*) -> #ret { #(debug_assert!(#checks);)* unsafe { #id(#(#arg_names),*) } })
+      } else {
+        quote!(pub unsafe fn #name(#(#arguments),*) -> #ret { #(debug_assert!(#checks);)* #id(#(#arg_names),*) })
+      };
+
+      quote!(
+        extern #abi {
+          #[link_name=#symbol]
+          fn #id(#(#arguments),*) -> #ret;
+        }
 
-    quote!(
-      extern {
-        #[link_name=#symbol]
-        fn #id(#(#raw_args),*) -> #ret;
-      }
-      impl #class {
         #(#comments)*
-        pub unsafe fn #name(#(#args),*) -> #ret {
-          #id(#(#arg_names),*)
+        #body
+      ).to_tokens(tokens);
+    } else {
+      quote!(
+        extern #abi {
+          #(#comments)*
+          #[link_name=#symbol]
+          pub fn #name(#(#arguments),*) -> #ret;
         }
-      }
-    ).to_tokens(tokens);
+      ).to_tokens(tokens);
+    }
   }
 }
 
-#[derive(Debug, Clone)]
-struct StaticMethod {
-  class: String,
+#[derive(Debug)]
+struct Enumerator {
   name: String,
-  symbol: String,
-  args: Vec<Arg>,
-  ret: TokenStream,
-  comments: Vec<String>,
+  value: TokenStream,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+#[derive(Debug)]
+struct ItemEnum {
+  name: Option<String>,
+  style: EnumStyle,
+  repr: TokenStream,
+  variants: Vec<Enumerator>,
+  comments: Vec<std::rc::Rc<str>>,
 }
 
-impl ToTokens for StaticMethod {
+impl ToTokens for ItemEnum {
   fn to_tokens(&self, tokens: &mut TokenStream) {
-    let name = format_ident!("{}", self.name);
-    let class = format_ident!("{}", self.class);
-    let id = format_ident!("_{:x}", random::<u64>());
-    let symbol = &self.symbol;
-    let ret = &self.ret;
+    let repr = &self.repr;
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
 
-    let comments = self.comments.iter().map(|x| TokenStream::from_str(&x).unwrap());
-    let arguments = &self.args;
-    let arg_names = self.args.iter().map(|x| x.0.as_ref().unwrap());
+    let name = if let Some(name) = &self.name {
+      format_ident!("{}", name)
+    } else {
+      // Anonymous enums only exist for their constants.
+      let variants = self.variants.iter().map(|x| {
+        let name = format_ident!("{}", x.name);
+        let value = &x.value;
+        let comments = x.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+        quote!(#(#comments)* pub const #name: #repr = #value;)
+      });
 
-    quote!(
-      extern {
-        #[link_name=#symbol]
-        fn #id(#(#arguments),*) -> #ret;
-      }
+      quote!(#(#variants)*).to_tokens(tokens);
+      return;
+    };
 
-      impl #class {
-        #(#comments)*
-        pub unsafe fn #name(#(#arguments),*) -> #ret {
-          #id(#(#arg_names),*)
+    let consts = |ty: TokenStream, wrap: &dyn Fn(&TokenStream) -> TokenStream| {
+      self.variants.iter().map(|x| {
+        let name = format_ident!("{}", x.name);
+        let value = wrap(&x.value);
+        let comments = x.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+        quote!(#(#comments)* pub const #name: #ty = #value;)
+      }).collect::<Vec<_>>()
+    };
+
+    match self.style {
+      EnumStyle::Rust => {
+        let mut seen = Vec::new();
+        let mut variants = Vec::new();
+        let mut aliases = Vec::new();
+        for x in &self.variants {
+          let variant = format_ident!("{}", x.name);
+          let value = &x.value;
+          let comments = x.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+          // Rust enums can't have two variants with the same discriminant,
+          // so repeated values become associated constants.
+          let value_str = value.to_string();
+          if let Some((_, original)) = seen.iter().find(|(x, _)| *x == value_str) {
+            aliases.push(quote!(#(#comments)* pub const #variant: #name = #name::#original;));
+          } else {
+            seen.push((value_str, variant.clone()));
+            variants.push(quote!(#(#comments)* #variant = #value));
+          }
         }
-      }
-    ).to_tokens(tokens);
+
+        quote!(
+          #(#comments)*
+          #[repr(#repr)]
+          #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+          pub enum #name {
+            #(#variants),*
+          }
+
+          impl #name {
+            #(#aliases)*
+          }
+        ).to_tokens(tokens);
+      },
+
+      EnumStyle::NewType | EnumStyle::Bitflags => {
+        let consts = consts(quote!(#name), &|x| quote!(#name(#x)));
+
+        quote!(
+          #(#comments)*
+          #[repr(transparent)]
+          #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+          pub struct #name(pub #repr);
+
+          impl #name {
+            #(#consts)*
+          }
+        ).to_tokens(tokens);
+
+        if self.style == EnumStyle::Bitflags {
+          quote!(
+            impl std::ops::BitOr for #name {
+              type Output = #name;
+
+              fn bitor(self, other: #name) -> #name {
+                #name(self.0 | other.0)
+              }
+            }
+
+            impl std::ops::BitOrAssign for #name {
+              fn bitor_assign(&mut self, other: #name) {
+                self.0 |= other.0;
+              }
+            }
+
+            impl std::ops::BitAnd for #name {
+              type Output = #name;
+
+              fn bitand(self, other: #name) -> #name {
+                #name(self.0 & other.0)
+              }
+            }
+
+            impl std::ops::BitAndAssign for #name {
+              fn bitand_assign(&mut self, other: #name) {
+                self.0 &= other.0;
+              }
+            }
+          ).to_tokens(tokens);
+        }
+      },
+
+      EnumStyle::ModuleConsts => {
+        let consts = consts(quote!(Type), &|x| x.clone());
+
+        quote!(
+          #(#comments)*
+          #[allow(non_snake_case)]
+          pub mod #name {
+            pub type Type = #repr;
+            #(#consts)*
+          }
+        ).to_tokens(tokens);
+      },
+    }
   }
 }
 
 #[derive(Debug)]
-struct ItemStruct {
+struct ItemOpaque {
   name: String,
-  fields: Vec<Field>,
-  constructor: Option<Constructor>,
-  destructor: Option<Destructor>,
-  methods: Vec<Method>,
-  static_methods: Vec<StaticMethod>,
-  comments: Vec<String>,
+  size: usize,
+  align: usize,
+  comments: Vec<std::rc::Rc<str>>,
 }
 
-impl ToTokens for ItemStruct {
+impl ToTokens for ItemOpaque {
   fn to_tokens(&self, tokens: &mut TokenStream) {
     let name = format_ident!("{}", self.name);
-    let fields = self.fields.iter().map(|x| x.to_token_stream());
-    let methods = self.methods.iter().map(|x| x.to_token_stream());
-    let static_methods = self.static_methods.iter().map(|x| x.to_token_stream());
-
-    let comments = self.comments.iter().map(|x| TokenStream::from_str(&x).unwrap());
-
-    let constructor = if let Some(constructor) = &self.constructor {
-      constructor.to_token_stream()
-    } else {
-      TokenStream::new()
-    };
+    let size = Literal::usize_unsuffixed(self.size);
+    let align = Literal::usize_unsuffixed(self.align);
+    let debug = format!("{} {{ .. }}", self.name);
 
-    let destructor = if let Some(destructor) = &self.destructor {
-      destructor.to_token_stream()
-    } else {
-      TokenStream::new()
-    };
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
 
     quote!(
       #(#comments)*
-      #[repr(C)]
-      #[derive(Default, Debug)]
+      #[repr(C, align(#align))]
       pub struct #name {
-        #(#fields),*
+        _opaque: [u8; #size],
       }
 
-      #constructor
-      #destructor
+      impl Default for #name {
+        fn default() -> #name {
+          unsafe { std::mem::zeroed() }
+        }
+      }
 
-      #(#methods)*
-      #(#static_methods)*
+      impl std::fmt::Debug for #name {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          f.write_str(#debug)
+        }
+      }
     ).to_tokens(tokens);
   }
 }
 
-#[derive(Debug)]
-struct State {
-  glue: String,
+// The owner of objects methods are annotated to return ownership of, each
+// class implementing `CxxDelete` by its own `delete` in the glue.
+#[derive(Debug, Clone)]
+struct ItemCxxBox {
+  elements: Vec<BoxElement>,
 }
 
 #[derive(Debug, Clone)]
-struct Context {
-  ns: Vec<String>,
+struct BoxElement {
+  elem: TokenStream,
+  cpp: String,
+  delete_symbol: String,
 }
 
-impl State {
-  fn process_children(&mut self, e: Entity, c: &Context) -> Vec<Item> {
-    let mut items = Vec::new();
-    for child in e.get_children() {
-      items.extend(self.process_entity(child, c))
-    }
-    items
-  }
-
-  fn process_entity(&mut self, e: Entity, c: &Context) -> Vec<Item> {
-    match e.get_kind() {
-      EntityKind::TranslationUnit => {
-        self.process_children(e, c)
-      },
+impl ToTokens for ItemCxxBox {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    quote!(
+      /// An object C++ allocated with `new`, deleted when dropped.
+      pub struct CxxBox<T: CxxDelete>(std::ptr::NonNull<T>);
 
-      EntityKind::Namespace => {
-        let mut c = c.clone();
-        c.ns.push(e.get_name().unwrap());
-        vec![Item::Mod(ItemMod {
-          name: e.get_name().unwrap(),
-          items: self.process_children(e, &c),
-          comments: e.get_comment().map_or(Vec::new(), |x| x.split("\n").map(|x| x.to_string()).collect()),
-        })]
+      pub unsafe trait CxxDelete {
+        #[doc(hidden)]
+        unsafe fn __delete(this: *mut Self);
       }
 
-      EntityKind::FunctionDecl => {
-        let mut symbol = if cfg!(unix) {
-          e.get_mangled_name().unwrap()[1..].to_string()
-        } else {
-          e.get_mangled_name().unwrap()
-        };
+      impl<T: CxxDelete> CxxBox<T> {
+        /// Takes ownership of an object allocated with `new`, or returns
+        /// `None` for null.
+        pub unsafe fn from_raw(ptr: *mut T) -> Option<CxxBox<T>> {
+          std::ptr::NonNull::new(ptr).map(CxxBox)
+        }
 
-        if e.is_inline_function() {
-          symbol = format!("_{:x}", random::<u64>());
-          self.glue += &format!(
-            "extern \"C\" {{ {ret} {temp}({args}) {{ return {name}({arg_names}); }} }}",
-            ret=e.get_result_type().unwrap().get_display_name(),
-            temp=symbol,
-            name=e.get_name().unwrap(),
-            args=e.get_arguments().unwrap().iter().map(|arg| {
-              format!("{} {}", arg.get_type().unwrap().get_display_name().to_string(), arg.get_display_name().unwrap())
-            }).collect::<Vec<_>>().join(", "),
-            arg_names=e.get_arguments().unwrap().iter().map(|arg| {
-              arg.get_display_name().unwrap()
-            }).collect::<Vec<_>>().join(", "),
-          );
+        /// Gives up ownership of the object, for C++ to delete.
+        pub fn into_raw(self) -> *mut T {
+          let ptr = self.0.as_ptr();
+          std::mem::forget(self);
+          ptr
         }
 
-        vec![Item::Fn(ItemFn {
-          name: e.get_name().unwrap(),
-          symbol: symbol,
-          ret: e.get_result_type().unwrap().to_token_stream(),
-          comments: e.get_comment().map_or(Vec::new(), |x| x.split("\n").map(|x| x.to_string()).collect()),
-          args: e.get_arguments().unwrap().iter().enumerate().map(|(i, arg)| {
-            Arg(Some(arg.get_display_name().unwrap_or(format!("a{}", i))), arg.get_type().unwrap().to_token_stream())
-          }).collect(),
-        })]
-      },
+        pub fn as_ptr(&self) -> *mut T {
+          self.0.as_ptr()
+        }
+      }
 
-      EntityKind::ClassDecl => {
-        let mut strukt = ItemStruct {
-          name: e.get_name().unwrap(),
-          comments: e.get_comment().map_or(Vec::new(), |x| x.split("\n").map(|x| x.to_string()).collect()),
-          fields: Vec::new(),
-          methods: Vec::new(),
-          static_methods: Vec::new(),
-          constructor: None,
-          destructor: None,
-        };
-    
-        for child in e.get_children() {
-          println!("{:#?}", child);
+      impl<T: CxxDelete> std::ops::Deref for CxxBox<T> {
+        type Target = T;
 
-          match child.get_kind() {
-            EntityKind::FieldDecl => {
-              println!("{:#?}", child.get_accessibility());
-              strukt.fields.push(Field(child.get_accessibility().unwrap() == Accessibility::Public, child.get_name().unwrap(), child.get_type().unwrap().to_token_stream()));
-            },
+        fn deref(&self) -> &T {
+          unsafe { self.0.as_ref() }
+        }
+      }
 
-            EntityKind::Constructor => {
-              let symbol = if cfg!(unix) {
-                child.get_mangled_name().unwrap()[1..].to_string()
-              } else {
-                child.get_mangled_name().unwrap()
-              };
+      impl<T: CxxDelete> std::ops::DerefMut for CxxBox<T> {
+        fn deref_mut(&mut self) -> &mut T {
+          unsafe { self.0.as_mut() }
+        }
+      }
 
-              strukt.constructor = Some(Constructor {
-                name: child.get_name().unwrap(),
-                symbol,
-                args: child.get_arguments().unwrap().iter().enumerate().map(|(i, arg)| {
-                  Arg(Some(arg.get_display_name().unwrap_or(format!("a{}", i))), arg.get_type().unwrap().to_token_stream())
-                }).collect(),
-                comments: child.get_comment().map_or(Vec::new(), |x| x.split("\n").map(|x| x.to_string()).collect()),
-              });
-            },
+      impl<T: CxxDelete> Drop for CxxBox<T> {
+        fn drop(&mut self) {
+          unsafe { T::__delete(self.0.as_ptr()) }
+        }
+      }
 
-            EntityKind::Destructor => {
-              let symbol = if cfg!(unix) {
-                child.get_mangled_names().unwrap()[0][1..].to_string()
-              } else {
-                child.get_mangled_names().unwrap()[0].clone()
-              };
+      impl<T: CxxDelete + std::fmt::Debug> std::fmt::Debug for CxxBox<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          (**self).fmt(f)
+        }
+      }
+    ).to_tokens(tokens);
 
-              strukt.destructor = Some(Destructor {
-                name: e.get_name().unwrap(),
-                symbol,
-                comments: child.get_comment().map_or(Vec::new(), |x| x.split("\n").map(|x| x.to_string()).collect()),
-              });
-            },
+    for element in &self.elements {
+      let elem = &element.elem;
+      let delete_symbol = &element.delete_symbol;
+      quote!(
+        unsafe impl CxxDelete for #elem {
+          unsafe fn __delete(this: *mut Self) {
+            extern "C" {
+              #[link_name=#delete_symbol]
+              fn delete(this: *mut #elem);
+            }
+            delete(this)
+          }
+        }
+      ).to_tokens(tokens);
+    }
+  }
+}
 
-            EntityKind::Method => {
-              let symbol = if cfg!(unix) {
-                child.get_mangled_name().unwrap()[1..].to_string()
-              } else {
-                child.get_mangled_name().unwrap()
-              };
+// The closures passed to C++ as `std::function`s, converted from each of
+// the signatures they're taken with by trampolines the glue calls with the
+// boxed closure.
+#[derive(Debug, Clone)]
+struct ItemCxxFunction {
+  signatures: Vec<FunctionSignature>,
+}
 
-              if child.is_static_method() {
-                strukt.static_methods.push(StaticMethod {
-                  class: e.get_name().unwrap(),
-                  name: child.get_name().unwrap(),
-                  symbol,
-                  args: child.get_arguments().unwrap().iter().enumerate().map(|(i, arg)| {
-                    Arg(Some(arg.get_display_name().unwrap_or(format!("a{}", i))), arg.get_type().unwrap().to_token_stream())
-                  }).collect(),
-                  ret: child.get_result_type().unwrap().to_token_stream(),
-                  comments: child.get_comment().map_or(Vec::new(), |x| x.split("\n").map(|x| x.to_string()).collect()),
-                });
-              } else {
-                strukt.methods.push(Method {
-                  class: e.get_name().unwrap(),
-                  name: child.get_name().unwrap(),
-                  symbol,
-                  args: child.get_arguments().unwrap().iter().enumerate().map(|(i, arg)| {
-                    Arg(Some(arg.get_display_name().unwrap_or(format!("a{}", i))), arg.get_type().unwrap().to_token_stream())
-                  }).collect(),
-                  ret: child.get_result_type().unwrap().to_token_stream(),
-                  comments: child.get_comment().map_or(Vec::new(), |x| x.split("\n").map(|x| x.to_string()).collect()),
-                });
-              }
-            },
+#[derive(Debug, Clone)]
+struct FunctionSignature {
+  args: Vec<TokenStream>,
+  ret: TokenStream,
+}
 
-            _ => {},
+impl FunctionSignature {
+  fn ty(&self) -> TokenStream {
+    let args = &self.args;
+    let ret = &self.ret;
+    quote!(CxxFunction<dyn Fn(#(#args),*) -> #ret>)
+  }
+}
+
+impl ToTokens for ItemCxxFunction {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    quote!(
+      /// A closure to pass to C++ as a `std::function`, converted with
+      /// `CxxFunction::from`. C++ owns it once it's passed, dropping it
+      /// along with the last copy of the `std::function`, and may call it
+      /// from any thread it calls the `std::function` on.
+      #[repr(C)]
+      pub struct CxxFunction<F: ?Sized> {
+        data: *mut std::os::raw::c_void,
+        call: *const std::os::raw::c_void,
+        drop: unsafe extern "C" fn(*mut std::os::raw::c_void),
+        _marker: std::marker::PhantomData<Box<F>>,
+      }
+
+      impl<F: ?Sized> Drop for CxxFunction<F> {
+        fn drop(&mut self) {
+          unsafe { (self.drop)(self.data) }
+        }
+      }
+
+      impl<F: ?Sized> std::fmt::Debug for CxxFunction<F> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          f.write_str("CxxFunction")
+        }
+      }
+    ).to_tokens(tokens);
+
+    for signature in &self.signatures {
+      let ty = signature.ty();
+      let args = &signature.args;
+      let ret = &signature.ret;
+      let names = (0..args.len()).map(|i| format_ident!("a{}", i)).collect::<Vec<_>>();
+      quote!(
+        impl<F: Fn(#(#args),*) -> #ret + 'static> From<F> for #ty {
+          fn from(f: F) -> #ty {
+            unsafe extern "C" fn call<F: Fn(#(#args),*) -> #ret>(data: *mut std::os::raw::c_void, #(#names: #args),*) -> #ret {
+              (*(data as *const F))(#(#names),*)
+            }
+            unsafe extern "C" fn drop<F>(data: *mut std::os::raw::c_void) {
+              std::mem::drop(Box::from_raw(data as *mut F))
+            }
+            CxxFunction {
+              data: Box::into_raw(Box::new(f)) as *mut std::os::raw::c_void,
+              call: call::<F> as *const std::os::raw::c_void,
+              drop: drop::<F>,
+              _marker: std::marker::PhantomData,
+            }
+          }
+        }
+      ).to_tokens(tokens);
+    }
+  }
+}
+
+// What the shims catching exceptions return when C++ throws.
+#[derive(Debug)]
+struct ItemException;
+
+impl ToTokens for ItemException {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    quote!(
+      /// An exception thrown by C++, with the message of `std::exception::what()`.
+      #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+      pub struct CxxException {
+        pub message: String,
+      }
+
+      impl CxxException {
+        // Takes the message the glue copied out of the exception.
+        #[doc(hidden)]
+        pub unsafe fn from_raw(what: *mut std::os::raw::c_char) -> CxxException {
+          extern "C" {
+            fn free(ptr: *mut std::os::raw::c_void);
+          }
+
+          if what.is_null() {
+            return CxxException { message: String::new() };
           }
+          let message = std::ffi::CStr::from_ptr(what).to_string_lossy().into_owned();
+          free(what as *mut std::os::raw::c_void);
+          CxxException { message }
         }
+      }
 
-        vec![Item::Struct(strukt)]
+      impl std::fmt::Display for CxxException {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          f.write_str(&self.message)
+        }
       }
 
-      _ => Vec::new(),
+      impl std::error::Error for CxxException {}
+    ).to_tokens(tokens);
+  }
+}
+
+// `std::string`, opaque and read through the glue, along with the borrowed
+// strings passed to the glue to be copied into one.
+#[derive(Debug, Clone)]
+struct ItemCxxString {
+  size: usize,
+  align: usize,
+  data_symbol: String,
+  len_symbol: String,
+  new_symbol: String,
+  drop_symbol: String,
+}
+
+impl ToTokens for ItemCxxString {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let size = Literal::usize_unsuffixed(self.size);
+    let align = Literal::usize_unsuffixed(self.align);
+    let data_symbol = &self.data_symbol;
+    let len_symbol = &self.len_symbol;
+    let new_symbol = &self.new_symbol;
+    let drop_symbol = &self.drop_symbol;
+
+    let data_id = extern_ident(data_symbol);
+    let len_id = extern_ident(len_symbol);
+    let new_id = extern_ident(new_symbol);
+    let drop_id = extern_ident(drop_symbol);
+
+    quote!(
+      #[repr(C, align(#align))]
+      pub struct CxxString {
+        _opaque: [u8; #size],
+      }
+
+      extern "C" {
+        #[link_name=#data_symbol]
+        fn #data_id(this: *const CxxString) -> *const u8;
+        #[link_name=#len_symbol]
+        fn #len_id(this: *const CxxString) -> usize;
+        #[link_name=#new_symbol]
+        fn #new_id(this: *mut CxxString);
+        #[link_name=#drop_symbol]
+        fn #drop_id(this: *mut CxxString);
+      }
+
+      impl CxxString {
+        pub fn len(&self) -> usize {
+          unsafe { #len_id(self) }
+        }
+
+        pub fn is_empty(&self) -> bool {
+          self.len() == 0
+        }
+
+        pub fn as_bytes(&self) -> &[u8] {
+          unsafe { std::slice::from_raw_parts(#data_id(self), self.len()) }
+        }
+
+        pub fn to_str(&self) -> Result<&str, std::str::Utf8Error> {
+          std::str::from_utf8(self.as_bytes())
+        }
+      }
+
+      impl Default for CxxString {
+        fn default() -> CxxString {
+          unsafe {
+            let mut x = std::mem::MaybeUninit::uninit();
+            #new_id(x.as_mut_ptr());
+            x.assume_init()
+          }
+        }
+      }
+
+      impl Drop for CxxString {
+        fn drop(&mut self) {
+          unsafe { #drop_id(self) }
+        }
+      }
+
+      impl std::fmt::Debug for CxxString {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          std::fmt::Debug::fmt(&String::from_utf8_lossy(self.as_bytes()), f)
+        }
+      }
+
+      #[repr(C)]
+      #[derive(Debug, Clone, Copy)]
+      pub struct CxxStr<'a> {
+        data: *const u8,
+        len: usize,
+        _marker: std::marker::PhantomData<&'a str>,
+      }
+
+      impl<'a> From<&'a str> for CxxStr<'a> {
+        fn from(x: &'a str) -> CxxStr<'a> {
+          CxxStr { data: x.as_ptr(), len: x.len(), _marker: std::marker::PhantomData }
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+// `std::vector`, opaque and generic over the element types it's
+// instantiated with, each implementing `VectorElement` by its own accessors
+// in the glue.
+#[derive(Debug, Clone)]
+struct ItemCxxVector {
+  size: usize,
+  align: usize,
+  elements: Vec<VectorElement>,
+}
+
+#[derive(Debug, Clone)]
+struct VectorElement {
+  elem: TokenStream,
+  cpp: String,
+  len_symbol: String,
+  data_symbol: String,
+  push_symbol: String,
+  new_symbol: String,
+  drop_symbol: String,
+}
+
+impl ToTokens for ItemCxxVector {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let size = Literal::usize_unsuffixed(self.size);
+    let align = Literal::usize_unsuffixed(self.align);
+
+    quote!(
+      #[repr(C, align(#align))]
+      pub struct CxxVector<T: VectorElement> {
+        _opaque: [u8; #size],
+        _marker: std::marker::PhantomData<T>,
+      }
+
+      pub unsafe trait VectorElement: Sized {
+        #[doc(hidden)]
+        unsafe fn __len(this: *const CxxVector<Self>) -> usize;
+        #[doc(hidden)]
+        unsafe fn __data(this: *const CxxVector<Self>) -> *const Self;
+        #[doc(hidden)]
+        unsafe fn __push_back(this: *mut CxxVector<Self>, value: *const Self);
+        #[doc(hidden)]
+        unsafe fn __new(this: *mut CxxVector<Self>);
+        #[doc(hidden)]
+        unsafe fn __drop(this: *mut CxxVector<Self>);
+      }
+
+      impl<T: VectorElement> CxxVector<T> {
+        pub fn len(&self) -> usize {
+          unsafe { T::__len(self) }
+        }
+
+        pub fn is_empty(&self) -> bool {
+          self.len() == 0
+        }
+
+        pub fn get(&self, i: usize) -> Option<&T> {
+          self.as_slice().get(i)
+        }
+
+        /// Appends a copy of `value`, made by its copy constructor.
+        pub fn push_back(&mut self, value: T) {
+          unsafe { T::__push_back(self, &value) }
+        }
+
+        pub fn as_slice(&self) -> &[T] {
+          match self.len() {
+            0 => &[],
+            len => unsafe { std::slice::from_raw_parts(T::__data(self), len) },
+          }
+        }
+      }
+
+      impl<T: VectorElement> Default for CxxVector<T> {
+        fn default() -> CxxVector<T> {
+          unsafe {
+            let mut x = std::mem::MaybeUninit::uninit();
+            T::__new(x.as_mut_ptr());
+            x.assume_init()
+          }
+        }
+      }
+
+      impl<T: VectorElement> Drop for CxxVector<T> {
+        fn drop(&mut self) {
+          unsafe { T::__drop(self) }
+        }
+      }
+
+      impl<T: VectorElement + std::fmt::Debug> std::fmt::Debug for CxxVector<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          f.debug_list().entries(self.as_slice()).finish()
+        }
+      }
+    ).to_tokens(tokens);
+
+    for element in &self.elements {
+      let elem = &element.elem;
+      let len_symbol = &element.len_symbol;
+      let data_symbol = &element.data_symbol;
+      let push_symbol = &element.push_symbol;
+      let new_symbol = &element.new_symbol;
+      let drop_symbol = &element.drop_symbol;
+
+      quote!(
+        unsafe impl VectorElement for #elem {
+          unsafe fn __len(this: *const CxxVector<Self>) -> usize {
+            extern "C" {
+              #[link_name=#len_symbol]
+              fn len(this: *const CxxVector<#elem>) -> usize;
+            }
+            len(this)
+          }
+
+          unsafe fn __data(this: *const CxxVector<Self>) -> *const Self {
+            extern "C" {
+              #[link_name=#data_symbol]
+              fn data(this: *const CxxVector<#elem>) -> *const #elem;
+            }
+            data(this)
+          }
+
+          unsafe fn __push_back(this: *mut CxxVector<Self>, value: *const Self) {
+            extern "C" {
+              #[link_name=#push_symbol]
+              fn push_back(this: *mut CxxVector<#elem>, value: *const #elem);
+            }
+            push_back(this, value)
+          }
+
+          unsafe fn __new(this: *mut CxxVector<Self>) {
+            extern "C" {
+              #[link_name=#new_symbol]
+              fn new(this: *mut CxxVector<#elem>);
+            }
+            new(this)
+          }
+
+          unsafe fn __drop(this: *mut CxxVector<Self>) {
+            extern "C" {
+              #[link_name=#drop_symbol]
+              fn drop(this: *mut CxxVector<#elem>);
+            }
+            drop(this)
+          }
+        }
+      ).to_tokens(tokens);
     }
   }
 }
 
-pub fn generate<P: Into<PathBuf>>(path: P) {
-  let path = path.into();
+// The `symbols` module, translating between the C++ names and USRs of the
+// bound items and their Rust paths and link names.
+#[derive(Debug)]
+struct ItemSymbols {
+  entries: Vec<SymbolEntry>,
+}
+
+#[derive(Debug)]
+struct SymbolEntry {
+  name: String,
+  usr: String,
+  path: String,
+  link_name: Option<String>,
+}
+
+impl ToTokens for ItemSymbols {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let entries = self.entries.iter().map(|x| {
+      let name = &x.name;
+      let usr = &x.usr;
+      let path = &x.path;
+      let link_name = match &x.link_name {
+        Some(x) => quote!(Some(#x)),
+        None => quote!(None),
+      };
+      quote!(Symbol { name: #name, usr: #usr, path: #path, link_name: #link_name })
+    });
 
-  let clang = Clang::new().unwrap();
+    quote!(
+      pub mod symbols {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Symbol {
+          pub name: &'static str,
+          pub usr: &'static str,
+          pub path: &'static str,
+          pub link_name: Option<&'static str>,
+        }
 
-  let index = Index::new(&clang, false, false);
+        pub static SYMBOLS: &[Symbol] = &[#(#entries),*];
 
-  let args = vec!["-std=c++11"];
-  let tu = index.parser(&path).arguments(&args).parse().unwrap();
-  let entity = tu.get_entity();
+        pub fn by_usr(usr: &str) -> Option<&'static Symbol> {
+          SYMBOLS.iter().find(|x| x.usr == usr)
+        }
 
-  let mut state = State {
-    glue: String::new(),
-  };
+        /// Overloads share their name, so there may be more than one.
+        pub fn by_name<'a>(name: &'a str) -> impl Iterator<Item = &'static Symbol> + 'a {
+          SYMBOLS.iter().filter(move |x| x.name == name)
+        }
 
-  let items = state.process_entity(entity, &Context {
-    ns: Vec::new(),
-  });
+        pub fn by_path(path: &str) -> Option<&'static Symbol> {
+          SYMBOLS.iter().find(|x| x.path == path)
+        }
 
-  let mut tokens = TokenStream::new();
-  for item in items {
-    item.to_tokens(&mut tokens);
+        pub fn by_link_name(link_name: &str) -> Option<&'static Symbol> {
+          SYMBOLS.iter().find(|x| x.link_name == Some(link_name))
+        }
+      }
+    ).to_tokens(tokens);
   }
+}
+
+// A nul terminated `wchar_t` string, converted from an `OsStr` as UTF-16
+// where `wchar_t` is 16 bits and UTF-32 elsewhere.
+#[derive(Debug)]
+struct ItemWide {
+  ty: TokenStream,
+}
+
+impl ToTokens for ItemWide {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let ty = &self.ty;
+
+    quote!(
+      pub type WChar = #ty;
+
+      #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+      pub struct WideCString(Vec<WChar>);
+
+      /// The position of a nul in a string passed as a `wchar_t*`, which
+      /// would cut it short.
+      #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+      pub struct InteriorNul(pub usize);
+
+      impl std::fmt::Display for InteriorNul {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          write!(f, "nul at position {} of a wide string", self.0)
+        }
+      }
 
-  let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+      impl std::error::Error for InteriorNul {}
 
-  let mut f = fs::File::create(out_dir.join("bindings.rs")).unwrap();
-  f.write_fmt(format_args!("{}", tokens)).unwrap();
+      impl WideCString {
+        pub fn new<S: AsRef<std::ffi::OsStr>>(s: S) -> Result<WideCString, InteriorNul> {
+          #[cfg(windows)]
+          fn utf16(s: &std::ffi::OsStr) -> Vec<u16> {
+            std::os::windows::ffi::OsStrExt::encode_wide(s).collect()
+          }
+          #[cfg(not(windows))]
+          fn utf16(s: &std::ffi::OsStr) -> Vec<u16> {
+            s.to_string_lossy().encode_utf16().collect()
+          }
 
-  let mut f = fs::File::create(out_dir.join("glue.cc")).unwrap();
-  f.write_fmt(format_args!("#include \"{}\"\n", path.to_str().unwrap().to_string().replace("\\", "\\\\"))).unwrap();
-  f.write_fmt(format_args!("{}", state.glue)).unwrap();
-  drop(f);
+          let s = s.as_ref();
+          let mut units: Vec<WChar> = if std::mem::size_of::<WChar>() == 2 {
+            utf16(s).into_iter().map(|x| x as WChar).collect()
+          } else {
+            s.to_string_lossy().chars().map(|x| x as WChar).collect()
+          };
+          if let Some(i) = units.iter().position(|x| *x == 0) {
+            return Err(InteriorNul(i));
+          }
+          units.push(0);
+          Ok(WideCString(units))
+        }
 
-  cc::Build::new()
-    .file(out_dir.join("glue.cc"))
-    .compile("glue");
+        pub fn as_ptr(&self) -> *const WChar {
+          self.0.as_ptr()
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+// A trait defined by the user from method signatures, implemented for every
+// class with methods matching all of them.
+#[derive(Debug)]
+struct ItemTrait {
+  name: String,
+  methods: Vec<TraitMethod>,
+  // The cfg and root relative path of each class implementing it.
+  impls: Vec<(TokenStream, String)>,
+}
+
+// A trait given to `define_trait`, by name, with its methods.
+type Trait = (String, Vec<TraitMethod>);
+
+#[derive(Debug, Clone)]
+struct TraitMethod {
+  signature: TokenStream,
+  name: String,
+  args: Vec<String>,
+  key: String,
+}
+
+impl TraitMethod {
+  fn new(signature: &str) -> Result<TraitMethod, Error> {
+    let invalid = || Error::InvalidSignature { signature: signature.to_string() };
+    let tokens = TokenStream::from_str(signature).map_err(|_| invalid())?;
+    let (name, args, key) = signature_key(tokens.clone(), None).ok_or_else(invalid)?;
+    Ok(TraitMethod { signature: tokens, name, args, key })
+  }
+}
+
+// The name and argument names of a method signature, and what it's compared
+// by: everything but the argument names, with the class spelled `Self`.
+fn signature_key(signature: TokenStream, class: Option<&str>) -> Option<(String, Vec<String>, String)> {
+  let mut tokens = signature.into_iter();
+  let mut key = String::new();
+  loop {
+    match tokens.next()? {
+      TokenTree::Ident(x) if x == "fn" => break,
+      x => key += &signature_tokens(x.into(), class),
+    }
+  }
+  let name = match tokens.next()? {
+    TokenTree::Ident(x) => x.to_string(),
+    _ => return None,
+  };
+  let params = match tokens.next()? {
+    TokenTree::Group(x) if x.delimiter() == Delimiter::Parenthesis => x.stream(),
+    _ => return None,
+  };
+
+  // Generics aren't groups, so commas inside them are told apart by depth.
+  let mut parts = vec![Vec::new()];
+  let mut depth = 0;
+  for token in params {
+    match &token {
+      TokenTree::Punct(x) if x.as_char() == ',' && depth == 0 => {
+        parts.push(Vec::new());
+        continue;
+      },
+      TokenTree::Punct(x) if x.as_char() == '<' => depth += 1,
+      TokenTree::Punct(x) if x.as_char() == '>' && depth > 0 => depth -= 1,
+      _ => {},
+    }
+    parts.last_mut().unwrap().push(token);
+  }
+  parts.retain(|x| !x.is_empty());
+  if parts.is_empty() || !parts[0].iter().any(|x| matches!(x, TokenTree::Ident(x) if x == "self")) {
+    return None;
+  }
+
+  key += &format!("fn {} ( {}", name, signature_tokens(parts[0].iter().cloned().collect(), class));
+  let mut args = Vec::new();
+  for part in &parts[1..] {
+    match &part[..] {
+      [TokenTree::Ident(name), TokenTree::Punct(colon), ty @ ..] if colon.as_char() == ':' && !ty.is_empty() => {
+        args.push(name.to_string());
+        key += &format!(", {}", signature_tokens(ty.iter().cloned().collect(), class));
+      },
+      _ => return None,
+    }
+  }
+
+  let ret = tokens.collect::<Vec<_>>();
+  let ret = match &ret[..] {
+    [] => "( ) ".to_string(),
+    [TokenTree::Punct(a), TokenTree::Punct(b), rest @ ..] if a.as_char() == '-' && b.as_char() == '>' && !rest.is_empty() => {
+      signature_tokens(rest.iter().cloned().collect(), class)
+    },
+    _ => return None,
+  };
+  key += &format!(") -> {}", ret);
+  Some((name, args, key))
+}
+
+// Spells out tokens the same way regardless of the spacing they were parsed
+// or quoted with.
+fn signature_tokens(tokens: TokenStream, class: Option<&str>) -> String {
+  let mut out = String::new();
+  for token in tokens {
+    match token {
+      TokenTree::Ident(x) if class.is_some_and(|class| x == class) => out += "Self ",
+      TokenTree::Ident(x) => out += &format!("{} ", x),
+      TokenTree::Punct(x) => out += &format!("{} ", x.as_char()),
+      TokenTree::Literal(x) => out += &format!("{} ", x),
+      TokenTree::Group(x) => {
+        let (open, close) = match x.delimiter() {
+          Delimiter::Parenthesis => ("( ", ") "),
+          Delimiter::Bracket => ("[ ", "] "),
+          Delimiter::Brace => ("{ ", "} "),
+          Delimiter::None => ("", ""),
+        };
+        out += &format!("{}{}{}", open, signature_tokens(x.stream(), class), close);
+      },
+    }
+  }
+  out
+}
+
+// Finds the classes with methods matching every one of a trait's.
+fn trait_impls(items: &[Item], path: &[String], cfg: &TokenStream, methods: &[TraitMethod], out: &mut Vec<(TokenStream, String)>) {
+  for item in items {
+    match item {
+      Item::Mod(item) => {
+        let mut path = path.to_vec();
+        path.push(item.name.clone());
+        trait_impls(&item.items, &path, cfg, methods, out);
+      },
+      Item::Cfg(item) => {
+        let targets = &item.targets;
+        let cfg = quote!(#cfg #[cfg(any(#(target_os = #targets),*))]);
+        trait_impls(&item.items, path, &cfg, methods, out);
+      },
+      Item::Struct(item) => {
+        let keys = item.methods.iter()
+          .filter_map(|x| signature_key(x.signature(), Some(&item.name)))
+          .map(|x| x.2)
+          .collect::<HashSet<_>>();
+        if methods.iter().all(|x| keys.contains(&x.key)) {
+          let mut path = path.to_vec();
+          path.push(item.name.clone());
+          out.push((cfg.clone(), path.join("::")));
+        }
+      },
+      _ => {},
+    }
+  }
+}
+
+impl ToTokens for ItemTrait {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let signatures = self.methods.iter().map(|x| &x.signature).collect::<Vec<_>>();
+    let impls = self.impls.iter().map(|(cfg, path)| {
+      let path = TokenStream::from_str(path).unwrap();
+      let methods = self.methods.iter().map(|x| {
+        let signature = &x.signature;
+        let method = format_ident!("{}", x.name);
+        let args = x.args.iter().map(|x| format_ident!("{}", x));
+        quote!(#signature { <#path>::#method(self, #(#args),*) })
+      });
+      quote!(
+        #cfg
+        impl #name for #path {
+          #(#methods)*
+        }
+      )
+    });
+
+    quote!(
+      pub trait #name {
+        #(#signatures;)*
+      }
+
+      #(#impls)*
+    ).to_tokens(tokens);
+  }
+}
+
+// Instantiations of a template with one integer parameter, bound together as
+// a generic struct big enough to hold any of them. Each member matches on the
+// parameter to call the instantiation's own.
+#[derive(Debug)]
+struct ItemGeneric {
+  name: String,
+  param: String,
+  // The parameter's value and the instantiation bound for it.
+  instances: Vec<(u64, String)>,
+  size: usize,
+  align: usize,
+  copy: bool,
+  constructors: Vec<GenericMember>,
+  methods: Vec<GenericMember>,
+}
+
+#[derive(Debug)]
+struct GenericMember {
+  signature: TokenStream,
+  name: String,
+  args: Vec<String>,
+  constant: bool,
+}
+
+// The members with the same signature in every instantiation, leaving out
+// those whose signatures name the instantiation itself.
+fn generic_members(instances: &[&ItemStruct]) -> (Vec<GenericMember>, Vec<GenericMember>) {
+  let first = instances[0];
+  let constructors = first.constructors.iter()
+    .filter_map(|x| {
+      let args = &x.args;
+      let key = quote!(#(#args),*).to_string();
+      let same = instances[1..].iter().all(|y| {
+        y.constructors.iter().any(|y| {
+          let args = &y.args;
+          y.method == x.method && quote!(#(#args),*).to_string() == key
+        })
+      });
+      if !same || key.split(|x: char| !x.is_alphanumeric() && x != '_').any(|x| x == first.name) {
+        return None;
+      }
+      let method = format_ident!("{}", x.method);
+      Some(GenericMember {
+        signature: quote!(unsafe fn #method(#(#args),*) -> Self),
+        name: x.method.clone(),
+        args: x.args.iter().map(|x| x.0.clone().unwrap()).collect(),
+        constant: false,
+      })
+    })
+    .collect();
+
+  let methods = first.methods.iter()
+    .filter_map(|x| {
+      let (name, args, key) = signature_key(x.signature(), Some(&first.name))?;
+      let same = instances[1..].iter().all(|y| {
+        y.methods.iter().any(|y| signature_key(y.signature(), Some(&y.name)).is_some_and(|y| y.0 == name && y.2 == key))
+      });
+      if !same || key.split(' ').any(|x| x == "Self") {
+        return None;
+      }
+      Some(GenericMember { signature: x.signature(), name, args, constant: x.constant })
+    })
+    .collect();
+  (constructors, methods)
+}
+
+impl ToTokens for ItemGeneric {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let param = format_ident!("{}", self.param);
+    let size = self.size;
+    let align = Literal::usize_unsuffixed(self.align);
+    let values = self.instances.iter().map(|x| Literal::u64_unsuffixed(x.0)).collect::<Vec<_>>();
+    let instances = self.instances.iter().map(|x| format_ident!("{}", x.1)).collect::<Vec<_>>();
+    let message = format!(
+      "{} is only instantiated for {} in {}",
+      self.name,
+      self.param,
+      self.instances.iter().map(|x| x.0.to_string()).collect::<Vec<_>>().join(", "),
+    );
+
+    let constructors = self.constructors.iter().map(|x| {
+      let signature = &x.signature;
+      let method = format_ident!("{}", x.name);
+      let args = x.args.iter().map(|x| format_ident!("{}", x)).collect::<Vec<_>>();
+      let calls = instances.iter().map(|instance| quote!(Self::wrap(#instance::#method(#(#args),*))));
+      quote!(
+        pub #signature {
+          let () = Self::INSTANTIATED;
+          match #param {
+            #(#values => #calls,)*
+            _ => unreachable!(),
+          }
+        }
+      )
+    });
+    let methods = self.methods.iter().map(|x| {
+      let signature = &x.signature;
+      let method = format_ident!("{}", x.name);
+      let args = x.args.iter().map(|x| format_ident!("{}", x)).collect::<Vec<_>>();
+      let calls = instances.iter().map(|instance| if x.constant {
+        quote!(#instance::#method(&*(self as *const Self as *const #instance), #(#args),*))
+      } else {
+        quote!(#instance::#method(&mut *(self as *mut Self as *mut #instance), #(#args),*))
+      });
+      quote!(
+        pub #signature {
+          let () = Self::INSTANTIATED;
+          match #param {
+            #(#values => unsafe { #calls },)*
+            _ => unreachable!(),
+          }
+        }
+      )
+    });
+
+    let (derives, drop) = if self.copy {
+      (quote!(#[derive(Clone, Copy)]), TokenStream::new())
+    } else {
+      (TokenStream::new(), quote!(
+        impl<const #param: usize> Drop for #name<#param> {
+          fn drop(&mut self) {
+            unsafe {
+              match #param {
+                #(#values => std::ptr::drop_in_place(self as *mut Self as *mut #instances),)*
+                _ => unreachable!(),
+              }
+            }
+          }
+        }
+      ))
+    };
+    let debug_name = format!("{}<{{}}>", self.name);
+
+    quote!(
+      #[repr(C, align(#align))]
+      #derives
+      pub struct #name<const #param: usize> {
+        _storage: [std::mem::MaybeUninit<u8>; #size],
+      }
+
+      impl<const #param: usize> #name<#param> {
+        const INSTANTIATED: () = assert!(#(#param == #values)||*, #message);
+
+        unsafe fn wrap<T>(x: T) -> Self {
+          debug_assert!(std::mem::size_of::<T>() <= #size);
+          let mut this = Self { _storage: [std::mem::MaybeUninit::uninit(); #size] };
+          std::ptr::write(&mut this as *mut Self as *mut T, x);
+          this
+        }
+
+        #(#constructors)*
+        #(#methods)*
+      }
+
+      #(
+        impl From<#instances> for #name<#values> {
+          fn from(x: #instances) -> Self {
+            unsafe { Self::wrap(x) }
+          }
+        }
+      )*
+
+      #drop
+
+      impl<const #param: usize> std::fmt::Debug for #name<#param> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+          f.debug_struct(&format!(#debug_name, #param)).finish_non_exhaustive()
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+// The methods of a class taking or returning raw pointers, left unsafe in a
+// trait of the `raw` module when binding a safe API.
+#[derive(Debug)]
+struct ItemRawMethods {
+  class: String,
+  methods: Vec<Method>,
+}
+
+impl ToTokens for ItemRawMethods {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let name = format_ident!("{}Raw", self.class);
+
+    let mut externs: Vec<(Option<String>, Vec<TokenStream>)> = Vec::new();
+    let mut items = Vec::new();
+    for method in &self.methods {
+      let (decl, item) = method.split_as(TokenStream::new());
+      match externs.iter_mut().find(|x| x.0 == method.abi) {
+        Some(x) => x.1.push(decl),
+        None => externs.push((method.abi.clone(), vec![decl])),
+      }
+      items.push(item);
+    }
+    let externs = externs.into_iter().map(|(abi, decls)| {
+      let abi = abi.as_deref().map(Literal::string);
+      quote!(extern #abi { #(#decls)* })
+    });
+    let signatures = self.methods.iter().map(|x| x.signature());
+
+    quote!(
+      #(#externs)*
+
+      pub trait #name {
+        #(#signatures;)*
+      }
+
+      impl #name for #class {
+        #(#items)*
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+// Whether a Rust signature only takes and returns plain values: no raw or
+// function pointers, and no variadic arguments.
+fn plain_signature(tokens: TokenStream) -> bool {
+  tokens.into_iter().all(|x| match x {
+    TokenTree::Punct(x) => x.as_char() != '*' && x.as_char() != '.',
+    TokenTree::Ident(x) => x != "NonNull" && x != "extern",
+    TokenTree::Group(x) => plain_signature(x.stream()),
+    TokenTree::Literal(_) => true,
+  })
+}
+
+// Makes the functions and members with plain signatures safe, and moves the
+// functions and methods without them to a `raw` module of each module when
+// `movable`.
+fn split_raw(items: Vec<Item>, movable: bool) -> Vec<Item> {
+  let mut out = Vec::new();
+  let mut raw = Vec::new();
+  for item in items {
+    match item {
+      Item::Mod(mut item) => {
+        item.items = split_raw(item.items, movable);
+        out.push(Item::Mod(item));
+      },
+      // Targets can share a module, so their raw items stay where they are.
+      Item::Cfg(mut item) => {
+        item.items = split_raw(item.items, false);
+        out.push(Item::Cfg(item));
+      },
+      Item::Fn(mut item) => {
+        if plain_signature(item.signature_types()) {
+          item.safe = true;
+          out.push(Item::Fn(item));
+        } else if movable {
+          raw.push(Item::Fn(item));
+        } else {
+          out.push(Item::Fn(item));
+        }
+      },
+      Item::Struct(mut item) => {
+        for constructor in &mut item.constructors {
+          let args = &constructor.args;
+          constructor.safe = plain_signature(quote!(#(#args),*));
+        }
+        for method in &mut item.static_methods {
+          let args = &method.args;
+          let ret = &method.ret;
+          method.safe |= method.shim == Shim::default() && plain_signature(quote!(#(#args),* -> #ret));
+        }
+        let (safe, unsafe_methods): (Vec<_>, Vec<_>) = item.methods.drain(..).partition(|x| plain_signature(x.signature()));
+        item.methods = safe.into_iter().map(|x| Method { safe: true, ..x }).collect();
+        if !unsafe_methods.is_empty() && movable {
+          raw.push(Item::RawMethods(ItemRawMethods { class: item.name.clone(), methods: unsafe_methods }));
+        } else {
+          item.methods.extend(unsafe_methods);
+        }
+        out.push(Item::Struct(item));
+      },
+      item => out.push(item),
+    }
+  }
+  if !raw.is_empty() {
+    out.push(Item::Mod(ItemMod {
+      name: "raw".to_string(),
+      items: raw,
+      comments: vec!["/// The functions and methods taking or returning raw pointers, which stay unsafe.".into()],
+    }));
+  }
+  out
+}
+
+// A `long double` wider than a `double`, which has no Rust equivalent and is
+// kept as its bytes. Not knowing how it's passed, functions can only take and
+// return it behind pointers.
+#[derive(Debug)]
+struct ItemLongDouble {
+  size: usize,
+  align: usize,
+}
+
+impl ToTokens for ItemLongDouble {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let size = Literal::usize_unsuffixed(self.size);
+    let align = Literal::usize_unsuffixed(self.align);
+    quote!(
+      /// The bytes of a C++ `long double`.
+      #[repr(C, align(#align))]
+      #[derive(Clone, Copy, Debug, Default, PartialEq)]
+      pub struct LongDouble(pub [u8; #size]);
+    ).to_tokens(tokens);
+  }
+}
+
+// A half precision float stored as its bits, either IEEE 754 binary16 or the
+// truncated bfloat16 format.
+#[derive(Debug)]
+struct ItemFloat {
+  name: String,
+  brain: bool,
+}
+
+impl ToTokens for ItemFloat {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+
+    let conversions = if self.brain {
+      quote!(
+        pub fn to_f32(self) -> f32 {
+          f32::from_bits((self.0 as u32) << 16)
+        }
+
+        pub fn from_f32(x: f32) -> #name {
+          let bits = x.to_bits();
+          if x.is_nan() {
+            return #name((bits >> 16) as u16 | 0x40);
+          }
+
+          // Round to nearest, ties to even.
+          let round = 0x7fff + ((bits >> 16) & 1);
+          #name((bits.wrapping_add(round) >> 16) as u16)
+        }
+      )
+    } else {
+      quote!(
+        pub fn to_f32(self) -> f32 {
+          let h = self.0 as u32;
+          let sign = (h & 0x8000) << 16;
+          let exp = (h >> 10) & 0x1f;
+          let man = h & 0x3ff;
+
+          let bits = if exp == 0x1f {
+            sign | 0x7f80_0000 | (man << 13)
+          } else if exp != 0 {
+            sign | ((exp + 112) << 23) | (man << 13)
+          } else if man == 0 {
+            sign
+          } else {
+            let shift = man.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | (((man << shift) & 0x3ff) << 13)
+          };
+          f32::from_bits(bits)
+        }
+
+        pub fn from_f32(x: f32) -> #name {
+          let bits = x.to_bits();
+          let sign = ((bits >> 16) & 0x8000) as u16;
+          let exp = ((bits >> 23) & 0xff) as i32;
+          let man = bits & 0x7f_ffff;
+
+          if exp == 0xff {
+            return #name(sign | 0x7c00 | if man != 0 { 0x200 } else { 0 });
+          }
+
+          // Round to nearest, ties to even.
+          let exp = exp - 127 + 15;
+          if exp >= 0x1f {
+            #name(sign | 0x7c00)
+          } else if exp <= 0 {
+            if exp < -10 {
+              return #name(sign);
+            }
+
+            let man = man | 0x80_0000;
+            let shift = (14 - exp) as u32;
+            let half = 1 << (shift - 1);
+            let rem = man & ((1 << shift) - 1);
+            let mut x = man >> shift;
+            if rem > half || (rem == half && x & 1 == 1) {
+              x += 1;
+            }
+            #name(sign | x as u16)
+          } else {
+            let mut x = ((exp as u32) << 10) | (man >> 13);
+            let rem = man & 0x1fff;
+            if rem > 0x1000 || (rem == 0x1000 && x & 1 == 1) {
+              x += 1;
+            }
+            #name(sign | x as u16)
+          }
+        }
+      )
+    };
+
+    quote!(
+      #[repr(transparent)]
+      #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+      pub struct #name(pub u16);
+
+      impl #name {
+        #conversions
+      }
+
+      impl From<#name> for f32 {
+        fn from(x: #name) -> f32 {
+          x.to_f32()
+        }
+      }
+
+      impl From<f32> for #name {
+        fn from(x: f32) -> #name {
+          #name::from_f32(x)
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+// A SIMD vector, wrapping the matching `core::arch` type on the listed
+// architectures and an aligned array of its lanes everywhere else.
+#[derive(Debug)]
+struct ItemVector {
+  name: String,
+  elem: String,
+  lanes: usize,
+  align: usize,
+  arches: Vec<(String, String)>,
+}
+
+impl ToTokens for ItemVector {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let elem = format_ident!("{}", self.elem);
+    let lanes = Literal::usize_unsuffixed(self.lanes);
+    let align = Literal::usize_unsuffixed(self.align);
+    let debug = format!("{} {{ .. }}", self.name);
+
+    let arches = self.arches.iter().map(|x| &x.0).collect::<Vec<_>>();
+    let definitions = self.arches.iter().map(|(arch, ty)| {
+      let module = format_ident!("{}", arch);
+      let ty = format_ident!("{}", ty);
+      quote!(
+        #[cfg(target_arch = #arch)]
+        #[repr(transparent)]
+        #[derive(Clone, Copy)]
+        pub struct #name(pub core::arch::#module::#ty);
+      )
+    });
+
+    quote!(
+      #(#definitions)*
+
+      #[cfg(not(any(#(target_arch = #arches),*)))]
+      #[repr(C, align(#align))]
+      #[derive(Clone, Copy)]
+      pub struct #name(pub [#elem; #lanes]);
+
+      impl Default for #name {
+        fn default() -> #name {
+          unsafe { std::mem::zeroed() }
+        }
+      }
+
+      impl std::fmt::Debug for #name {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          f.write_str(#debug)
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug)]
+struct ItemConst {
+  name: String,
+  ty: TokenStream,
+  value: TokenStream,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+impl ToTokens for ItemConst {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let ty = &self.ty;
+    let value = &self.value;
+
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    quote!(
+      #(#comments)*
+      pub const #name: #ty = #value;
+    ).to_tokens(tokens);
+  }
+}
+
+// A `typedef` or `using` alias, which signatures refer to by its name.
+#[derive(Debug)]
+struct ItemAlias {
+  name: String,
+  ty: TokenStream,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+impl ToTokens for ItemAlias {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let ty = &self.ty;
+
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    quote!(
+      #(#comments)*
+      pub type #name = #ty;
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug)]
+struct ItemVar {
+  name: String,
+  symbol: String,
+  ty: TokenStream,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+impl ToTokens for ItemVar {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let symbol = &self.symbol;
+    let ty = &self.ty;
+
+    let id = extern_ident(symbol);
+
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    quote!(
+      extern {
+        #[link_name=#symbol]
+        fn #id() -> #ty;
+      }
+
+      #(#comments)*
+      pub unsafe fn #name() -> #ty {
+        #id()
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Field(bool, String, TokenStream);
+
+impl ToTokens for Field {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let vis = if self.0 {
+      quote!(pub)
+    } else {
+      TokenStream::new()
+    };
+    let name = format_ident!("{}", self.1);
+    let ty = &self.2;
+    quote!(#vis #name: #ty).to_tokens(tokens);
+  }
+}
+
+// A base class, embedded as a field unless it's empty. The first public one
+// is reached through `Deref`, the others through `AsRef`.
+#[derive(Debug, Clone)]
+struct BaseClass {
+  class: String,
+  ty: TokenStream,
+  field: Option<String>,
+  public: bool,
+  deref: bool,
+}
+
+impl ToTokens for BaseClass {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    if !self.public {
+      return;
+    }
+
+    let class = format_ident!("{}", self.class);
+    let ty = &self.ty;
+
+    // Empty bases take no space, so they're at the start of the class.
+    let (get, get_mut) = match &self.field {
+      Some(field) => {
+        let field = format_ident!("{}", field);
+        (quote!(&self.#field), quote!(&mut self.#field))
+      },
+      None => (
+        quote!(unsafe { &*(self as *const #class as *const #ty) }),
+        quote!(unsafe { &mut *(self as *mut #class as *mut #ty) }),
+      ),
+    };
+
+    if self.deref {
+      quote!(
+        impl std::ops::Deref for #class {
+          type Target = #ty;
+
+          fn deref(&self) -> &#ty {
+            #get
+          }
+        }
+
+        impl std::ops::DerefMut for #class {
+          fn deref_mut(&mut self) -> &mut #ty {
+            #get_mut
+          }
+        }
+      ).to_tokens(tokens);
+    } else {
+      quote!(
+        impl AsRef<#ty> for #class {
+          fn as_ref(&self) -> &#ty {
+            #get
+          }
+        }
+
+        impl AsMut<#ty> for #class {
+          fn as_mut(&mut self) -> &mut #ty {
+            #get_mut
+          }
+        }
+      ).to_tokens(tokens);
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Constructor {
+  name: String,
+  method: String,
+  symbol: String,
+  args: Vec<Arg>,
+  abi: Option<String>,
+  safe: bool,
+  strict: bool,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+// Members are split into the declaration they link against and the item
+// they add to the class, so a class gets one extern and one impl block.
+impl Constructor {
+  fn split(&self) -> (TokenStream, TokenStream) {
+    let class = format_ident!("{}", self.name);
+    let method = format_ident!("{}", self.method);
+    let symbol = &self.symbol;
+
+    let id = extern_ident(symbol);
+
+    let args = &self.args;
+    let arg_names = self.args.iter().map(|x| format_ident!("{}", x.0.as_ref().unwrap()));
+    let this = quote!(this.as_mut_ptr());
+    let (init, finish) = this_init(self.strict, &class);
+
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    let decl = quote!(
+      #[link_name=#symbol]
+      fn #id(this: *mut #class, #(#args),*);
+    );
+    let body = quote!(
+      let mut this = #init;
+      #id(#this, #(#arg_names),*);
+      #finish
+    );
+    let item = if self.safe {
+      quote!(
+        #(#comments)*
+        pub fn #method(#(#args),*) -> #class {
+          unsafe { #body }
+        }
+      )
+    } else {
+      quote!(
+        #(#comments)*
+        pub unsafe fn #method(#(#args),*) -> #class {
+          #body
+        }
+      )
+    };
+    (decl, item)
+  }
+}
+
+// A setter for a function pointer field taking a `void*`, generic over what
+// the context points to. It also sets the field holding the context, if the
+// class has one.
+#[derive(Debug, Clone)]
+struct CallbackSetter {
+  class: String,
+  field: String,
+  abi: String,
+  args: Vec<TokenStream>,
+  context: usize,
+  ret: TokenStream,
+  context_field: Option<String>,
+}
+
+impl ToTokens for CallbackSetter {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let field = format_ident!("{}", self.field);
+    let setter = format_ident!("set_{}", self.field);
+    let abi = Literal::string(&self.abi);
+    let ret = &self.ret;
+
+    let mut args = self.args.clone();
+    args[self.context] = quote!(*mut T);
+    let callback = quote!(unsafe extern #abi fn(#(#args),*) -> #ret);
+
+    // Only the pointee of the context differs, which doesn't change how the
+    // function is called.
+    let set = quote!(self.#field = Some(unsafe { std::mem::transmute::<#callback, _>(callback) }););
+    match &self.context_field {
+      Some(context_field) => {
+        let context_field = format_ident!("{}", context_field);
+        quote!(
+          impl #class {
+            pub fn #setter<T>(&mut self, callback: #callback, context: *mut T) {
+              #set
+              self.#context_field = context as *mut std::os::raw::c_void;
+            }
+          }
+        )
+      },
+      None => quote!(
+        impl #class {
+          pub fn #setter<T>(&mut self, callback: #callback) {
+            #set
+          }
+        }
+      ),
+    }.to_tokens(tokens);
+  }
+}
+
+// A const method returning a pointer to elements and one returning how
+// many there are, called together by `as_slice`.
+#[derive(Debug, Clone)]
+struct SliceAccessor {
+  class: String,
+  data: String,
+  data_symbol: String,
+  size: String,
+  size_symbol: String,
+  elem: TokenStream,
+  len: TokenStream,
+  abi: Option<String>,
+}
+
+impl ToTokens for SliceAccessor {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let data_symbol = &self.data_symbol;
+    let size_symbol = &self.size_symbol;
+    let elem = &self.elem;
+    let len = &self.len;
+    let abi = self.abi.as_deref().map(Literal::string);
+    let doc = format!(" The `{}()` elements `{}()` points to.", self.size, self.data);
+    quote!(
+      impl #class {
+        #[doc = #doc]
+        pub fn as_slice(&self) -> &[#elem] {
+          extern #abi {
+            #[link_name=#data_symbol]
+            fn data(this: *const #class) -> *const #elem;
+            #[link_name=#size_symbol]
+            fn size(this: *const #class) -> #len;
+          }
+          unsafe {
+            match <usize as std::convert::TryFrom<#len>>::try_from(size(self)) {
+              Ok(len) if len > 0 => std::slice::from_raw_parts(data(self), len),
+              _ => &[],
+            }
+          }
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+// A conversion from `&str` through a constructor taking a C string or a
+// `std::string`, run by the glue.
+#[derive(Debug, Clone)]
+struct StrConstructor {
+  class: String,
+  symbol: String,
+  string: bool,
+  strict: bool,
+}
+
+impl ToTokens for StrConstructor {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let symbol = &self.symbol;
+
+    let id = extern_ident(symbol);
+    let this = quote!(this.as_mut_ptr());
+    let (init, finish) = this_init(self.strict, &class);
+
+    let decl = quote!(
+      extern {
+        #[link_name=#symbol]
+        fn #id(this: *mut #class, data: *const std::os::raw::c_char, len: usize);
+      }
+    );
+
+    // A `std::string` is built from the bytes and length, so interior nuls
+    // are fine; a C string can't hold them.
+    if self.string {
+      quote!(
+        #decl
+
+        impl<'a> From<&'a str> for #class {
+          fn from(s: &'a str) -> #class {
+            unsafe {
+              let mut this = #init;
+              #id(#this, s.as_ptr() as *const std::os::raw::c_char, s.len());
+              #finish
+            }
+          }
+        }
+      ).to_tokens(tokens);
+    } else {
+      quote!(
+        #decl
+
+        impl<'a> std::convert::TryFrom<&'a str> for #class {
+          type Error = std::ffi::NulError;
+
+          fn try_from(s: &'a str) -> Result<#class, std::ffi::NulError> {
+            let s = std::ffi::CString::new(s)?;
+            unsafe {
+              let mut this = #init;
+              #id(#this, s.as_ptr(), s.as_bytes().len());
+              Ok(#finish)
+            }
+          }
+        }
+      ).to_tokens(tokens);
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct BoxedConstructor {
+  class: String,
+  new_symbol: String,
+  delete_symbol: String,
+  args: Vec<Arg>,
+}
+
+impl ToTokens for BoxedConstructor {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let owner = format_ident!("{}Box", self.class);
+    let new_symbol = &self.new_symbol;
+    let delete_symbol = &self.delete_symbol;
+
+    let new_id = extern_ident(new_symbol);
+    let delete_id = extern_ident(delete_symbol);
+
+    let args = &self.args;
+    let arg_names = self.args.iter().map(|x| format_ident!("{}", x.0.as_ref().unwrap()));
+
+    quote!(
+      extern {
+        #[link_name=#new_symbol]
+        fn #new_id(#(#args),*) -> *mut #class;
+        #[link_name=#delete_symbol]
+        fn #delete_id(this: *mut #class);
+      }
+
+      #[derive(Debug)]
+      pub struct #owner(*mut #class);
+
+      impl #class {
+        pub unsafe fn new_boxed(#(#args),*) -> #owner {
+          #owner(#new_id(#(#arg_names),*))
+        }
+      }
+
+      impl #owner {
+        pub fn as_ptr(&self) -> *mut #class {
+          self.0
+        }
+
+        pub fn as_pin_mut(&mut self) -> std::pin::Pin<&mut #class> {
+          unsafe { std::pin::Pin::new_unchecked(&mut *self.0) }
+        }
+      }
+
+      impl std::ops::Deref for #owner {
+        type Target = #class;
+
+        fn deref(&self) -> &#class {
+          unsafe { &*self.0 }
+        }
+      }
+
+      impl Drop for #owner {
+        fn drop(&mut self) {
+          unsafe { #delete_id(self.0); }
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug)]
+struct CtorBuilder {
+  class: String,
+  required: Vec<Arg>,
+  optional: Vec<(Arg, Option<TokenStream>)>,
+  symbols: Vec<String>,
+  strict: bool,
+}
+
+impl ToTokens for CtorBuilder {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let builder = format_ident!("{}Builder", self.class);
+
+    let ids = self.symbols.iter().map(|x| extern_ident(x)).collect::<Vec<_>>();
+
+    let required = &self.required;
+    let required_names = self.required.iter().map(|x| format_ident!("{}", x.0.as_ref().unwrap())).collect::<Vec<_>>();
+    let required_types = self.required.iter().map(|x| &x.1).collect::<Vec<_>>();
+    let optional_names = self.optional.iter().map(|x| format_ident!("{}", (x.0).0.as_ref().unwrap())).collect::<Vec<_>>();
+    let optional_types = self.optional.iter().map(|x| &(x.0).1).collect::<Vec<_>>();
+
+    let externs = self.symbols.iter().zip(&ids).enumerate().map(|(i, (symbol, id))| {
+      let optional = self.optional[..i].iter().map(|x| &x.0);
+      quote!(
+        #[link_name=#symbol]
+        fn #id(this: *mut #class, #(#required,)* #(#optional),*);
+      )
+    });
+
+    let values = self.optional.iter().map(|(arg, default)| {
+      let name = format_ident!("{}", arg.0.as_ref().unwrap());
+      if let Some(default) = default {
+        quote!(self.#name.unwrap_or(#default))
+      } else {
+        let message = format!("{} must be set explicitly when a later argument is set", arg.0.as_ref().unwrap());
+        quote!(self.#name.expect(#message))
+      }
+    }).collect::<Vec<_>>();
+
+    let this = quote!(this.as_mut_ptr());
+    let (init, finish) = this_init(self.strict, &class);
+    let arms = ids.iter().enumerate().map(|(i, id)| {
+      let values = &values[..i];
+      quote!(#i => #id(#this, #(self.#required_names,)* #(#values),*))
+    });
+
+    quote!(
+      extern {
+        #(#externs)*
+      }
+
+      pub struct #builder {
+        #(#required_names: #required_types,)*
+        #(#optional_names: Option<#optional_types>,)*
+      }
+
+      impl #class {
+        pub fn builder(#(#required),*) -> #builder {
+          #builder {
+            #(#required_names,)*
+            #(#optional_names: None,)*
+          }
+        }
+      }
+
+      impl #builder {
+        #(
+          pub fn #optional_names(mut self, #optional_names: #optional_types) -> #builder {
+            self.#optional_names = Some(#optional_names);
+            self
+          }
+        )*
+
+        pub unsafe fn build(self) -> #class {
+          let set = [#(self.#optional_names.is_some()),*];
+          let mut this = #init;
+          match set.iter().rposition(|x| *x).map_or(0, |x| x + 1) {
+            #(#arms,)*
+            _ => unreachable!(),
+          }
+          #finish
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Destructor {
+  name: String,
+  symbol: String,
+  abi: Option<String>,
+  strict: bool,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+impl Destructor {
+  fn split(&self) -> (TokenStream, TokenStream) {
+    let class = format_ident!("{}", self.name);
+    let symbol = &self.symbol;
+
+    let id = extern_ident(symbol);
+
+    let this = self_ptr(self.strict, false, &class);
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    let decl = quote!(
+      #[link_name=#symbol]
+      fn #id(_: *mut #class);
+    );
+    let item = quote!(
+      impl Drop for #class {
+        #(#comments)*
+        fn drop(&mut self) {
+          unsafe { #id(#this); }
+        }
+      }
+    );
+    (decl, item)
+  }
+}
+
+// What a method returning a pointer or reference is annotated to return
+// instead.
+#[derive(Debug, Clone)]
+enum Returns {
+  Raw,
+  Owned(TokenStream),
+  Borrowed {
+    pointee: TokenStream,
+    mutable: bool,
+    // Pointers are borrowed as an `Option`, and references returned as
+    // `NonNull` converted from that.
+    nullable: bool,
+    non_null: bool,
+  },
+}
+
+#[derive(Debug, Clone)]
+struct Method {
+  class: std::rc::Rc<str>,
+  name: String,
+  symbol: String,
+  args: Vec<Arg>,
+  ret: TokenStream,
+  abi: Option<String>,
+  safe: bool,
+  strict: bool,
+  // Const methods take `&self`.
+  constant: bool,
+  outs: Vec<(usize, TokenStream)>,
+  slices: Vec<SliceParam>,
+  error: Option<TokenStream>,
+  shim: Shim,
+  preconditions: Vec<TokenStream>,
+  returns: Returns,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+impl Method {
+  // The signature of the Rust method, and the body calling `call` with the
+  // arguments to pass on when they need converting and `direct` otherwise.
+  fn wrapper(&self, direct: TokenStream, call: impl FnOnce(Vec<TokenStream>) -> TokenStream) -> (TokenStream, TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let receiver = if self.constant { quote!(&self) } else { quote!(&mut self) };
+    let unsafety = if self.safe { quote!() } else { quote!(unsafe) };
+    if !self.outs.is_empty() || !self.slices.is_empty() || self.error.is_some() || self.shim != Shim::default() {
+      let (inputs, outputs, body) = out_wrapper(&self.args, &self.outs, &self.slices, &self.ret, self.error.as_ref(), self.shim, call);
+      (quote!(#unsafety fn #name(#receiver, #(#inputs),*) -> #outputs), body)
+    } else {
+      let args = &self.args;
+      let (ret, body) = match &self.returns {
+        Returns::Raw => (self.ret.clone(), direct),
+        Returns::Owned(pointee) => (quote!(Option<CxxBox<#pointee>>), quote!(CxxBox::from_raw(#direct as *mut #pointee))),
+        Returns::Borrowed { pointee, mutable, nullable, non_null } => {
+          let borrow = if *mutable { quote!(&mut #pointee) } else { quote!(&#pointee) };
+          let convert = if *mutable { quote!(as_mut) } else { quote!(as_ref) };
+          if *nullable {
+            (quote!(Option<#borrow>), quote!((#direct).#convert()))
+          } else if *non_null {
+            (borrow, quote!((#direct).#convert()))
+          } else if *mutable {
+            (borrow, quote!(&mut *(#direct)))
+          } else {
+            (borrow, quote!(&*(#direct)))
+          }
+        },
+      };
+      (quote!(#unsafety fn #name(#receiver, #(#args),*) -> #ret), body)
+    }
+  }
+
+  fn signature(&self) -> TokenStream {
+    self.wrapper(quote!(), |_| quote!()).0
+  }
+
+  fn split(&self) -> (TokenStream, TokenStream) {
+    self.split_as(quote!(pub))
+  }
+
+  // Like `split`, with the item given the visibility `vis`, which trait
+  // impls have none of.
+  fn split_as(&self, vis: TokenStream) -> (TokenStream, TokenStream) {
+    let class = format_ident!("{}", &*self.class);
+    let symbol = &self.symbol;
+    let ret = &self.ret;
+
+    let id = extern_ident(symbol);
+
+    let arg_names = self.args.iter().map(|x| format_ident!("{}", x.0.as_ref().unwrap())).collect::<Vec<_>>();
+
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    let this = self_ptr(self.strict, self.constant, &class);
+    let pointer = if self.constant { quote!(*const #class) } else { quote!(*mut #class) };
+    let checks = &self.preconditions;
+    let (signature, body) = self.wrapper(quote!(#id(#this, #(#arg_names),*)), |args| quote!(#id(#this, #(#args),*)));
+    let body = if self.safe {
+      quote!(#vis #signature { #(debug_assert!(#checks);)* unsafe { #body } })
+    } else {
+      quote!(#vis #signature { #(debug_assert!(#checks);)* #body })
+    };
+
+    let (params, ret) = extern_signature(&self.args, ret, self.shim);
+    let decl = quote!(
+      #[link_name=#symbol]
+      fn #id(this: #pointer, #(#params),*) -> #ret;
+    );
+    let item = quote!(
+      #(#comments)*
+      #body
+    );
+    (decl, item)
+  }
+}
+
+#[derive(Debug, Clone)]
+struct StaticMethod {
+  name: String,
+  symbol: String,
+  args: Vec<Arg>,
+  ret: TokenStream,
+  abi: Option<String>,
+  safe: bool,
+  shim: Shim,
+  preconditions: Vec<TokenStream>,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+impl StaticMethod {
+  fn split(&self) -> (TokenStream, TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let symbol = &self.symbol;
+    let id = extern_ident(symbol);
+    let ret = &self.ret;
+
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+    let arguments = &self.args;
+    let arg_names = self.args.iter().map(|x| format_ident!("{}", x.0.as_ref().unwrap()));
+
+    let checks = &self.preconditions;
+    let body = if self.shim != Shim::default() {
+      let (inputs, outputs, body) = out_wrapper(&self.args, &[], &[], ret, None, self.shim, |args| quote!(#id(#(#args),*)));
+      if self.safe {
+        quote!(pub fn #name(#(#inputs),*) -> #outputs { #(debug_assert!(#checks);)* unsafe { #body } })
+      } else {
+        quote!(pub unsafe fn #name(#(#inputs),*) -> #outputs { #(debug_assert!(#checks);)* #body })
+      }
+    } else if self.safe {
+      quote!(pub fn #name(#(#arguments),*) -> #ret { #(debug_assert!(#checks);)* unsafe { #id(#(#arg_names),*) } })
+    } else {
+      quote!(pub unsafe fn #name(#(#arguments),*) -> #ret { #(debug_assert!(#checks);)* #id(#(#arg_names),*) })
+    };
+
+    let (params, ret) = extern_signature(&self.args, ret, self.shim);
+    let decl = quote!(
+      #[link_name=#symbol]
+      fn #id(#(#params),*) -> #ret;
+    );
+    let item = quote!(
+      #(#comments)*
+      #body
+    );
+    (decl, item)
+  }
+}
+
+#[derive(Debug)]
+struct ItemStruct {
+  name: String,
+  fields: Vec<Field>,
+  constructors: Vec<Constructor>,
+  destructor: Option<Destructor>,
+  methods: Vec<Method>,
+  static_methods: Vec<StaticMethod>,
+  tagged_unions: Vec<TaggedUnion>,
+  subclass: Option<Subclass>,
+  interface: Option<Interface>,
+  boxed: Option<BoxedConstructor>,
+  polymorphic: bool,
+  downcasts: Vec<Downcast>,
+  type_info: Option<TypeInfo>,
+  overload_sets: Vec<OverloadSet>,
+  ctor_builder: Option<CtorBuilder>,
+  str_constructor: Option<StrConstructor>,
+  callback_setters: Vec<CallbackSetter>,
+  slice_accessor: Option<SliceAccessor>,
+  bases: Vec<BaseClass>,
+  copy: bool,
+  zeroed_default: bool,
+  // Set when it holds a vtable pointer, which a `Default` would leave null
+  // for safe code to call virtual members through, so it has none.
+  vtable: bool,
+  // Set for aggregates, which get a `new` taking each field.
+  aggregate_constructor: bool,
+  no_drop: bool,
+  // Set when the layout is opaque bytes rather than the C++ fields.
+  align: Option<usize>,
+  eq: Option<bool>,
+  // Set when `Debug` is implemented rather than derived.
+  debug: Option<DebugImpl>,
+  layout: Option<Layout>,
+  serde: Option<Serde>,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+// The `serde` derives of a struct, renaming its fields by the rule of
+// `rename_all` and skipping the fields named in `skip` along with pointers,
+// which deserialize as null.
+#[derive(Debug)]
+struct Serde {
+  rename_all: Option<String>,
+  skip: Vec<String>,
+}
+
+impl Serde {
+  fn field(&self, field: &Field) -> TokenStream {
+    let ty = field.2.to_string();
+    let skip = if ty.starts_with("* const") {
+      quote!(#[serde(skip, default = "std::ptr::null")])
+    } else if ty.starts_with("* mut") {
+      quote!(#[serde(skip, default = "std::ptr::null_mut")])
+    } else if ty.starts_with("Option < unsafe extern") || self.skip.contains(&field.1) {
+      quote!(#[serde(skip)])
+    } else {
+      TokenStream::new()
+    };
+    quote!(#skip #field)
+  }
+}
+
+// The size, alignment and field offsets clang laid a class out with, checked
+// against the Rust struct's at compile time in test builds.
+#[derive(Debug)]
+struct Layout {
+  size: usize,
+  align: usize,
+  offsets: Vec<(String, usize)>,
+}
+
+impl Layout {
+  fn to_tokens(&self, class: &proc_macro2::Ident) -> TokenStream {
+    let size = self.size;
+    let align = self.align;
+    let size_message = format!("the size of {} differs from C++", class);
+    let align_message = format!("the alignment of {} differs from C++", class);
+    let offsets = self.offsets.iter().map(|(field, offset)| {
+      let message = format!("the offset of {}::{} differs from C++", class, field);
+      let field = format_ident!("{}", field);
+      quote!(assert!(std::mem::offset_of!(#class, #field) == #offset, #message);)
+    });
+    quote!(
+      #[cfg(test)]
+      const _: () = {
+        assert!(std::mem::size_of::<#class>() == #size, #size_message);
+        assert!(std::mem::align_of::<#class>() == #align, #align_message);
+        #(#offsets)*
+      };
+    )
+  }
+}
+
+impl ToTokens for ItemStruct {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let fields = self.fields.iter().map(|x| match &self.serde {
+      Some(serde) => serde.field(x),
+      None => x.to_token_stream(),
+    });
+    let tagged_unions = &self.tagged_unions;
+    let subclass = &self.subclass;
+    let interface = &self.interface;
+    let boxed = &self.boxed;
+    let downcasts = &self.downcasts;
+    let type_info = &self.type_info;
+    let overload_sets = &self.overload_sets;
+    let ctor_builder = &self.ctor_builder;
+    let str_constructor = &self.str_constructor;
+    let callback_setters = &self.callback_setters;
+    let slice_accessor = &self.slice_accessor;
+    let bases = &self.bases;
+
+    let downcast = if self.polymorphic {
+      let downcast = format_ident!("Downcast{}", self.name);
+      quote!(
+        /// Implementors' `downcast_ptr` must return `base` as `Self` when
+        /// that's what it points to, and null otherwise.
+        pub unsafe trait #downcast: Sized {
+          unsafe fn downcast_ptr(base: *mut #name) -> *mut Self;
+        }
+
+        impl #name {
+          pub fn downcast_ref<T: #downcast>(&self) -> Option<&T> {
+            unsafe { T::downcast_ptr(self as *const #name as *mut #name).as_ref() }
+          }
+
+          pub fn downcast_mut<T: #downcast>(&mut self) -> Option<&mut T> {
+            unsafe { T::downcast_ptr(self as *mut #name).as_mut() }
+          }
+        }
+      )
+    } else {
+      TokenStream::new()
+    };
+
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    // One extern block per calling convention, the default first.
+    let mut externs: Vec<(Option<String>, Vec<TokenStream>)> = vec![(None, Vec::new())];
+    let mut members = Vec::new();
+    let mut add = |abi: Option<String>, (decl, item): (TokenStream, TokenStream)| {
+      match externs.iter_mut().find(|x| x.0 == abi) {
+        Some(x) => x.1.push(decl),
+        None => externs.push((abi, vec![decl])),
+      }
+      members.push(item);
+    };
+
+    for constructor in &self.constructors {
+      add(constructor.abi.clone(), constructor.split());
+    }
+    for method in &self.methods {
+      add(method.abi.clone(), method.split());
+    }
+    for method in &self.static_methods {
+      add(method.abi.clone(), method.split());
+    }
+
+    let destructor = self.destructor.as_ref().map(|x| {
+      let (decl, item) = x.split();
+      match externs.iter_mut().find(|y| y.0 == x.abi) {
+        Some(y) => y.1.push(decl),
+        None => externs.push((x.abi.clone(), vec![decl])),
+      }
+      item
+    });
+
+    let externs = externs.into_iter().filter(|x| !x.1.is_empty()).map(|(abi, decls)| {
+      let abi = abi.as_deref().map(Literal::string);
+      quote!(extern #abi { #(#decls)* })
+    });
+    let members = if members.is_empty() {
+      TokenStream::new()
+    } else {
+      quote!(impl #name { #(#members)* })
+    };
+
+    // Objects C++ owns are only ever borrowed.
+    let handle = if self.no_drop {
+      quote!(
+        impl #name {
+          pub unsafe fn from_ptr<'a>(ptr: *mut #name) -> Option<&'a mut #name> {
+            ptr.as_mut()
+          }
+        }
+      )
+    } else {
+      TokenStream::new()
+    };
+
+    // Arrays only implement `Default` up to 32 elements.
+    let (mut derives, default) = if self.no_drop || self.vtable {
+      (Vec::new(), TokenStream::new())
+    } else if self.zeroed_default {
+      (Vec::new(), quote!(
+        impl Default for #name {
+          fn default() -> #name {
+            unsafe { std::mem::zeroed() }
+          }
+        }
+      ))
+    } else {
+      (vec![quote!(Default)], TokenStream::new())
+    };
+    let debug = match &self.debug {
+      Some(debug) => debug.to_tokens(&name),
+      None => {
+        derives.push(quote!(Debug));
+        TokenStream::new()
+      },
+    };
+    if self.copy {
+      derives.extend(vec![quote!(Clone), quote!(Copy)]);
+    }
+    match self.eq {
+      Some(true) => derives.extend(vec![quote!(PartialEq), quote!(Eq), quote!(Hash)]),
+      Some(false) => derives.push(quote!(PartialEq)),
+      None => {},
+    }
+    let serde = self.serde.as_ref().map(|serde| {
+      derives.extend(vec![quote!(serde::Serialize), quote!(serde::Deserialize)]);
+      serde.rename_all.as_ref().map(|x| quote!(#[serde(rename_all = #x)]))
+    });
+    let derives = quote!(#[derive(#(#derives),*)] #serde);
+    let repr = match self.align {
+      Some(align) => {
+        let align = Literal::usize_unsuffixed(align);
+        quote!(#[repr(C, align(#align))])
+      },
+      None => quote!(#[repr(C)]),
+    };
+    let layout = self.layout.as_ref().map(|x| x.to_tokens(&name));
+    let aggregate_constructor = if self.aggregate_constructor {
+      let names = self.fields.iter().map(|x| format_ident!("{}", x.1)).collect::<Vec<_>>();
+      let types = self.fields.iter().map(|x| &x.2);
+      quote!(
+        impl #name {
+          pub const fn new(#(#names: #types),*) -> #name {
+            #name { #(#names),* }
+          }
+        }
+      )
+    } else {
+      TokenStream::new()
+    };
+
+    quote!(
+      #(#comments)*
+      #repr
+      #derives
+      pub struct #name {
+        #(#fields),*
+      }
+
+      #default
+      #debug
+      #handle
+      #aggregate_constructor
+      #(#externs)*
+      #members
+      #destructor
+
+      #(#tagged_unions)*
+      #subclass
+      #interface
+      #boxed
+      #downcast
+      #(#downcasts)*
+      #type_info
+      #(#overload_sets)*
+      #ctor_builder
+      #str_constructor
+      #(#callback_setters)*
+      #slice_accessor
+      #(#bases)*
+      #layout
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Virtual {
+  name: String,
+  args: Vec<Arg>,
+  ret: TokenStream,
+}
+
+#[derive(Debug)]
+struct Subclass {
+  class: String,
+  new_symbol: String,
+  delete_symbol: String,
+  virtuals: Vec<Virtual>,
+}
+
+impl ToTokens for Subclass {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let table = format_ident!("{}Virtuals", self.class);
+    let new_symbol = &self.new_symbol;
+    let delete_symbol = &self.delete_symbol;
+
+    let new_id = extern_ident(new_symbol);
+    let delete_id = extern_ident(delete_symbol);
+
+    let virtuals = self.virtuals.iter().map(|x| {
+      let name = format_ident!("{}", x.name);
+      let args = &x.args;
+      let ret = &x.ret;
+      quote!(pub #name: Option<unsafe extern "C" fn(user_data: *mut std::os::raw::c_void, this: *mut #class, #(#args),*) -> #ret>)
+    });
+
+    quote!(
+      #[repr(C)]
+      pub struct #table {
+        pub user_data: *mut std::os::raw::c_void,
+        #(#virtuals),*
+      }
+
+      extern {
+        #[link_name=#new_symbol]
+        fn #new_id(virtuals: *const #table) -> *mut #class;
+        #[link_name=#delete_symbol]
+        fn #delete_id(this: *mut #class);
+      }
+
+      impl #class {
+        pub unsafe fn new_subclass(virtuals: *const #table) -> *mut #class {
+          #new_id(virtuals)
+        }
+
+        pub unsafe fn delete_subclass(this: *mut #class) {
+          #delete_id(this)
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug)]
+struct InterfaceMethod {
+  name: String,
+  symbol: String,
+  args: Vec<Arg>,
+  ret: TokenStream,
+}
+
+#[derive(Debug)]
+struct Interface {
+  class: String,
+  methods: Vec<InterfaceMethod>,
+}
+
+impl ToTokens for Interface {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let trait_name = format_ident!("{}Interface", self.class);
+    let handle = format_ident!("Dyn{}", self.class);
+
+    let ids = self.methods.iter().map(|x| extern_ident(&x.symbol)).collect::<Vec<_>>();
+
+    let externs = self.methods.iter().zip(&ids).map(|(x, id)| {
+      let symbol = &x.symbol;
+      let args = &x.args;
+      let ret = &x.ret;
+      quote!(
+        #[link_name=#symbol]
+        fn #id(this: *mut #class, #(#args),*) -> #ret;
+      )
+    });
+
+    let signatures = self.methods.iter().map(|x| {
+      let name = format_ident!("{}", x.name);
+      let args = &x.args;
+      let ret = &x.ret;
+      quote!(unsafe fn #name(&mut self, #(#args),*) -> #ret;)
+    });
+
+    let impls = self.methods.iter().zip(&ids).map(|(x, id)| {
+      let name = format_ident!("{}", x.name);
+      let args = &x.args;
+      let ret = &x.ret;
+      let arg_names = x.args.iter().map(|x| format_ident!("{}", x.0.as_ref().unwrap()));
+      quote!(
+        unsafe fn #name(&mut self, #(#args),*) -> #ret {
+          #id(self.0, #(#arg_names),*)
+        }
+      )
+    });
+
+    quote!(
+      extern {
+        #(#externs)*
+      }
+
+      pub trait #trait_name {
+        #(#signatures)*
+      }
+
+      #[derive(Debug, Clone, Copy)]
+      pub struct #handle(pub *mut #class);
+
+      impl #trait_name for #handle {
+        #(#impls)*
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug)]
+struct Downcast {
+  class: String,
+  base: String,
+  path: TokenStream,
+  symbol: String,
+}
+
+impl ToTokens for Downcast {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let path = &self.path;
+    let base = format_ident!("{}", self.base);
+    let base = quote!(#path #base);
+    let downcast = format_ident!("Downcast{}", self.base);
+    let downcast = quote!(#path #downcast);
+    let symbol = &self.symbol;
+
+    let id = extern_ident(symbol);
+
+    quote!(
+      extern {
+        #[link_name=#symbol]
+        fn #id(base: *mut #base) -> *mut #class;
+      }
+
+      unsafe impl #downcast for #class {
+        unsafe fn downcast_ptr(base: *mut #base) -> *mut #class {
+          #id(base)
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug)]
+struct OverloadSet {
+  class: String,
+  name: String,
+  methods: Vec<Method>,
+}
+
+impl ToTokens for OverloadSet {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let name = format_ident!("{}", self.name);
+    let args = format_ident!("{}{}Args", self.class, camel_case(&self.name));
+    let ret = &self.methods[0].ret;
+
+    let ids = self.methods.iter().map(|x| extern_ident(&x.symbol)).collect::<Vec<_>>();
+    let variants = (0..self.methods.len()).map(|i| format_ident!("Overload{}", i)).collect::<Vec<_>>();
+
+    let comments = self.methods.iter().flat_map(|x| &x.comments).map(|x| TokenStream::from_str(x).unwrap());
+
+    // The set takes `&self` only if every overload is const.
+    let constant = self.methods.iter().all(|x| x.constant);
+    let receiver = if constant { quote!(&self) } else { quote!(&mut self) };
+
+    let externs = self.methods.iter().zip(&ids).map(|(x, id)| {
+      let abi = x.abi.as_deref().map(Literal::string);
+      let symbol = &x.symbol;
+      let arguments = &x.args;
+      let pointer = if x.constant { quote!(*const #class) } else { quote!(*mut #class) };
+      quote!(
+        extern #abi {
+          #[link_name=#symbol]
+          fn #id(this: #pointer, #(#arguments),*) -> #ret;
+        }
+      )
+    });
+
+    let definitions = self.methods.iter().zip(&variants).map(|(x, variant)| {
+      let types = x.args.iter().map(|x| &x.1);
+      quote!(#variant(#(#types),*))
+    });
+
+    let conversions = self.methods.iter().zip(&variants).map(|(x, variant)| {
+      let types = x.args.iter().map(|x| &x.1).collect::<Vec<_>>();
+      if types.len() == 1 {
+        let ty = types[0];
+        quote!(
+          impl From<#ty> for #args {
+            fn from(x: #ty) -> #args {
+              #args::#variant(x)
+            }
+          }
+        )
+      } else {
+        let fields = (0..types.len()).map(Literal::usize_unsuffixed);
+        quote!(
+          impl From<(#(#types,)*)> for #args {
+            #[allow(unused_variables)]
+            fn from(x: (#(#types,)*)) -> #args {
+              #args::#variant(#(x.#fields),*)
+            }
+          }
+        )
+      }
+    });
+
+    let arms = self.methods.iter().zip(&ids).zip(&variants).map(|((x, id), variant)| {
+      let arg_names = (0..x.args.len()).map(|i| format_ident!("a{}", i)).collect::<Vec<_>>();
+      let this = self_ptr(x.strict, constant, &class);
+      quote!(#args::#variant(#(#arg_names),*) => #id(#this, #(#arg_names),*))
+    });
+
+    let call = quote!(match args.into() { #(#arms,)* });
+    let body = if self.methods.iter().all(|x| x.safe) {
+      quote!(pub fn #name<A: Into<#args>>(#receiver, args: A) -> #ret { unsafe { #call } })
+    } else {
+      quote!(pub unsafe fn #name<A: Into<#args>>(#receiver, args: A) -> #ret { #call })
+    };
+
+    quote!(
+      #(#externs)*
+
+      pub enum #args {
+        #(#definitions),*
+      }
+
+      #(#conversions)*
+
+      impl #class {
+        #(#comments)*
+        #body
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+// A `Debug` implementation printing what the glue formats the object as,
+// or just its name without glue.
+#[derive(Debug)]
+struct DebugImpl {
+  symbol: Option<String>,
+}
+
+impl DebugImpl {
+  fn to_tokens(&self, class: &proc_macro2::Ident) -> TokenStream {
+    let terse = quote!(f.debug_struct(stringify!(#class)).finish_non_exhaustive());
+    let symbol = match &self.symbol {
+      Some(x) => x,
+      None => return quote!(
+        impl std::fmt::Debug for #class {
+          fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            #terse
+          }
+        }
+      ),
+    };
+    let id = extern_ident(symbol);
+
+    quote!(
+      extern {
+        #[link_name=#symbol]
+        fn #id(this: *const #class) -> *mut std::os::raw::c_char;
+      }
+
+      impl std::fmt::Debug for #class {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          extern "C" {
+            fn free(ptr: *mut std::os::raw::c_void);
+          }
+
+          unsafe {
+            let x = #id(self as *const #class);
+            if x.is_null() {
+              return #terse;
+            }
+            let result = f.write_str(&std::ffi::CStr::from_ptr(x).to_string_lossy());
+            free(x as *mut std::os::raw::c_void);
+            result
+          }
+        }
+      }
+    )
+  }
+}
+
+#[derive(Debug)]
+struct TypeInfo {
+  class: String,
+  qualified_name: String,
+  symbol: String,
+  static_symbol: String,
+}
+
+impl ToTokens for TypeInfo {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let qualified_name = &self.qualified_name;
+    let type_id = fnv1a(&self.qualified_name);
+    let symbol = &self.symbol;
+    let static_symbol = &self.static_symbol;
+
+    let id = extern_ident(symbol);
+    let static_id = extern_ident(static_symbol);
+
+    quote!(
+      extern {
+        #[link_name=#symbol]
+        fn #id(this: *const #class) -> *const std::os::raw::c_char;
+        #[link_name=#static_symbol]
+        fn #static_id() -> *const std::os::raw::c_char;
+      }
+
+      impl #class {
+        pub const TYPE_NAME: &'static str = #qualified_name;
+        pub const TYPE_ID: u64 = #type_id;
+
+        pub fn type_name(&self) -> &'static std::ffi::CStr {
+          unsafe { std::ffi::CStr::from_ptr(#id(self as *const #class)) }
+        }
+
+        pub fn static_type_name() -> &'static std::ffi::CStr {
+          unsafe { std::ffi::CStr::from_ptr(#static_id()) }
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+fn fnv1a(s: &str) -> u64 {
+  s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, x| (hash ^ u64::from(x)).wrapping_mul(0x0100_0000_01b3))
+}
+
+// The identifier the extern declaration of `symbol` is bound to, hashed from
+// it so the bindings come out the same every time, and prefixed like glue
+// symbols. The hash keeps it lowercase whatever the symbol.
+fn extern_ident(symbol: &str) -> proc_macro2::Ident {
+  format_ident!("__blackbird_{:x}", fnv1a(symbol))
+}
+
+// What identifies an entity from one parse to the next, to hash into the
+// symbols of its glue.
+fn stable_key(e: &Entity) -> String {
+  e.get_usr().map_or_else(|| qualified_name(e), |x| x.0)
+}
+
+impl ItemStruct {
+  fn exports(&self) -> Vec<String> {
+    let mut names = vec![self.name.clone()];
+    if self.subclass.is_some() {
+      names.push(format!("{}Virtuals", self.name));
+    }
+    if self.interface.is_some() {
+      names.push(format!("{}Interface", self.name));
+      names.push(format!("Dyn{}", self.name));
+    }
+    if self.boxed.is_some() {
+      names.push(format!("{}Box", self.name));
+    }
+    if self.polymorphic {
+      names.push(format!("Downcast{}", self.name));
+    }
+    if self.ctor_builder.is_some() {
+      names.push(format!("{}Builder", self.name));
+    }
+    names.extend(self.tagged_unions.iter().map(|x| format!("{}{}", x.class, camel_case(&x.field))));
+    names.extend(self.overload_sets.iter().map(|x| format!("{}{}Args", x.class, camel_case(&x.name))));
+    names
+  }
+}
+
+fn prelude_exports(items: &[Item], path: &[String], cfg: &TokenStream, out: &mut Vec<(TokenStream, Vec<String>)>) {
+  for item in items {
+    let names = match item {
+      Item::Struct(item) => item.exports(),
+      Item::Union(item) => vec![item.name.clone()],
+      Item::Enum(item) => item.name.iter().cloned().collect(),
+      Item::Opaque(item) => vec![item.name.clone()],
+      Item::Alias(item) => vec![item.name.clone()],
+      Item::Float(item) => vec![item.name.clone()],
+      Item::LongDouble(_) => vec!["LongDouble".to_string()],
+      Item::Vector(item) => vec![item.name.clone()],
+      Item::Exception(_) => vec!["CxxException".to_string()],
+      Item::CxxString(_) => vec!["CxxString".to_string(), "CxxStr".to_string()],
+      Item::CxxVector(_) => vec!["CxxVector".to_string(), "VectorElement".to_string()],
+      Item::Wide(_) => vec!["WChar".to_string(), "WideCString".to_string(), "InteriorNul".to_string()],
+      Item::CxxBox(_) => vec!["CxxBox".to_string(), "CxxDelete".to_string()],
+      Item::CxxFunction(_) => vec!["CxxFunction".to_string()],
+      Item::Trait(item) => vec![item.name.clone()],
+      Item::Generic(item) => vec![item.name.clone()],
+      Item::RawMethods(item) => vec![format!("{}Raw", item.class)],
+      Item::Mod(item) => {
+        let mut path = path.to_vec();
+        path.push(item.name.clone());
+        prelude_exports(&item.items, &path, cfg, out);
+        Vec::new()
+      },
+      Item::Cfg(item) => {
+        let targets = &item.targets;
+        let cfg = quote!(#cfg #[cfg(any(#(target_os = #targets),*))]);
+        prelude_exports(&item.items, path, &cfg, out);
+        Vec::new()
+      },
+      _ => Vec::new(),
+    };
+
+    for name in names {
+      let mut path = path.to_vec();
+      path.push(name);
+      out.push((cfg.clone(), path));
+    }
+  }
+}
+
+fn prelude(items: &[Item]) -> TokenStream {
+  let mut exports = Vec::new();
+  prelude_exports(items, &[], &TokenStream::new(), &mut exports);
+
+  // Names defined in more than one namespace would make the glob import
+  // ambiguous, so those are left to be imported by path.
+  let ambiguous = |path: &Vec<String>| exports.iter().any(|(_, x)| x.last() == path.last() && x != path);
+  let uses = exports.iter().filter(|(_, path)| !ambiguous(path)).map(|(cfg, path)| {
+    let path = path.iter().map(|x| format_ident!("{}", x));
+    quote!(#cfg pub use super::#(#path)::*;)
+  });
+
+  quote!(
+    pub mod prelude {
+      #(#uses)*
+    }
+  )
+}
+
+#[derive(Debug)]
+struct ItemUnion {
+  name: String,
+  fields: Vec<Field>,
+  copy: bool,
+  comments: Vec<std::rc::Rc<str>>,
+}
+
+impl ToTokens for ItemUnion {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let name = format_ident!("{}", self.name);
+    let fields = &self.fields;
+    let debug = format!("{} {{ .. }}", self.name);
+
+    let comments = self.comments.iter().map(|x| TokenStream::from_str(x).unwrap());
+
+    let derives = if self.copy {
+      quote!(#[derive(Clone, Copy)])
+    } else {
+      TokenStream::new()
+    };
+
+    quote!(
+      #(#comments)*
+      #[repr(C)]
+      #derives
+      pub union #name {
+        #(#fields),*
+      }
+
+      impl Default for #name {
+        fn default() -> #name {
+          unsafe { std::mem::zeroed() }
+        }
+      }
+
+      impl std::fmt::Debug for #name {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          f.write_str(#debug)
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+#[derive(Debug)]
+struct Variant {
+  name: String,
+  value: i64,
+  ty: TokenStream,
+  manually_drop: bool,
+}
+
+#[derive(Debug)]
+struct TaggedUnion {
+  class: String,
+  tag: String,
+  field: String,
+  variants: Vec<Variant>,
+}
+
+impl ToTokens for TaggedUnion {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    let class = format_ident!("{}", self.class);
+    let tag = format_ident!("{}", self.tag);
+    let field = format_ident!("{}", self.field);
+    let kind = format_ident!("{}{}", self.class, camel_case(&self.field));
+
+    let accessors = self.variants.iter().map(|x| {
+      let name = format_ident!("{}", x.name);
+      let method = format_ident!("as_{}", x.name);
+      let value = x.value;
+      let ty = &x.ty;
+      let deref = if x.manually_drop {
+        quote!(&*)
+      } else {
+        quote!(&)
+      };
+
+      quote!(
+        pub fn #method(&self) -> Option<&#ty> {
+          if self.#tag as i64 == #value {
+            Some(unsafe { #deref self.#field.#name })
+          } else {
+            None
+          }
+        }
+      )
+    });
+
+    let variants = self.variants.iter().map(|x| {
+      let name = format_ident!("{}", camel_case(&x.name));
+      let ty = &x.ty;
+      quote!(#name(&'a #ty))
+    });
+
+    let arms = self.variants.iter().map(|x| {
+      let name = format_ident!("{}", camel_case(&x.name));
+      let method = format_ident!("as_{}", x.name);
+      let value = x.value;
+      quote!(#value => #kind::#name(x.#method().unwrap()))
+    });
+
+    quote!(
+      impl #class {
+        #(#accessors)*
+      }
+
+      #[derive(Debug)]
+      pub enum #kind<'a> {
+        #(#variants,)*
+        Unknown(i64),
+      }
+
+      impl<'a> From<&'a #class> for #kind<'a> {
+        fn from(x: &'a #class) -> #kind<'a> {
+          match x.#tag as i64 {
+            #(#arms,)*
+            tag => #kind::Unknown(tag),
+          }
+        }
+      }
+    ).to_tokens(tokens);
+  }
+}
+
+fn default_argument<'tu>(arg: &Entity<'tu>) -> Option<Entity<'tu>> {
+  arg.get_children().into_iter().find(|x| x.is_expression())
+}
+
+// Arguments of a GCC style attribute such as `nonnull(1, 2)` on `e`, if it
+// has one.
+fn gcc_attribute(e: &Entity, name: &str) -> Option<Vec<usize>> {
+  e.get_children().iter().filter(|x| x.get_kind() == EntityKind::UnexposedAttr).find_map(|attr| {
+    let tokens = attr.get_range()?.tokenize().iter().map(|x| x.get_spelling()).collect::<Vec<_>>();
+    if tokens.first()?.trim_matches('_') != name {
+      return None;
+    }
+    Some(tokens[1..].iter().filter_map(|x| x.parse().ok()).collect())
+  })
+}
+
+// libclang only reports `_Nonnull` and `_Nullable` from version 8, which
+// the `clang_8_0` feature requires. Without it, pointers are only known not
+// to be null from GCC attributes.
+#[cfg(not(feature = "clang_8_0"))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Nullability {
+  NonNull,
+  Nullable,
+}
+
+#[cfg(feature = "clang_8_0")]
+fn type_nullability(ty: &Type) -> Option<Nullability> {
+  ty.get_nullability()
+}
+
+#[cfg(not(feature = "clang_8_0"))]
+fn type_nullability(_: &Type) -> Option<Nullability> {
+  None
+}
+
+fn nullability(arg: &Entity) -> Option<Nullability> {
+  let ty = arg.get_type()?;
+  if ty.get_canonical_type().get_kind() != TypeKind::Pointer {
+    return None;
+  }
+
+  type_nullability(&ty).or_else(|| {
+    let func = arg.get_semantic_parent()?;
+    let index = func.get_arguments()?.iter().position(|x| x == arg)?;
+    // GCC counts the implicit `this` argument of members.
+    let first = match func.get_kind() {
+      EntityKind::Method if !func.is_static_method() => 2,
+      EntityKind::Constructor => 2,
+      _ => 1,
+    };
+    let list = gcc_attribute(&func, "nonnull")?;
+    if list.is_empty() || list.contains(&(index + first)) {
+      Some(Nullability::NonNull)
+    } else {
+      None
+    }
+  })
+}
+
+fn return_nullability(func: &Entity) -> Option<Nullability> {
+  let ty = func.get_result_type()?;
+  if ty.get_canonical_type().get_kind() != TypeKind::Pointer {
+    return None;
+  }
+
+  type_nullability(&ty).or_else(|| gcc_attribute(func, "returns_nonnull").map(|_| Nullability::NonNull))
+}
+
+// The Rust ABI string of a function type's calling convention, or `None`
+// for the default C one.
+fn abi(ty: &Type) -> Option<String> {
+  let abi = match ty.get_canonical_type().get_calling_convention()? {
+    CallingConvention::Stdcall => "stdcall",
+    CallingConvention::Fastcall => "fastcall",
+    CallingConvention::Thiscall => "thiscall",
+    CallingConvention::Vectorcall => "vectorcall",
+    CallingConvention::Win64 => "win64",
+    CallingConvention::SysV64 => "sysv64",
+    CallingConvention::Aapcs => "aapcs",
+    _ => return None,
+  };
+  Some(abi.to_string())
+}
+
+// The name Rust links a symbol clang mangled as `mangled` by, for the target
+// `triple`. Where the object format prefixes C symbols with an underscore,
+// Mach-O and 32-bit Windows, clang's mangled names include it, and on the
+// latter the decorations of `stdcall`, `fastcall` and `vectorcall` too, all
+// of which rustc adds itself. MSVC's C++ names are never prefixed.
+fn link_name(mangled: &str, triple: &str) -> String {
+  let (arch, os, _) = triple_abi(triple);
+  if os == "darwin" || os == "ios" {
+    return mangled.strip_prefix('_').unwrap_or(mangled).to_string();
+  }
+  if arch != "x86" || os != "windows" || mangled.starts_with('?') {
+    return mangled.to_string();
+  }
+
+  let name = mangled.strip_prefix(|x| x == '_' || x == '@').unwrap_or(mangled);
+  match name.rfind('@') {
+    Some(i) if i > 0 && name.len() > i + 1 && name[i + 1..].bytes().all(|x| x.is_ascii_digit()) => {
+      name[..i].trim_end_matches('@').to_string()
+    },
+    _ => name.to_string(),
+  }
+}
+
+// Rewrites the declarations in extern blocks of symbols the glue doesn't
+// define into functions calling through the pointers `library` resolves at
+// runtime, numbering the symbols in `symbols`. Variadic functions can't
+// forward their arguments, so those stay linked.
+fn dynamic_externs(tokens: TokenStream, glue: &HashSet<String>, symbols: &mut Vec<String>) -> TokenStream {
+  let mut out = Vec::new();
+  let mut tokens = tokens.into_iter().peekable();
+  while let Some(token) = tokens.next() {
+    match token {
+      TokenTree::Ident(x) if x == "extern" => {
+        let abi = match tokens.peek() {
+          Some(TokenTree::Literal(x)) => {
+            let x = x.clone();
+            tokens.next();
+            Some(x)
+          },
+          _ => None,
+        };
+        match tokens.peek() {
+          Some(TokenTree::Group(x)) if x.delimiter() == Delimiter::Brace => {
+            let body = x.stream();
+            tokens.next();
+            out.extend(dynamic_block(abi, body, glue, symbols));
+          },
+          _ => {
+            out.push(TokenTree::Ident(x));
+            out.extend(abi.map(TokenTree::Literal));
+          },
+        }
+      },
+      TokenTree::Group(x) => {
+        let mut group = proc_macro2::Group::new(x.delimiter(), dynamic_externs(x.stream(), glue, symbols));
+        group.set_span(x.span());
+        out.push(TokenTree::Group(group));
+      },
+      x => out.push(x),
+    }
+  }
+  out.into_iter().collect()
+}
+
+fn dynamic_block(abi: Option<Literal>, body: TokenStream, glue: &HashSet<String>, symbols: &mut Vec<String>) -> TokenStream {
+  let mut kept = TokenStream::new();
+  let mut functions = TokenStream::new();
+  let mut decl = Vec::new();
+  for token in body {
+    let end = matches!(&token, TokenTree::Punct(x) if x.as_char() == ';');
+    decl.push(token);
+    if !end {
+      continue;
+    }
+    let tokens = std::mem::take(&mut decl);
+    match dynamic_decl(&tokens, abi.as_ref(), glue, symbols) {
+      Some(function) => functions.extend(function),
+      None => kept.extend(tokens),
+    }
+  }
+  kept.extend(decl);
+
+  let kept = if kept.is_empty() { TokenStream::new() } else { quote!(extern #abi { #kept }) };
+  quote!(#kept #functions)
+}
+
+// `#[attrs] [pub] fn name(params) [-> ret];` as a function forwarding to the
+// resolved pointer, if it links to the library.
+fn dynamic_decl(tokens: &[TokenTree], abi: Option<&Literal>, glue: &HashSet<String>, symbols: &mut Vec<String>) -> Option<TokenStream> {
+  let mut attrs = TokenStream::new();
+  let mut symbol = None;
+  let mut i = 0;
+  while let (Some(TokenTree::Punct(hash)), Some(TokenTree::Group(attr))) = (tokens.get(i), tokens.get(i + 1)) {
+    if hash.as_char() != '#' {
+      break;
+    }
+    let attr_tokens = attr.stream().into_iter().collect::<Vec<_>>();
+    match &attr_tokens[..] {
+      [TokenTree::Ident(name), TokenTree::Punct(eq), TokenTree::Literal(value)] if name == "link_name" && eq.as_char() == '=' => {
+        symbol = Some(value.to_string().trim_matches('"').to_string());
+      },
+      _ => attrs.extend(tokens[i..i + 2].iter().cloned()),
+    }
+    i += 2;
+  }
+  let symbol = symbol.filter(|x| !glue.contains(x))?;
+
+  let vis = match tokens.get(i) {
+    Some(TokenTree::Ident(x)) if x == "pub" => {
+      i += 1;
+      quote!(pub)
+    },
+    _ => TokenStream::new(),
+  };
+  let (name, params) = match &tokens[i..] {
+    [TokenTree::Ident(f), TokenTree::Ident(name), TokenTree::Group(params), ..] if f == "fn" && params.delimiter() == Delimiter::Parenthesis => {
+      (name.clone(), params.stream())
+    },
+    _ => return None,
+  };
+  let ret = match &tokens[i + 3..tokens.len() - 1] {
+    [] => quote!(()),
+    [TokenTree::Punct(a), TokenTree::Punct(b), ret @ ..] if a.as_char() == '-' && b.as_char() == '>' => ret.iter().cloned().collect(),
+    _ => return None,
+  };
+
+  let mut types = Vec::new();
+  let mut param = Vec::new();
+  let mut depth = 0;
+  for token in params.into_iter().chain(Some(TokenTree::Punct(proc_macro2::Punct::new(',', proc_macro2::Spacing::Alone)))) {
+    match &token {
+      TokenTree::Punct(x) if x.as_char() == ',' && depth == 0 => {
+        match &param[..] {
+          [] => {},
+          [_, TokenTree::Punct(colon), ty @ ..] if colon.as_char() == ':' && !ty.is_empty() => types.push(ty.iter().cloned().collect::<TokenStream>()),
+          _ => return None,
+        }
+        param.clear();
+        continue;
+      },
+      TokenTree::Punct(x) if x.as_char() == '.' => return None,
+      TokenTree::Punct(x) if x.as_char() == '<' => depth += 1,
+      TokenTree::Punct(x) if x.as_char() == '>' && depth > 0 => depth -= 1,
+      _ => {},
+    }
+    param.push(token);
+  }
+
+  let index = symbols.iter().position(|x| *x == symbol).unwrap_or_else(|| {
+    symbols.push(symbol);
+    symbols.len() - 1
+  });
+  let names = (0..types.len()).map(|i| format_ident!("a{}", i)).collect::<Vec<_>>();
+  let abi = abi.cloned().unwrap_or_else(|| Literal::string("C"));
+  Some(quote!(
+    #attrs
+    #[inline]
+    #vis unsafe fn #name(#(#names: #types),*) -> #ret {
+      let f: unsafe extern #abi fn(#(#types),*) -> #ret = std::mem::transmute(library::symbol(#index));
+      f(#(#names),*)
+    }
+  ))
+}
+
+// The `library` module loading what the bindings call into at runtime.
+fn library_module(symbols: &[String]) -> TokenStream {
+  let count = symbols.len();
+  let names = symbols.iter().map(|x| format!("{}\0", x));
+
+  quote!(
+    pub mod library {
+      use std::os::raw::{c_char, c_void};
+      use std::sync::atomic::{AtomicPtr, Ordering};
+
+      #[cfg(unix)]
+      mod sys {
+        use std::os::raw::{c_char, c_int, c_void};
+
+        #[cfg_attr(target_os = "linux", link(name = "dl"))]
+        extern "C" {
+          fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+          pub fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+          fn dlerror() -> *mut c_char;
+        }
+
+        pub unsafe fn open(path: &std::ffi::OsStr) -> Result<*mut c_void, String> {
+          use std::os::unix::ffi::OsStrExt;
+          let path = std::ffi::CString::new(path.as_bytes()).map_err(|x| x.to_string())?;
+          // RTLD_NOW
+          let handle = dlopen(path.as_ptr(), 2);
+          if handle.is_null() {
+            let error = dlerror();
+            return Err(if error.is_null() {
+              "unknown error".to_string()
+            } else {
+              std::ffi::CStr::from_ptr(error).to_string_lossy().into_owned()
+            });
+          }
+          Ok(handle)
+        }
+
+        pub use self::dlsym as symbol;
+      }
+
+      #[cfg(windows)]
+      mod sys {
+        use std::os::raw::{c_char, c_void};
+
+        extern "system" {
+          fn LoadLibraryW(name: *const u16) -> *mut c_void;
+          fn GetProcAddress(module: *mut c_void, name: *const c_char) -> *mut c_void;
+        }
+
+        pub unsafe fn open(path: &std::ffi::OsStr) -> Result<*mut c_void, String> {
+          use std::os::windows::ffi::OsStrExt;
+          let path = path.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+          let handle = LoadLibraryW(path.as_ptr());
+          if handle.is_null() {
+            return Err(std::io::Error::last_os_error().to_string());
+          }
+          Ok(handle)
+        }
+
+        pub unsafe fn symbol(handle: *mut c_void, name: *const c_char) -> *mut c_void {
+          GetProcAddress(handle, name)
+        }
+      }
+
+      #[derive(Debug, Clone, PartialEq, Eq)]
+      pub enum LoadError {
+        Open(String),
+        MissingSymbol(&'static str),
+      }
+
+      impl std::fmt::Display for LoadError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+          match self {
+            LoadError::Open(x) => write!(f, "could not open the library: {}", x),
+            LoadError::MissingSymbol(x) => write!(f, "the library doesn't define {}", x),
+          }
+        }
+      }
+
+      impl std::error::Error for LoadError {}
+
+      static NAMES: [&str; #count] = [#(#names),*];
+      static CURRENT: AtomicPtr<Library> = AtomicPtr::new(std::ptr::null_mut());
+
+      /// The library the bindings call into, with every symbol they use
+      /// resolved.
+      #[derive(Debug)]
+      pub struct Library {
+        symbols: Vec<*mut c_void>,
+      }
+
+      unsafe impl Send for Library {}
+      unsafe impl Sync for Library {}
+
+      impl Library {
+        /// Opens the library at `path`, which is never closed, failing if
+        /// it doesn't define everything the bindings call.
+        pub unsafe fn open<P: AsRef<std::ffi::OsStr>>(path: P) -> Result<Library, LoadError> {
+          let handle = sys::open(path.as_ref()).map_err(LoadError::Open)?;
+          let mut symbols = Vec::with_capacity(NAMES.len());
+          for name in NAMES.iter() {
+            let symbol = sys::symbol(handle, name.as_ptr() as *const c_char);
+            if symbol.is_null() {
+              return Err(LoadError::MissingSymbol(name.trim_end_matches('\0')));
+            }
+            symbols.push(symbol);
+          }
+          Ok(Library { symbols })
+        }
+
+        /// Makes the bindings call into this library from now on. A library
+        /// installed before is leaked rather than dropped, as calls into it
+        /// may still be running.
+        pub fn install(self) {
+          CURRENT.swap(Box::into_raw(Box::new(self)), Ordering::AcqRel);
+        }
+      }
+
+      #[doc(hidden)]
+      pub fn symbol(index: usize) -> *mut c_void {
+        let library = CURRENT.load(Ordering::Acquire);
+        assert!(!library.is_null(), "the bindings were called before a library was installed");
+        unsafe { (&(*library).symbols)[index] }
+      }
+    }
+  )
+}
+
+// The link names of everything bound directly to the library rather than
+// through the glue, along with the item they belong to.
+fn link_names(items: &[Item], prefix: &str, out: &mut Vec<(String, String)>) {
+  for item in items {
+    match item {
+      Item::Mod(item) => link_names(&item.items, &format!("{}{}::", prefix, item.name), out),
+      Item::Cfg(item) => link_names(&item.items, prefix, out),
+      Item::Fn(item) => out.push((item.symbol.clone(), format!("{}{}", prefix, item.name))),
+      Item::Var(item) => out.push((item.symbol.clone(), format!("{}{}", prefix, item.name))),
+      Item::Struct(item) => {
+        for x in &item.constructors {
+          out.push((x.symbol.clone(), format!("{}{}::{}", prefix, item.name, x.method)));
+        }
+        if let Some(x) = &item.destructor {
+          out.push((x.symbol.clone(), format!("{}{}::drop", prefix, item.name)));
+        }
+        let methods = item.methods.iter().chain(item.overload_sets.iter().flat_map(|x| &x.methods));
+        for x in methods {
+          out.push((x.symbol.clone(), format!("{}{}::{}", prefix, item.name, x.name)));
+        }
+        for x in &item.static_methods {
+          out.push((x.symbol.clone(), format!("{}{}::{}", prefix, item.name, x.name)));
+        }
+      },
+      _ => {},
+    }
+  }
+}
+
+// Checks the library defines every symbol the bindings link to, using `nm`,
+// other than those of the functions in the glue.
+fn verify_symbols(library: &std::path::Path, items: &[Item], glue: &HashSet<String>) -> Result<(), Error> {
+  let output = match std::process::Command::new("nm").arg("-g").arg("--defined-only").arg(library).output() {
+    Ok(x) if x.status.success() => x,
+    _ => {
+      println!("cargo:warning=could not read the symbols of {}, skipping verification", library.display());
+      return Ok(());
+    },
+  };
+
+  let defined = String::from_utf8_lossy(&output.stdout).lines()
+    .filter_map(|x| x.split_whitespace().last().map(|x| x.to_string()))
+    .collect::<HashSet<_>>();
+
+  let mut symbols = Vec::new();
+  link_names(items, "", &mut symbols);
+
+  // Symbols may or may not have kept their leading underscore.
+  let missing = symbols.iter()
+    .filter(|(symbol, _)| !glue.contains(symbol))
+    .filter(|(symbol, _)| !defined.contains(symbol) && !defined.contains(&format!("_{}", symbol)))
+    .map(|(symbol, name)| format!("{} ({})", symbol, name))
+    .collect::<Vec<_>>();
+
+  if !missing.is_empty() {
+    return Err(Error::MissingSymbols { library: library.to_path_buf(), symbols: missing });
+  }
+  Ok(())
+}
+
+// The parts of a target triple that decide the C++ ABI: the architecture,
+// the OS and the environment, if any. Vendors and OS versions are ignored.
+fn triple_abi(triple: &str) -> (String, String, Option<String>) {
+  let parts = triple.split('-').collect::<Vec<_>>();
+  let arch = match parts[0] {
+    "i386" | "i486" | "i586" | "i686" => "x86",
+    "amd64" => "x86_64",
+    "arm64" => "aarch64",
+    x => x,
+  };
+
+  let os = parts[1..].iter().position(|x| {
+    ["linux", "windows", "darwin", "macos", "ios", "freebsd", "netbsd", "openbsd", "android", "mingw", "none"].iter().any(|os| x.starts_with(os))
+  }).map(|x| x + 1);
+  let (os, env) = match os {
+    Some(i) if parts[i].starts_with("mingw") => ("windows".to_string(), Some("gnu".to_string())),
+    Some(i) => {
+      let os = parts[i].trim_end_matches(|x: char| x.is_ascii_digit() || x == '.');
+      let os = if os == "macos" || os == "macosx" { "darwin" } else { os };
+      (os.to_string(), parts.get(i + 1).map(|x| x.trim_end_matches(|x: char| x.is_ascii_digit() || x == '.').to_string()))
+    },
+    None => (parts.last().unwrap().to_string(), None),
+  };
+  (arch.to_string(), os, env)
+}
+
+// The sizes of the C types `std::os::raw` has for the target being built
+// for, from what cargo tells build scripts about it.
+fn rust_c_sizes() -> Option<Vec<(&'static str, usize)>> {
+  let pointer = std::env::var("CARGO_CFG_TARGET_POINTER_WIDTH").ok()?.parse::<usize>().ok()? / 8;
+  let os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+  let arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+  let small = arch == "avr" || arch == "msp430";
+  Some(vec![
+    ("short", 2),
+    ("int", if small { 2 } else { 4 }),
+    ("long", if os == "windows" || pointer < 8 { 4 } else { 8 }),
+    ("long long", 8),
+    ("float", 4),
+    ("double", if arch == "avr" { 4 } else { 8 }),
+    ("void*", pointer),
+  ])
+}
+
+// Checks the glue compiler targets the ABI the header was parsed for, as
+// far as it can say which; mangled names and layouts silently disagree
+// otherwise.
+fn check_compiler_target(build: &cc::Build, triple: &str) -> Result<(), Error> {
+  let compiler = build.get_compiler();
+  let output = match compiler.to_command().arg("-dumpmachine").output() {
+    Ok(x) if x.status.success() => x,
+    _ => return Ok(()),
+  };
+  let machine = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+  let (arch, os, env) = triple_abi(&machine);
+  let (parsed_arch, parsed_os, parsed_env) = triple_abi(triple);
+  let env_matches = match (env, parsed_env) {
+    (Some(x), Some(y)) => x == y,
+    _ => true,
+  };
+  if arch != parsed_arch || os != parsed_os || !env_matches {
+    return Err(Error::CompilerMismatch { compiler: compiler.path().to_path_buf(), machine, triple: triple.to_string() });
+  }
+  Ok(())
+}
+
+// The JSON form of the IR written by `Builder::ir`, for tools that want
+// blackbird's view of an API without linking libclang. Types are given as
+// the Rust tokens they are bound as.
+#[cfg(feature = "ir")]
+mod ir {
+  use proc_macro2::TokenStream;
+  use serde::Serialize;
+  use std::rc::Rc;
+  use super::{Arg, Field, Item};
+
+  // Version of the format. The major version is bumped when fields are
+  // removed or change meaning, the minor version when fields are added.
+  const SCHEMA_VERSION: &str = "1.12";
+
+  #[derive(Serialize)]
+  pub(crate) struct Document {
+    schema: &'static str,
+    header: String,
+    items: Vec<Node>,
+  }
+
+  impl Document {
+    pub(crate) fn new(header: &str, items: &[Item]) -> Document {
+      Document { schema: SCHEMA_VERSION, header: header.to_string(), items: items.iter().map(Node::new).collect() }
+    }
+  }
+
+  #[derive(Serialize)]
+  #[serde(tag = "kind")]
+  enum Node {
+    #[serde(rename = "mod")]
+    Mod { name: String, items: Vec<Node>, comments: Vec<String> },
+    #[serde(rename = "cfg")]
+    Cfg { targets: Vec<String>, items: Vec<Node> },
+    #[serde(rename = "fn")]
+    Fn(Function),
+    #[serde(rename = "struct")]
+    Struct {
+      name: String,
+      fields: Vec<Member>,
+      constructor: Option<Constructor>,
+      constructors: Vec<Constructor>,
+      destructor: Option<String>,
+      methods: Vec<Function>,
+      static_methods: Vec<Function>,
+      polymorphic: bool,
+      copy: bool,
+      comments: Vec<String>,
+    },
+    #[serde(rename = "union")]
+    Union { name: String, fields: Vec<Member>, copy: bool, comments: Vec<String> },
+    #[serde(rename = "enum")]
+    Enum { name: Option<String>, style: String, repr: String, variants: Vec<Variant>, comments: Vec<String> },
+    #[serde(rename = "opaque")]
+    Opaque { name: String, size: usize, align: usize, comments: Vec<String> },
+    #[serde(rename = "exception")]
+    Exception { name: &'static str },
+    #[serde(rename = "string")]
+    CxxString { name: &'static str, size: usize, align: usize },
+    #[serde(rename = "wide_string")]
+    Wide { name: &'static str, elem: String },
+    #[serde(rename = "box")]
+    CxxBox { name: &'static str, elements: Vec<String> },
+    #[serde(rename = "function")]
+    CxxFunction { name: &'static str, signatures: Vec<String> },
+    #[serde(rename = "trait")]
+    Trait { name: String, methods: Vec<String>, impls: Vec<String> },
+    #[serde(rename = "raw_methods")]
+    RawMethods { class: String, methods: Vec<String> },
+    #[serde(rename = "generic")]
+    Generic { name: String, param: String, instances: Vec<Instance>, size: usize, align: usize },
+    #[serde(rename = "symbols")]
+    Symbols { entries: Vec<Symbol> },
+    #[serde(rename = "cxx_vector")]
+    CxxVector { name: &'static str, size: usize, align: usize, elements: Vec<String> },
+    #[serde(rename = "float")]
+    Float { name: String, format: &'static str },
+    #[serde(rename = "long_double")]
+    LongDouble { name: &'static str, size: usize, align: usize },
+    #[serde(rename = "vector")]
+    Vector { name: String, elem: String, lanes: usize, align: usize },
+    #[serde(rename = "const")]
+    Const {
+      name: String,
+      #[serde(rename = "type")]
+      ty: String,
+      value: String,
+      comments: Vec<String>,
+    },
+    #[serde(rename = "alias")]
+    Alias {
+      name: String,
+      #[serde(rename = "type")]
+      ty: String,
+      comments: Vec<String>,
+    },
+    #[serde(rename = "var")]
+    Var {
+      name: String,
+      symbol: String,
+      #[serde(rename = "type")]
+      ty: String,
+      comments: Vec<String>,
+    },
+  }
+
+  // A function or method.
+  #[derive(Serialize)]
+  struct Function {
+    name: String,
+    symbol: String,
+    args: Vec<Param>,
+    ret: String,
+    abi: Option<String>,
+    safe: bool,
+    comments: Vec<String>,
+  }
+
+  #[derive(Serialize)]
+  struct Param {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    ty: String,
+  }
+
+  #[derive(Serialize)]
+  struct Member {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    public: bool,
+  }
+
+  // Constructors of a struct. The first is also given alone, as it was
+  // before overloads were, without its name.
+  #[derive(Serialize)]
+  struct Constructor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    symbol: String,
+    args: Vec<Param>,
+  }
+
+  #[derive(Serialize)]
+  struct Variant {
+    name: String,
+    value: String,
+    comments: Vec<String>,
+  }
+
+  #[derive(Serialize)]
+  struct Instance {
+    value: u64,
+    name: String,
+  }
+
+  #[derive(Serialize)]
+  struct Symbol {
+    name: String,
+    usr: String,
+    path: String,
+    link_name: Option<String>,
+  }
+
+  fn strings<S: ToString>(items: &[S]) -> Vec<String> {
+    items.iter().map(|x| x.to_string()).collect()
+  }
+
+  fn params(args: &[Arg]) -> Vec<Param> {
+    args.iter().map(|x| Param { name: x.0.clone(), ty: x.1.to_string() }).collect()
+  }
+
+  fn members(fields: &[Field]) -> Vec<Member> {
+    fields.iter().map(|x| Member { name: x.1.clone(), ty: x.2.to_string(), public: x.0 }).collect()
+  }
+
+  fn function(name: &str, symbol: &str, args: &[Arg], ret: &TokenStream, abi: &Option<String>, safe: bool, comments: &[Rc<str>]) -> Function {
+    Function {
+      name: name.to_string(),
+      symbol: symbol.to_string(),
+      args: params(args),
+      ret: ret.to_string(),
+      abi: abi.clone(),
+      safe,
+      comments: strings(comments),
+    }
+  }
+
+  impl Node {
+    fn new(item: &Item) -> Node {
+      match item {
+        Item::Mod(item) => Node::Mod { name: item.name.clone(), items: item.items.iter().map(Node::new).collect(), comments: strings(&item.comments) },
+        Item::Cfg(item) => Node::Cfg { targets: strings(&item.targets), items: item.items.iter().map(Node::new).collect() },
+        Item::Fn(item) => Node::Fn(function(&item.name, &item.symbol, &item.args, &item.ret, &item.abi, item.safe, &item.comments)),
+        Item::Struct(item) => {
+          let methods = item.methods.iter().chain(item.overload_sets.iter().flat_map(|x| &x.methods));
+          Node::Struct {
+            name: item.name.clone(),
+            fields: members(&item.fields),
+            constructor: item.constructors.first().map(|x| Constructor { name: None, symbol: x.symbol.clone(), args: params(&x.args) }),
+            constructors: item.constructors.iter().map(|x| Constructor { name: Some(x.method.clone()), symbol: x.symbol.clone(), args: params(&x.args) }).collect(),
+            destructor: item.destructor.as_ref().map(|x| x.symbol.clone()),
+            methods: methods.map(|x| function(&x.name, &x.symbol, &x.args, &x.ret, &x.abi, x.safe, &x.comments)).collect(),
+            static_methods: item.static_methods.iter().map(|x| function(&x.name, &x.symbol, &x.args, &x.ret, &x.abi, x.safe, &x.comments)).collect(),
+            polymorphic: item.polymorphic,
+            copy: item.copy,
+            comments: strings(&item.comments),
+          }
+        },
+        Item::Union(item) => Node::Union { name: item.name.clone(), fields: members(&item.fields), copy: item.copy, comments: strings(&item.comments) },
+        Item::Enum(item) => Node::Enum {
+          name: item.name.clone(),
+          style: format!("{:?}", item.style),
+          repr: item.repr.to_string(),
+          variants: item.variants.iter().map(|x| Variant { name: x.name.clone(), value: x.value.to_string(), comments: strings(&x.comments) }).collect(),
+          comments: strings(&item.comments),
+        },
+        Item::Opaque(item) => Node::Opaque { name: item.name.clone(), size: item.size, align: item.align, comments: strings(&item.comments) },
+        Item::Exception(_) => Node::Exception { name: "CxxException" },
+        Item::CxxString(item) => Node::CxxString { name: "CxxString", size: item.size, align: item.align },
+        Item::Wide(item) => Node::Wide { name: "WideCString", elem: item.ty.to_string() },
+        Item::CxxBox(item) => Node::CxxBox { name: "CxxBox", elements: item.elements.iter().map(|x| x.elem.to_string()).collect() },
+        Item::CxxFunction(item) => Node::CxxFunction { name: "CxxFunction", signatures: item.signatures.iter().map(|x| x.ty().to_string()).collect() },
+        Item::Trait(item) => Node::Trait {
+          name: item.name.clone(),
+          methods: item.methods.iter().map(|x| x.signature.to_string()).collect(),
+          impls: item.impls.iter().map(|x| x.1.clone()).collect(),
+        },
+        Item::RawMethods(item) => Node::RawMethods { class: item.class.clone(), methods: item.methods.iter().map(|x| x.signature().to_string()).collect() },
+        Item::Generic(item) => Node::Generic {
+          name: item.name.clone(),
+          param: item.param.clone(),
+          instances: item.instances.iter().map(|x| Instance { value: x.0, name: x.1.clone() }).collect(),
+          size: item.size,
+          align: item.align,
+        },
+        Item::Symbols(item) => Node::Symbols {
+          entries: item.entries.iter().map(|x| Symbol { name: x.name.clone(), usr: x.usr.clone(), path: x.path.clone(), link_name: x.link_name.clone() }).collect(),
+        },
+        Item::CxxVector(item) => Node::CxxVector { name: "CxxVector", size: item.size, align: item.align, elements: item.elements.iter().map(|x| x.cpp.clone()).collect() },
+        Item::Float(item) => Node::Float { name: item.name.clone(), format: if item.brain { "bfloat16" } else { "binary16" } },
+        Item::LongDouble(item) => Node::LongDouble { name: "LongDouble", size: item.size, align: item.align },
+        Item::Vector(item) => Node::Vector { name: item.name.clone(), elem: item.elem.clone(), lanes: item.lanes, align: item.align },
+        Item::Const(item) => Node::Const { name: item.name.clone(), ty: item.ty.to_string(), value: item.value.to_string(), comments: strings(&item.comments) },
+        Item::Alias(item) => Node::Alias { name: item.name.clone(), ty: item.ty.to_string(), comments: strings(&item.comments) },
+        Item::Var(item) => Node::Var { name: item.name.clone(), symbol: item.symbol.clone(), ty: item.ty.to_string(), comments: strings(&item.comments) },
+      }
+    }
+  }
+}
+
+// How a fuzz harness feeds each argument of a function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FuzzArg {
+  Scalar,
+  Bool,
+  Buffer,
+  Length,
+}
+
+// Only scalars and byte buffers directly followed by their length can be
+// made up from fuzz input.
+fn fuzz_args(args: &[Entity]) -> Option<Vec<FuzzArg>> {
+  let mut out = Vec::new();
+  for arg in args {
+    let ty = arg.get_type()?.get_canonical_type();
+    let bytes = ty.get_pointee_type().is_some_and(|x| matches!(
+      x.get_canonical_type().get_kind(),
+      TypeKind::Void | TypeKind::CharS | TypeKind::CharU | TypeKind::SChar | TypeKind::UChar
+    ));
+
+    let kind = match ty.get_kind() {
+      TypeKind::Bool => FuzzArg::Bool,
+      TypeKind::Pointer if bytes && !out.contains(&FuzzArg::Buffer) => FuzzArg::Buffer,
+      _ if ty.is_integer() && out.last() == Some(&FuzzArg::Buffer) => FuzzArg::Length,
+      TypeKind::Float | TypeKind::Double => FuzzArg::Scalar,
+      _ if ty.is_integer() => FuzzArg::Scalar,
+      _ => return None,
+    };
+    out.push(kind);
+  }
+
+  let buffers = out.iter().filter(|x| **x == FuzzArg::Buffer).count();
+  let lengths = out.iter().filter(|x| **x == FuzzArg::Length).count();
+  if out.is_empty() || buffers != lengths {
+    None
+  } else {
+    Some(out)
+  }
+}
+
+fn fuzz_harnesses(items: &[Item], module: &TokenStream, path: &[String], out: &mut Vec<(String, TokenStream)>) {
+  for item in items {
+    match item {
+      Item::Mod(item) => {
+        let mut path = path.to_vec();
+        path.push(item.name.clone());
+        fuzz_harnesses(&item.items, module, &path, out);
+      },
+      Item::Cfg(item) => fuzz_harnesses(&item.items, module, path, out),
+      Item::Fn(item) if item.fuzz.is_some() => {
+        let decode = item.fuzz.as_ref().unwrap().iter().zip(&item.args).enumerate().map(|(i, (kind, arg))| {
+          let name = format_ident!("a{}", i);
+          let ty = &arg.1;
+          match kind {
+            FuzzArg::Scalar => quote!(
+              if data.len() < std::mem::size_of::<#ty>() {
+                return;
+              }
+              let (#name, data) = data.split_at(std::mem::size_of::<#ty>());
+              let #name = <#ty>::from_ne_bytes(#name.try_into().unwrap());
+            ),
+            FuzzArg::Bool => quote!(
+              if data.is_empty() {
+                return;
+              }
+              let (#name, data) = data.split_at(1);
+              let #name = #name[0] & 1 != 0;
+            ),
+            FuzzArg::Buffer | FuzzArg::Length => quote!(),
+          }
+        });
+
+        let values = item.fuzz.as_ref().unwrap().iter().zip(&item.args).enumerate().map(|(i, (kind, arg))| {
+          let name = format_ident!("a{}", i);
+          let ty = &arg.1;
+          match kind {
+            FuzzArg::Scalar | FuzzArg::Bool => quote!(#name),
+            FuzzArg::Buffer => quote!(buffer.as_mut_ptr() as #ty),
+            FuzzArg::Length => quote!(buffer.len() as #ty),
+          }
+        });
+
+        let path_idents = path.iter().map(|x| format_ident!("{}", x));
+        let name = format_ident!("{}", item.name);
+
+        let file = path.iter().chain(std::iter::once(&item.name)).map(|x| x.trim_start_matches("r#")).collect::<Vec<_>>().join("_");
+        out.push((file, quote!(
+          #![no_main]
+          use std::convert::TryInto;
+          use libfuzzer_sys::fuzz_target;
+
+          fuzz_target!(|data: &[u8]| {
+            #(#decode)*
+            // Whatever is left over is the buffer.
+            #[allow(unused_mut, unused_variables)]
+            let mut buffer = data.to_vec();
+            #[allow(unused_unsafe)]
+            unsafe {
+              let _ = #module::#(#path_idents::)*#name(#(#values),*);
+            }
+          });
+        )));
+      },
+      _ => {},
+    }
+  }
+}
+
+// Whether a parameter type takes a string: `Some(false)` for a C string,
+// `Some(true)` for a `std::string`, by value or const reference.
+fn string_kind(ty: &Type) -> Option<bool> {
+  let ty = ty.get_canonical_type();
+  match ty.get_kind() {
+    TypeKind::Pointer => {
+      let pointee = ty.get_pointee_type().unwrap();
+      let char = matches!(pointee.get_kind(), TypeKind::CharS | TypeKind::CharU);
+      if char && pointee.is_const_qualified() { Some(false) } else { None }
+    },
+    TypeKind::LValueReference => {
+      let pointee = ty.get_pointee_type().unwrap();
+      if pointee.is_const_qualified() { string_kind(&pointee).filter(|x| *x) } else { None }
+    },
+    TypeKind::Record => {
+      let decl = ty.get_declaration()?;
+      let name = qualified_name(&decl);
+      let chars = ty.get_template_argument_types()?.first().cloned().flatten()?;
+      let string = ["std::basic_string", "std::__cxx11::basic_string", "std::__1::basic_string"].contains(&name.as_str())
+        && matches!(chars.get_canonical_type().get_kind(), TypeKind::CharS | TypeKind::CharU);
+      if string { Some(true) } else { None }
+    },
+    _ => None,
+  }
+}
+
+// The element type of a `std::vector`.
+fn vector_element<'tu>(ty: &Type<'tu>) -> Option<Type<'tu>> {
+  let ty = ty.get_canonical_type();
+  if ty.get_kind() != TypeKind::Record {
+    return None;
+  }
+  let name = qualified_name(&ty.get_declaration()?);
+  if !["std::vector", "std::__1::vector"].contains(&name.as_str()) {
+    return None;
+  }
+  ty.get_template_argument_types()?.first().cloned().flatten()
+}
+
+// The function type of a `std::function`, taken by value or reference.
+fn function_signature<'tu>(ty: &Type<'tu>) -> Option<Type<'tu>> {
+  let ty = ty.get_canonical_type();
+  let ty = match ty.get_kind() {
+    TypeKind::LValueReference | TypeKind::RValueReference => ty.get_pointee_type()?.get_canonical_type(),
+    _ => ty,
+  };
+  if ty.get_kind() != TypeKind::Record {
+    return None;
+  }
+  let name = qualified_name(&ty.get_declaration()?);
+  if !["std::function", "std::__1::function"].contains(&name.as_str()) {
+    return None;
+  }
+  ty.get_template_argument_types()?.first().cloned().flatten().filter(|x| x.get_kind() == TypeKind::FunctionPrototype)
+}
+
+// Whether `ty` is a `std::string` or `std::vector` by value, which can't be
+// passed across `extern "C"`.
+fn library_value(ty: &Type) -> bool {
+  ty.get_canonical_type().get_kind() == TypeKind::Record && (string_kind(ty) == Some(true) || vector_element(ty).is_some())
+}
+
+fn is_void_pointer(ty: &Type) -> bool {
+  let ty = ty.get_canonical_type();
+  ty.get_kind() == TypeKind::Pointer && ty.get_pointee_type().is_some_and(|x| x.get_kind() == TypeKind::Void && !x.is_const_qualified())
+}
+
+fn has_large_array(ty: &Type) -> bool {
+  let ty = ty.get_canonical_type();
+  match ty.get_kind() {
+    TypeKind::ConstantArray => ty.get_size().unwrap() > 32 || has_large_array(&ty.get_element_type().unwrap()),
+    _ => false,
+  }
+}
+
+fn is_borrowed(ty: &TokenStream) -> bool {
+  let ty = ty.to_string();
+  ty.starts_with('&') || ty.starts_with("Option < &")
+}
+
+// Whether `ty` is const, which for arrays clang has on the element type.
+fn const_qualified(ty: &Type) -> bool {
+  match ty.get_kind() {
+    TypeKind::ConstantArray | TypeKind::IncompleteArray => const_qualified(&ty.get_element_type().unwrap()),
+    _ => ty.is_const_qualified(),
+  }
+}
+
+// Declares `name` as the type clang spells `ty`, which for pointers and
+// references to arrays and functions goes inside the parentheses, as in
+// `int (*name)[4]`, and for arrays before the brackets.
+fn declare(ty: &str, name: &str) -> String {
+  let bytes = ty.as_bytes();
+  let mut depth = 0;
+  for (i, &c) in bytes.iter().enumerate() {
+    match c {
+      b'<' => depth += 1,
+      b'>' => depth -= 1,
+      b'(' if depth == 0 && matches!(bytes.get(i + 1), Some(b'*' | b'&')) => {
+        let rest = &ty[i + 1..];
+        let end = rest.find(|x: char| !matches!(x, '*' | '&' | ' ' | 'a'..='z')).unwrap_or(rest.len());
+        if rest[end..].starts_with(')') {
+          let space = if rest[..end].ends_with(|x: char| x.is_ascii_lowercase()) { " " } else { "" };
+          return format!("{}{}{}{}", &ty[..i + 1 + end], space, name, &rest[end..]);
+        }
+      },
+      b'[' if depth == 0 => return format!("{} {}{}", ty[..i].trim_end(), name, &ty[i..]),
+      _ => {},
+    }
+  }
+  format!("{} {}", ty, name)
+}
+
+// Debug assertions for the glue shim arguments `a0..` that must not be null.
+fn null_checks(args: &[Entity]) -> String {
+  args.iter().enumerate()
+    .filter(|(_, arg)| nullability(arg) == Some(Nullability::NonNull))
+    .map(|(i, _)| format!("assert(a{} != nullptr); ", i))
+    .collect()
+}
+
+fn default_value(arg: &Entity, tokens: TokenStream) -> Option<TokenStream> {
+  let expr = default_argument(arg)?;
+  let ty = arg.get_type()?;
+
+  match expr.evaluate() {
+    Some(EvaluationResult::SignedInteger(x)) => {
+      let x = Literal::i64_unsuffixed(x);
+      Some(quote!(#x as #tokens))
+    },
+    Some(EvaluationResult::UnsignedInteger(x)) => {
+      let x = Literal::u64_unsuffixed(x);
+      Some(quote!(#x as #tokens))
+    },
+    Some(EvaluationResult::Float(x)) => {
+      let x = Literal::f64_unsuffixed(x);
+      Some(quote!(#x as #tokens))
+    },
+    Some(EvaluationResult::String(x)) => {
+      let x = Literal::byte_string(x.as_bytes_with_nul());
+      Some(quote!(#x.as_ptr() as #tokens))
+    },
+    _ => {
+      let spelling = expr.get_range()?.tokenize().iter().map(|x| x.get_spelling()).collect::<String>();
+      let null = ["nullptr", "NULL", "0"].contains(&spelling.as_str());
+      if ty.get_canonical_type().get_kind() == TypeKind::Pointer && null {
+        Some(quote!(std::ptr::null_mut()))
+      } else {
+        None
+      }
+    },
+  }
+}
+
+fn unify_overloads(strukt: &mut ItemStruct) {
+  let mut names: Vec<String> = Vec::new();
+  for method in &strukt.methods {
+    if !names.contains(&method.name) {
+      names.push(method.name.clone());
+    }
+  }
+
+  for name in names {
+    let (overloads, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut strukt.methods).into_iter().partition(|x| x.name == name);
+    strukt.methods = rest;
+
+    let signatures = overloads.iter().map(|x| x.args.iter().map(|x| x.1.to_string()).collect::<Vec<_>>()).collect::<Vec<_>>();
+    let unifiable = overloads.len() > 1
+      && overloads.iter().all(|x| x.ret.to_string() == overloads[0].ret.to_string())
+      && signatures.iter().enumerate().all(|(i, x)| !signatures[..i].contains(x))
+      // The argument enums don't carry a lifetime.
+      && overloads.iter().all(|x| !x.args.iter().any(|x| is_borrowed(&x.1)))
+      && overloads.iter().all(|x| x.outs.is_empty() && x.slices.is_empty() && x.error.is_none() && x.shim == Shim::default() && x.preconditions.is_empty());
+
+    if unifiable {
+      strukt.overload_sets.push(OverloadSet {
+        class: strukt.name.clone(),
+        name,
+        methods: overloads,
+      });
+    } else {
+      strukt.methods.extend(overloads);
+    }
+  }
+}
+
+// Gives overloads bound as separate functions distinct names. The first keeps
+// the C++ name, the others are suffixed with their arity, or the types of
+// their arguments if another overload takes as many.
+fn rename_overloads(scope: &str, callbacks: Option<&dyn Callbacks>, mut functions: Vec<(&mut String, &[Arg])>) {
+  let names = functions.iter().map(|x| x.0.clone()).collect::<Vec<_>>();
+  let mut used = names.iter().cloned().collect::<HashSet<_>>();
+
+  for (i, name) in names.iter().enumerate() {
+    let overloads = (0..names.len()).filter(|&j| names[j] == *name).collect::<Vec<_>>();
+    if overloads.len() < 2 {
+      continue;
+    }
+
+    let index = overloads.iter().position(|&j| j == i).unwrap();
+    let args = functions[i].1;
+    let renamed = match callbacks.and_then(|x| x.overload_name(&format!("{}{}", scope, name), index)) {
+      Some(x) => x,
+      None if index == 0 => continue,
+      None => {
+        let base = if overloads.iter().filter(|&&j| functions[j].1.len() == args.len()).count() == 1 {
+          format!("{}_{}", name, args.len())
+        } else {
+          std::iter::once(name.clone()).chain(args.iter().map(|x| type_suffix(&x.1))).collect::<Vec<_>>().join("_")
+        };
+
+        let mut renamed = base.clone();
+        let mut i = 2;
+        while used.contains(&renamed) {
+          renamed = format!("{}_{}", base, i);
+          i += 1;
+        }
+        renamed
+      },
+    };
+
+    used.insert(renamed.clone());
+    *functions[i].0 = renamed;
+  }
+}
+
+// A type as part of an identifier, `*const std::os::raw::c_char` becoming
+// `const_char`.
+fn type_suffix(ty: &TokenStream) -> String {
+  ty.to_string()
+    .split(|x: char| !x.is_alphanumeric() && x != '_')
+    .filter(|x| !x.is_empty() && !["std", "os", "raw", "ptr"].contains(x))
+    .map(|x| x.trim_start_matches("c_").to_lowercase())
+    .collect::<Vec<_>>()
+    .join("_")
+}
+
+// Renames static methods whose names are taken by other members of the
+// class, C++ allowing a static and an instance overload to share one.
+fn resolve_static_names(strukt: &mut ItemStruct) {
+  let mut used = strukt.methods.iter().map(|x| x.name.clone())
+    .chain(strukt.overload_sets.iter().map(|x| x.name.clone()))
+    .chain(strukt.constructors.iter().map(|x| x.method.clone()))
+    .collect::<Vec<_>>();
+  let generated: &[(bool, &[&str])] = &[
+    (strukt.boxed.is_some(), &["new_boxed"]),
+    (strukt.aggregate_constructor, &["new"]),
+    (strukt.ctor_builder.is_some(), &["builder"]),
+    (strukt.subclass.is_some(), &["new_subclass", "delete_subclass"]),
+    (strukt.polymorphic, &["downcast_ref", "downcast_mut", "type_name", "static_type_name"]),
+  ];
+  for (present, names) in generated {
+    if *present {
+      used.extend(names.iter().map(|x| x.to_string()));
+    }
+  }
+
+  for method in &mut strukt.static_methods {
+    if used.contains(&method.name) {
+      let base = format!("{}_static", method.name);
+      let mut name = base.clone();
+      let mut i = 2;
+      while used.contains(&name) {
+        name = format!("{}_{}", base, i);
+        i += 1;
+      }
+      method.name = name;
+    }
+    used.push(method.name.clone());
+  }
+}
+
+// Patterns are globs, so `ns::*` matches everything declared in `ns`. Ones
+// naming an operator, like `operator*`, and ones that aren't valid globs,
+// like `operator[]`, only match literally, as every pattern did before globs.
+fn matches_name(e: &Entity, pattern: &str) -> bool {
+  e.get_name().is_some_and(|x| matches_pattern(&x, pattern)) || matches_pattern(&qualified_name(e), pattern)
+}
+
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+  let operator = pattern.rsplit("::").next().is_some_and(|x| x.starts_with("operator"));
+  let glob = if operator { None } else { glob::Pattern::new(pattern).ok() };
+  name == pattern || glob.is_some_and(|x| x.matches(name))
+}
+
+fn qualified_name(e: &Entity) -> String {
+  let mut path = vec![e.get_name().unwrap_or_default()];
+  let mut parent = e.get_semantic_parent();
+  while let Some(x) = parent {
+    if x.get_kind() == EntityKind::TranslationUnit {
+      break;
+    }
+    path.insert(0, x.get_name().unwrap_or_default());
+    parent = x.get_semantic_parent();
+  }
+  path.join("::")
+}
+
+// The conditions of the `assert`s and gsl `Expects` a function's body
+// starts with, as the spellings of their tokens.
+fn leading_asserts(func: &Entity) -> Vec<Vec<String>> {
+  let tokens = match func.get_range() {
+    Some(x) => x.tokenize().into_iter().map(|x| x.get_spelling()).collect::<Vec<_>>(),
+    None => return Vec::new(),
+  };
+  let mut i = match tokens.iter().position(|x| x == "{") {
+    Some(x) => x + 1,
+    None => return Vec::new(),
+  };
+
+  let mut conditions = Vec::new();
+  while i + 1 < tokens.len() && (tokens[i] == "assert" || tokens[i] == "Expects") && tokens[i + 1] == "(" {
+    let start = i + 2;
+    let mut end = start;
+    let mut depth = 1;
+    while end < tokens.len() {
+      match tokens[end].as_str() {
+        "(" => depth += 1,
+        ")" if depth == 1 => break,
+        ")" => depth -= 1,
+        _ => {},
+      }
+      end += 1;
+    }
+    if end == tokens.len() {
+      break;
+    }
+
+    conditions.push(tokens[start..end].to_vec());
+    i = end + 1;
+    if tokens.get(i).map(|x| x.as_str()) == Some(";") {
+      i += 1;
+    }
+  }
+  conditions
+}
+
+// Translates an assert condition made of `&&`ed null checks of pointer
+// arguments and comparisons of integer arguments with each other or with
+// literals into a check per clause. Anything else isn't translated at all,
+// and checks of references are dropped, those never being null.
+fn translate_condition(tokens: &[String], args: &[&Arg]) -> Option<Vec<TokenStream>> {
+  let arg = |x: &[String]| match x {
+    [x] => args.iter().find(|arg| arg.0.as_deref() == Some(x.as_str())),
+    _ => None,
+  };
+  let null = |x: &[String]| matches!(x, [x] if x == "nullptr" || x == "NULL" || x == "0");
+  let not_null = |arg: &Arg| {
+    let name = format_ident!("{}", arg.0.as_ref().unwrap());
+    let ty = arg.1.to_string();
+    if ty.starts_with('*') {
+      Some(Some(quote!(!#name.is_null())))
+    } else if ty.starts_with("Option") {
+      Some(Some(quote!(#name.is_some())))
+    } else if ty.starts_with('&') || ty.starts_with("std :: ptr :: NonNull") {
+      Some(None)
+    } else {
+      None
+    }
+  };
+  let operand = |x: &[String]| -> Option<(TokenStream, Option<String>)> {
+    if let Some(arg) = arg(x) {
+      if !is_integer(&arg.1) {
+        return None;
+      }
+      let name = format_ident!("{}", arg.0.as_ref().unwrap());
+      return Some((quote!(#name), Some(arg.1.to_string())));
+    }
+
+    let (sign, digits) = match x {
+      [sign, digits] if sign == "-" => ("-", digits),
+      [digits] => ("", digits),
+      _ => return None,
+    };
+    let digits = digits.trim_end_matches(|x| "uUlL".contains(x));
+    let valid = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+      Some(hex) => u64::from_str_radix(hex, 16).is_ok(),
+      None => digits.parse::<u64>().is_ok(),
+    };
+    if !valid {
+      return None;
+    }
+    Some((TokenStream::from_str(&format!("{}{}", sign, digits)).ok()?, None))
+  };
+
+  let mut checks = Vec::new();
+  for clause in tokens.split(|x| x == "&&") {
+    let mut clause = clause;
+    while clause.len() > 2 && clause[0] == "(" && clause[clause.len() - 1] == ")" && balanced(&clause[1..clause.len() - 1]) {
+      clause = &clause[1..clause.len() - 1];
+    }
+
+    let check = match clause.iter().position(|x| ["==", "!=", "<", "<=", ">", ">="].contains(&x.as_str())) {
+      None => not_null(arg(clause)?)?,
+      Some(i) if clause[i] == "!=" && null(&clause[i + 1..]) => not_null(arg(&clause[..i])?)?,
+      Some(i) if clause[i] == "!=" && null(&clause[..i]) => not_null(arg(&clause[i + 1..])?)?,
+      Some(i) => {
+        let (lhs, lhs_ty) = operand(&clause[..i])?;
+        let (rhs, rhs_ty) = operand(&clause[i + 1..])?;
+        match (lhs_ty, rhs_ty) {
+          (None, None) => return None,
+          (Some(x), Some(y)) if x != y => return None,
+          _ => {},
+        }
+        let op = TokenStream::from_str(&clause[i]).ok()?;
+        Some(quote!(#lhs #op #rhs))
+      },
+    };
+    checks.extend(check);
+  }
+  Some(checks)
+}
+
+fn balanced(tokens: &[String]) -> bool {
+  let mut depth = 0;
+  for x in tokens {
+    match x.as_str() {
+      "(" => depth += 1,
+      ")" if depth == 0 => return false,
+      ")" => depth -= 1,
+      _ => {},
+    }
+  }
+  depth == 0
+}
+
+fn is_integer(ty: &TokenStream) -> bool {
+  let ty = ty.to_string();
+  let last = ty.rsplit(':').next().unwrap().trim();
+  [
+    "c_char", "c_schar", "c_uchar", "c_short", "c_ushort", "c_int", "c_uint", "c_long", "c_ulong", "c_longlong", "c_ulonglong",
+    "i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64", "isize", "usize",
+  ].contains(&last) && !ty.starts_with('*')
+}
+
+// Copy and move constructors are named for what they do, the others are
+// overloads of `new`.
+fn constructor_name(e: &Entity) -> &'static str {
+  if e.is_copy_constructor() {
+    "new_copy"
+  } else if e.is_move_constructor() {
+    "new_move"
+  } else {
+    "new"
+  }
+}
+
+// The name of a class template's parameter, if it's its only one and an
+// integer, given the typedef of one of its instantiations.
+fn integer_parameter(typedef: Entity) -> Option<String> {
+  let ty = typedef.get_typedef_underlying_type()?.get_canonical_type();
+  let template = ty.get_declaration()?.get_template()?;
+  let params = template.get_children().into_iter()
+    .filter(|x| matches!(
+      x.get_kind(),
+      EntityKind::TemplateTypeParameter | EntityKind::NonTypeTemplateParameter | EntityKind::TemplateTemplateParameter
+    ))
+    .collect::<Vec<_>>();
+  match &params[..] {
+    [x] if x.get_kind() == EntityKind::NonTypeTemplateParameter && x.get_type()?.is_integer() => x.get_name(),
+    _ => None,
+  }
+}
+
+// The Rust name of an instantiation, `ns::SmallVec<int, 8>` becoming
+// `SmallVec_int_8`.
+fn instance_name(spelling: &str) -> String {
+  let start = spelling[..spelling.find('<').unwrap_or(spelling.len())].rfind("::").map_or(0, |x| x + 2);
+  spelling[start..]
+    .split(|x: char| !x.is_alphanumeric() && x != '_')
+    .filter(|x| !x.is_empty())
+    .collect::<Vec<_>>()
+    .join("_")
+}
+
+// The arguments of a template-id as spelled, split at the top level commas.
+fn template_arguments(spelling: &str) -> Vec<String> {
+  let inner = match (spelling.find('<'), spelling.rfind('>')) {
+    (Some(start), Some(end)) if start < end => &spelling[start + 1..end],
+    _ => return Vec::new(),
+  };
+
+  let mut args = Vec::new();
+  let mut depth = 0;
+  let mut arg = String::new();
+  for x in inner.chars() {
+    match x {
+      '<' | '(' | '[' => depth += 1,
+      '>' | ')' | ']' => depth -= 1,
+      ',' if depth == 0 => {
+        args.push(arg.trim().to_string());
+        arg.clear();
+        continue;
+      },
+      _ => {},
+    }
+    arg.push(x);
+  }
+  args.push(arg.trim().to_string());
+  args
+}
+
+// Replaces the template parameters named in a type spelled inside the
+// template with the arguments they were given.
+fn substitute(spelling: &str, params: &[String], args: &[String]) -> String {
+  let mut out = String::new();
+  let mut ident = String::new();
+  for x in spelling.chars().chain(std::iter::once(' ')) {
+    if x.is_alphanumeric() || x == '_' {
+      ident.push(x);
+      continue;
+    }
+
+    match params.iter().position(|x| *x == ident) {
+      Some(i) if i < args.len() => out += &args[i],
+      _ => out += &ident,
+    }
+    ident.clear();
+    out.push(x);
+  }
+  out.pop();
+  out
+}
+
+// The declarations a `using` declaration brings into scope.
+fn using_targets<'tu>(e: &Entity<'tu>) -> Vec<Entity<'tu>> {
+  e.get_children().into_iter().flat_map(|x| match x.get_kind() {
+    EntityKind::OverloadedDeclRef => x.get_overloaded_declarations().unwrap_or_default(),
+    EntityKind::NamespaceRef | EntityKind::TypeRef | EntityKind::TemplateRef => Vec::new(),
+    _ => x.get_reference().into_iter().collect(),
+  }).collect()
+}
+
+fn base_classes<'tu>(e: &Entity<'tu>) -> Vec<(Entity<'tu>, Option<Accessibility>)> {
+  e.get_children().into_iter().filter(|x| x.get_kind() == EntityKind::BaseSpecifier).filter_map(|x| {
+    let base = x.get_type()?.get_canonical_type().get_declaration()?;
+    Some((base.get_definition().unwrap_or(base), x.get_accessibility()))
+  }).collect()
+}
+
+// Classes laid out in no space as a base.
+fn is_empty_class(e: &Entity) -> bool {
+  e.get_children().iter().all(|x| x.get_kind() != EntityKind::FieldDecl && !(x.get_kind() == EntityKind::BaseSpecifier && x.is_virtual_base()))
+    && !is_polymorphic(e)
+    && base_classes(e).iter().all(|(base, _)| is_empty_class(base))
+}
+
+// Whether values of `ty` hold a vtable pointer, in themselves, a base or a
+// field.
+fn has_vtable(ty: &Type) -> bool {
+  let ty = ty.get_canonical_type();
+  match ty.get_kind() {
+    TypeKind::Record => ty.get_declaration().is_some_and(|x| {
+      let x = x.get_definition().unwrap_or(x);
+      is_polymorphic(&x)
+        || x.get_children().iter().any(|x| match x.get_kind() {
+          EntityKind::BaseSpecifier => x.is_virtual_base() || x.get_type().is_some_and(|x| has_vtable(&x)),
+          EntityKind::FieldDecl => x.get_type().is_some_and(|x| has_vtable(&x)),
+          _ => false,
+        })
+    }),
+    TypeKind::ConstantArray => has_vtable(&ty.get_element_type().unwrap()),
+    _ => false,
+  }
+}
+
+fn is_polymorphic(e: &Entity) -> bool {
+  e.get_children().iter().any(|x| match x.get_kind() {
+    EntityKind::Method | EntityKind::Destructor => x.is_virtual_method(),
+    _ => false,
+  }) || base_classes(e).iter().any(|(base, _)| is_polymorphic(base))
+}
+
+fn public_ancestors<'tu>(e: &Entity<'tu>) -> Vec<Entity<'tu>> {
+  let mut ancestors = Vec::new();
+  for (base, accessibility) in base_classes(e) {
+    if accessibility == Some(Accessibility::Public) {
+      for ancestor in public_ancestors(&base).into_iter().chain(Some(base)) {
+        if !ancestors.contains(&ancestor) {
+          ancestors.push(ancestor);
+        }
+      }
+    }
+  }
+  ancestors
+}
+
+fn snake_case(s: &str) -> String {
+  let chars = s.chars().collect::<Vec<_>>();
+  let mut out = String::new();
+  for (i, c) in chars.iter().enumerate() {
+    if c.is_uppercase() && i > 0 {
+      let prev = chars[i - 1];
+      let next_lower = chars.get(i + 1).is_some_and(|x| x.is_lowercase());
+      if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower) {
+        out.push('_');
+      }
+    }
+    out.extend(c.to_lowercase());
+  }
+  out
+}
+
+// The name of the module a namespace is bound as.
+fn module_name(namespace: &str) -> String {
+  let name = snake_case(namespace);
+  match name.as_str() {
+    "crate" | "self" | "super" | "Self" => format!("{}_", name),
+    x if KEYWORDS.contains(&x) => format!("r#{}", name),
+    _ => name,
+  }
+}
+
+const KEYWORDS: &[&str] = &[
+  "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do",
+  "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+  "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self",
+  "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+  "virtual", "where", "while", "yield",
+];
+
+const QT_MOC_MEMBERS: &[&str] = &[
+  "staticMetaObject", "metaObject", "qt_metacast", "qt_metacall", "qt_static_metacall",
+  "tr", "trUtf8", "QPrivateSignal", "qt_check_for_QGADGET_macro",
+];
+
+// Defines for the Qt macros that need moc, in case the Qt headers themselves
+// aren't included. When parsing, access specifiers of signals are annotated
+// either way.
+const QT_ANNOTATE: &str = "-DQT_ANNOTATE_ACCESS_SPECIFIER(x)=__attribute__((annotate(#x)))";
+const QT_DEFINES: &[&str] = &[
+  "-DQT_ANNOTATE_ACCESS_SPECIFIER(x)=",
+  "-DQ_SIGNALS=public QT_ANNOTATE_ACCESS_SPECIFIER(qt_signal)",
+  "-Dsignals=Q_SIGNALS",
+  "-DQ_SLOTS=",
+  "-Dslots=",
+  "-DQ_EMIT=",
+  "-Demit=",
+  "-DQ_OBJECT=",
+  "-DQ_GADGET=",
+  "-DQ_INVOKABLE=",
+  "-DQ_PROPERTY(...)=",
+  "-DQ_ENUM(x)=",
+  "-DQ_ENUMS(x)=",
+  "-DQ_FLAG(x)=",
+  "-DQ_FLAGS(x)=",
+  "-DQ_INTERFACES(x)=",
+  "-DQ_CLASSINFO(name, value)=",
+];
+
+fn sanitize_modules(items: Vec<Item>, scope: &str, callbacks: Option<&dyn Callbacks>) -> Vec<Item> {
+  let mut out: Vec<Item> = Vec::new();
+  for item in items {
+    if let Item::Mod(item) = item {
+      // Reopened namespaces end up as one module.
+      let existing = out.iter_mut().find_map(|x| match x {
+        Item::Mod(x) if x.name == item.name => Some(x),
+        _ => None,
+      });
+
+      if let Some(existing) = existing {
+        existing.items.extend(item.items);
+        existing.comments.extend(item.comments);
+      } else {
+        out.push(Item::Mod(item));
+      }
+    } else {
+      out.push(item);
+    }
+  }
+
+  let functions = out.iter_mut().filter_map(|x| match x {
+    Item::Fn(x) => Some((&mut x.name, &x.args[..])),
+    _ => None,
+  });
+  rename_overloads(scope, callbacks, functions.collect());
+
+  let mut used = Vec::new();
+  for item in &mut out {
+    if let Item::Mod(item) = item {
+      let scope = format!("{}{}::", scope, item.name);
+      let base = module_name(&item.name);
+
+      let mut name = base.clone();
+      let mut i = 2;
+      while used.contains(&name) {
+        name = format!("{}_{}", base, i);
+        i += 1;
+      }
+
+      used.push(name.clone());
+      item.name = name;
+      item.items = sanitize_modules(std::mem::take(&mut item.items), &scope, callbacks);
+    }
+  }
+
+  out
+}
+
+fn idents(tokens: TokenStream, out: &mut HashSet<String>) {
+  for token in tokens {
+    match token {
+      proc_macro2::TokenTree::Ident(x) => {
+        out.insert(x.to_string());
+      },
+      proc_macro2::TokenTree::Group(x) => idents(x.stream(), out),
+      _ => {},
+    }
+  }
+}
+
+// Orders the types of each module after the ones their fields contain, so
+// the bindings read top-down. Fields that are pointers don't count, which
+// keeps types referring to each other in the order they were declared.
+fn order_items(items: Vec<Item>) -> Vec<Item> {
+  let names = items.iter().map(|x| match x {
+    Item::Struct(x) => Some(x.name.clone()),
+    Item::Union(x) => Some(x.name.clone()),
+    Item::Enum(x) => x.name.clone(),
+    Item::Opaque(x) => Some(x.name.clone()),
+    _ => None,
+  }).collect::<Vec<_>>();
+
+  let deps = items.iter().map(|x| {
+    let fields = match x {
+      Item::Struct(x) => &x.fields,
+      Item::Union(x) => &x.fields,
+      _ => return Vec::new(),
+    };
+    let mut used = HashSet::new();
+    for field in fields {
+      if field.2.to_string().starts_with('*') {
+        continue;
+      }
+      idents(field.2.clone(), &mut used);
+    }
+    names.iter().enumerate().filter(|(_, x)| x.as_ref().is_some_and(|x| used.contains(x))).map(|(i, _)| i).collect()
+  }).collect::<Vec<Vec<usize>>>();
+
+  fn visit(i: usize, deps: &[Vec<usize>], seen: &mut Vec<bool>, order: &mut Vec<usize>) {
+    if seen[i] {
+      return;
+    }
+    seen[i] = true;
+    for &dep in &deps[i] {
+      visit(dep, deps, seen, order);
+    }
+    order.push(i);
+  }
+
+  let mut seen = vec![false; items.len()];
+  let mut order = Vec::new();
+  for i in 0..items.len() {
+    visit(i, &deps, &mut seen, &mut order);
+  }
+
+  let mut items = items.into_iter().map(|x| match x {
+    Item::Mod(mut x) => {
+      x.items = order_items(x.items);
+      Some(Item::Mod(x))
+    },
+    x => Some(x),
+  }).collect::<Vec<_>>();
+  order.into_iter().map(|i| items[i].take().unwrap()).collect()
+}
+
+fn camel_case(s: &str) -> String {
+  s.split('_').map(|x| {
+    let mut chars = x.chars();
+    match chars.next() {
+      Some(c) => c.to_uppercase().chain(chars).collect(),
+      None => String::new(),
+    }
+  }).collect()
+}
+
+// Declarations reachable from the allowlist, by USR. Records only used behind
+// pointers or references are bound as opaque types instead of walked.
+#[derive(Debug, Default)]
+struct Reachable {
+  full: HashSet<String>,
+  opaque: HashSet<String>,
+}
+
+impl Reachable {
+  fn new(tu: Entity, allowlist: &[(AllowKind, String)], used: &HashSet<String>, blocklist: &[String]) -> Reachable {
+    let mut reachable = Reachable::default();
+    let mut roots = Vec::new();
+    find_allowlisted(tu, allowlist, used, blocklist, false, &mut roots);
+    for e in roots {
+      reachable.visit(e);
+    }
+    reachable
+  }
+
+  fn contains(&self, e: &Entity) -> bool {
+    e.get_usr().is_some_and(|x| self.full.contains(&x.0))
+  }
+
+  fn is_opaque(&self, e: &Entity) -> bool {
+    e.get_usr().is_some_and(|x| self.opaque.contains(&x.0))
+  }
+
+  fn visit(&mut self, e: Entity) {
+    if !e.get_usr().is_some_and(|x| self.full.insert(x.0)) {
+      return;
+    }
+
+    let e = e.get_definition().unwrap_or(e);
+    match e.get_kind() {
+      EntityKind::FunctionDecl => self.visit_function(e),
+      EntityKind::VarDecl | EntityKind::TypedefDecl => {
+        if let Some(ty) = e.get_type().or_else(|| e.get_typedef_underlying_type()) {
+          self.visit_type(ty, true);
+        }
+      },
+      _ => {},
+    }
+
+    for child in e.get_children() {
+      match child.get_kind() {
+        EntityKind::FieldDecl | EntityKind::BaseSpecifier => self.visit_type(child.get_type().unwrap(), true),
+        EntityKind::Method | EntityKind::Constructor => self.visit_function(child),
+        _ => {},
+      }
+    }
+  }
+
+  fn visit_function(&mut self, e: Entity) {
+    for arg in e.get_arguments().unwrap_or_default() {
+      self.visit_type(arg.get_type().unwrap(), true);
+    }
+    if let Some(ty) = e.get_result_type() {
+      self.visit_type(ty, true);
+    }
+  }
+
+  fn visit_type(&mut self, ty: Type, by_value: bool) {
+    // Aliases are bound along with what they name.
+    let named = if ty.get_kind() == TypeKind::Elaborated { ty.get_elaborated_type().unwrap_or(ty) } else { ty };
+    if named.get_kind() == TypeKind::Typedef {
+      if let Some(decl) = named.get_declaration() {
+        self.visit(decl);
+      }
+    }
+    let ty = ty.get_canonical_type();
+    match ty.get_kind() {
+      TypeKind::Pointer | TypeKind::LValueReference | TypeKind::RValueReference => {
+        self.visit_type(ty.get_pointee_type().unwrap(), false);
+      },
+      TypeKind::ConstantArray | TypeKind::IncompleteArray => {
+        self.visit_type(ty.get_element_type().unwrap(), by_value);
+      },
+      TypeKind::FunctionPrototype => {
+        for arg in ty.get_argument_types().unwrap_or_default() {
+          self.visit_type(arg, true);
+        }
+        self.visit_type(ty.get_result_type().unwrap(), true);
+      },
+      TypeKind::Record if !by_value => {
+        if let Some(usr) = ty.get_declaration().and_then(|x| x.get_usr()) {
+          self.opaque.insert(usr.0);
+        }
+      },
+      TypeKind::Record | TypeKind::Enum => {
+        self.visit(ty.get_declaration().unwrap());
+      },
+      _ => {},
+    }
+  }
+}
+
+// Which kinds of items an allowlist entry applies to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AllowKind {
+  Any,
+  Type,
+  Function,
+  // Everything declared in the namespace.
+  Namespace,
+}
+
+impl AllowKind {
+  fn matches(self, kind: EntityKind) -> bool {
+    match self {
+      AllowKind::Any => kind != EntityKind::Namespace,
+      AllowKind::Type => matches!(
+        kind,
+        EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl | EntityKind::EnumDecl | EntityKind::TypedefDecl
+      ),
+      AllowKind::Function => kind == EntityKind::FunctionDecl,
+      AllowKind::Namespace => kind == EntityKind::Namespace,
+    }
+  }
+}
+
+// Names the Rust code refers to allow items, but not whole namespaces, which
+// are named in the paths to everything in them.
+fn find_allowlisted<'tu>(
+  e: Entity<'tu>,
+  allowlist: &[(AllowKind, String)],
+  used: &HashSet<String>,
+  blocklist: &[String],
+  all: bool,
+  out: &mut Vec<Entity<'tu>>,
+) {
+  let allowed = |child: &Entity| {
+    all
+      || (child.get_kind() != EntityKind::Namespace && child.get_name().is_some_and(|x| used.contains(&x)))
+      || allowlist.iter().any(|(kind, x)| kind.matches(child.get_kind()) && matches_name(child, x))
+  };
+  for child in e.get_children() {
+    if blocklist.iter().any(|x| matches_name(&child, x)) {
+      continue;
+    }
+
+    match child.get_kind() {
+      EntityKind::Namespace => find_allowlisted(child, allowlist, used, blocklist, allowed(&child), out),
+      EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl | EntityKind::EnumDecl |
+      EntityKind::FunctionDecl | EntityKind::VarDecl | EntityKind::TypedefDecl if allowed(&child) => out.push(child),
+      _ => {},
+    }
+  }
+}
+
+// The `.rs` files under `dir`, at any depth.
+fn rust_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), Error> {
+  for entry in fs::read_dir(dir).map_err(io_error(dir))? {
+    let path = entry.map_err(io_error(dir))?.path();
+    if path.is_dir() {
+      rust_files(&path, out)?;
+    } else if path.extension().is_some_and(|x| x == "rs") {
+      out.push(path);
+    }
+  }
+  Ok(())
+}
+
+// Every identifier in Rust source, comments and strings included. Finding
+// too many only binds a little more than needed.
+fn rust_identifiers(source: &str, out: &mut HashSet<String>) {
+  let mut start = None;
+  for (i, c) in source.char_indices().chain(Some((source.len(), ' '))) {
+    match start {
+      None if c.is_ascii_alphabetic() || c == '_' => start = Some(i),
+      Some(x) if !c.is_ascii_alphanumeric() && c != '_' => {
+        out.insert(source[x..i].to_string());
+        start = None;
+      },
+      _ => {},
+    }
+  }
+}
+
+#[derive(Debug)]
+struct State<'a> {
+  builder: &'a Builder,
+  // What the header was parsed for, which decides the symbols it links to.
+  triple: String,
+  glue: String,
+  glue_header: String,
+  reachable: Option<Reachable>,
+  // Support types emitted at the root when something maps to them.
+  helpers: std::cell::RefCell<Vec<Item>>,
+  // USRs of the records whose fields are being looked through, innermost
+  // last.
+  resolving: std::cell::RefCell<Vec<String>>,
+  // Where the entity being processed is, and the first error hit. Errors
+  // don't stop the walk, what failed is left out of the bindings instead.
+  location: std::cell::RefCell<String>,
+  error: std::cell::RefCell<Option<Error>>,
+  instance: std::cell::RefCell<Option<Instance>>,
+  references: Vec<CrossReference>,
+  glue_symbols: std::cell::RefCell<HashSet<String>>,
+  // Comment lines, which repeat across items, like `///` on its own or
+  // boilerplate shared by overloads, kept once and shared.
+  strings: std::cell::RefCell<HashSet<std::rc::Rc<str>>>,
+  // Those given to `Builder::precondition`, parsed.
+  preconditions: &'a [(String, TokenStream)],
+}
+
+// What an item was bound from, for the `symbols` module. Functions are
+// known by the symbol they link to until overloads are renamed, and types
+// by their path.
+#[derive(Debug)]
+struct CrossReference {
+  name: String,
+  usr: String,
+  path: Option<String>,
+  symbol: Option<String>,
+}
+
+// The class template being instantiated, by USR, with the Rust types of its
+// arguments and the name of the instantiation.
+#[derive(Debug)]
+struct Instance {
+  usr: String,
+  args: Vec<Option<TokenStream>>,
+  name: String,
+}
+
+// How long each phase of generation took, in order. Phases that run once per
+// target are listed once for each. With the `tracing` feature, each phase
+// also runs in a span.
+#[derive(Default)]
+struct Timings(Vec<(&'static str, std::time::Duration)>);
+
+impl Timings {
+  fn time<T, F: FnOnce() -> T>(&mut self, phase: &'static str, f: F) -> T {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("blackbird", phase).entered();
+    let start = std::time::Instant::now();
+    let x = f();
+    self.0.push((phase, start.elapsed()));
+    x
+  }
+
+  fn report(&self) {
+    let total = self.0.iter().map(|x| x.1).sum::<std::time::Duration>();
+    for (phase, time) in &self.0 {
+      println!("cargo:warning={}: {:.2?}", phase, time);
+    }
+    println!("cargo:warning=total: {:.2?}", total);
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Context {
+  ns: Vec<String>,
+}
+
+impl Context {
+  fn qualify(&self, name: &str) -> String {
+    let mut path = self.ns.clone();
+    path.push(name.to_string());
+    path.join("::")
+  }
+}
+
+impl State<'_> {
+  fn map_type(&self, ty: &Type) -> TokenStream {
+    let ty = match ty.get_kind() {
+      TypeKind::Elaborated => ty.get_elaborated_type().unwrap_or(*ty),
+      _ => *ty,
+    };
+    if ty.get_kind() == TypeKind::Typedef {
+      if let Some(decl) = ty.get_declaration().filter(|x| self.alias_bound(x)) {
+        return self.type_path(&decl);
+      }
+    }
+    let root = ty.get_canonical_type();
+
+    if let Some(tokens) = self.map_instance(&root) {
+      return tokens;
+    }
+
+    // Newer kinds like `__bf16` are unknown to the bindings, so they have to
+    // be told apart by name before asking for the kind.
+    if root.get_display_name() == "__bf16" {
+      return self.float_type("BFloat16", "bf16", true);
+    }
+
+    match root.get_kind() {
+      // References have the same representation as pointers.
+      TypeKind::Pointer | TypeKind::LValueReference | TypeKind::RValueReference => {
+        let pointee = root.get_pointee_type().unwrap();
+        if let TypeKind::FunctionPrototype | TypeKind::FunctionNoPrototype = pointee.get_kind() {
+          let abi = Literal::string(abi(&pointee).as_deref().unwrap_or("C"));
+          let args = pointee.get_argument_types().unwrap_or_default().iter().map(|x| self.map_type(x)).collect::<Vec<_>>();
+          let variadic = if pointee.is_variadic() && !args.is_empty() { Some(quote!(, ...)) } else { None };
+          let ret = self.map_type(&pointee.get_result_type().unwrap());
+          return quote!(Option<unsafe extern #abi fn(#(#args),* #variadic) -> #ret>);
+        }
+
+        // The pointee as written, which may be an alias.
+        let tokens = match ty.get_kind() {
+          TypeKind::Pointer | TypeKind::LValueReference | TypeKind::RValueReference => self.map_type(&ty.get_pointee_type().unwrap()),
+          _ => self.map_type(&pointee),
+        };
+        if const_qualified(&pointee) {
+          quote!(*const #tokens)
+        } else {
+          quote!(*mut #tokens)
+        }
+      },
+
+      TypeKind::Record if string_kind(&root) == Some(true) => self.string_type(&root),
+      TypeKind::Record if vector_element(&root).is_some() => self.cxx_vector_type(&root),
+      TypeKind::Record => self.type_path(&root.get_declaration().unwrap()),
+
+      TypeKind::ConstantArray => {
+        let elem = self.map_type(&root.get_element_type().unwrap());
+        let len = Literal::usize_unsuffixed(root.get_size().unwrap());
+        quote!([#elem; #len])
+      },
+
+      // Flexible array members take no space in the struct.
+      TypeKind::IncompleteArray => {
+        let elem = self.map_type(&root.get_element_type().unwrap());
+        quote!([#elem; 0])
+      },
+
+      TypeKind::Enum => {
+        let decl = root.get_declaration().unwrap();
+        let path = self.type_path(&decl);
+        if self.enum_style(&decl) == EnumStyle::ModuleConsts && self.builder.existing_type(&decl).is_none() {
+          quote!(#path::Type)
+        } else {
+          path
+        }
+      },
+
+      TypeKind::Void => quote!(std::os::raw::c_void),
+      TypeKind::CharS | TypeKind::CharU => match self.builder.char_style {
+        CharStyle::CChar => quote!(std::os::raw::c_char),
+        CharStyle::Signed => quote!(i8),
+        CharStyle::Unsigned => quote!(u8),
+        CharStyle::Target if root.get_kind() == TypeKind::CharS => quote!(i8),
+        CharStyle::Target => quote!(u8),
+      },
+      TypeKind::SChar if self.builder.char_style == CharStyle::CChar => quote!(std::os::raw::c_schar),
+      TypeKind::UChar if self.builder.char_style == CharStyle::CChar => quote!(std::os::raw::c_uchar),
+      TypeKind::SChar => quote!(i8),
+      TypeKind::UChar => quote!(u8),
+      TypeKind::Short => quote!(std::os::raw::c_short),
+      TypeKind::UShort => quote!(std::os::raw::c_ushort),
+      TypeKind::Int => quote!(std::os::raw::c_int),
+      TypeKind::UInt => quote!(std::os::raw::c_uint),
+      TypeKind::Long => quote!(std::os::raw::c_long),
+      TypeKind::ULong => quote!(std::os::raw::c_ulong),
+      TypeKind::LongLong => quote!(std::os::raw::c_longlong),
+      TypeKind::ULongLong => quote!(std::os::raw::c_ulonglong),
+      TypeKind::Float => quote!(std::os::raw::c_float),
+      TypeKind::Double => quote!(std::os::raw::c_double),
+      // `long double` is a `double` on MSVC and 32-bit ARM.
+      TypeKind::LongDouble if root.get_sizeof() == Ok(8) => quote!(std::os::raw::c_double),
+      TypeKind::LongDouble => self.long_double_type(&root),
+      TypeKind::Bool => quote!(bool),
+      TypeKind::Char16 => quote!(u16),
+      TypeKind::Char32 => quote!(u32),
+      TypeKind::Int128 => quote!(i128),
+      TypeKind::UInt128 => quote!(u128),
+      TypeKind::WChar => {
+        let bits = root.get_sizeof().unwrap() * 8;
+        let prefix = if root.is_signed_integer() { "i" } else { "u" };
+        let ty = format_ident!("{}{}", prefix, bits);
+        quote!(#ty)
+      },
+      TypeKind::Half | TypeKind::Float16 => self.float_type("Float16", "f16", false),
+      TypeKind::Vector => self.vector_type(&root),
+      _ => {
+        let location = root.get_declaration().map_or_else(|| self.location.borrow().clone(), |x| location(&x));
+        self.fail(Error::UnsupportedType { ty: root.get_display_name(), location });
+        quote!(())
+      },
+    }
+  }
+
+  // Instantiated templates, and the parameters and class of the template
+  // being instantiated, which are dependent types clang can't lay out.
+  fn map_instance(&self, root: &Type) -> Option<TokenStream> {
+    if self.builder.instantiations.is_empty() {
+      return None;
+    }
+
+    let spelling = root.get_display_name().replace(' ', "");
+    let instantiation = self.builder.instantiations.iter().find(|x| {
+      let x = x.replace(' ', "");
+      spelling == x || spelling.ends_with(&format!("::{}", x))
+    });
+    if let Some(x) = instantiation {
+      let name = format_ident!("{}", instance_name(x));
+      return Some(quote!(#name));
+    }
+
+    let instance = self.instance.borrow();
+    let instance = instance.as_ref()?;
+    let parameter = spelling.trim_start_matches("const").trim_start_matches("volatile");
+    if let Some(i) = parameter.strip_prefix("type-parameter-0-") {
+      return instance.args.get(i.parse::<usize>().ok()?).cloned().flatten();
+    }
+    if root.get_declaration().and_then(|x| x.get_usr()).is_some_and(|x| x.0 == instance.usr) {
+      let name = format_ident!("{}", instance.name);
+      return Some(quote!(#name));
+    }
+    None
+  }
+
+  // The path of the module the namespace of `decl` is bound as, ending in
+  // `::` unless it's the root. It's relative to the root of the bindings,
+  // which every module imports, or starts at `module_path` when given, so it
+  // resolves from any module.
+  fn module_prefix(&self, decl: &Entity) -> TokenStream {
+    let mut path = Vec::new();
+    let mut parent = decl.get_semantic_parent();
+    while let Some(x) = parent {
+      match x.get_kind() {
+        EntityKind::Namespace => path.push(format_ident!("{}", module_name(&x.get_name().unwrap()))),
+        // Types nested in a named class are bound in its module.
+        EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl => {
+          if let Some(name) = x.get_name() {
+            path.push(format_ident!("{}", module_name(&name)));
+          }
+        },
+        _ => {},
+      }
+      parent = x.get_semantic_parent();
+    }
+    path.reverse();
+
+    // Checked before parsing, by `check_paths`.
+    let root = self.builder.module_path.as_ref().and_then(|x| TokenStream::from_str(x).ok()).map(|x| quote!(#x::));
+    quote!(#root #(#path::)*)
+  }
+
+  // Whether the alias `decl` is bound, for signatures to refer to it. Ones
+  // in classes, system headers or for types of the same name aren't.
+  fn alias_bound(&self, decl: &Entity) -> bool {
+    if !self.builder.type_aliases || !matches!(decl.get_kind(), EntityKind::TypedefDecl | EntityKind::TypeAliasDecl) {
+      return false;
+    }
+    let name = match decl.get_name() {
+      Some(x) if !x.starts_with("__blackbird_") => x,
+      _ => return false,
+    };
+    let scoped = decl.get_semantic_parent().is_some_and(|x| matches!(x.get_kind(), EntityKind::Namespace | EntityKind::TranslationUnit));
+    if !scoped || decl.is_in_system_header() || self.builder.blocklisted(decl) {
+      return false;
+    }
+    if self.reachable.as_ref().is_some_and(|x| !x.contains(decl)) {
+      return false;
+    }
+    let underlying = match decl.get_typedef_underlying_type() {
+      Some(x) => x.get_canonical_type(),
+      None => return false,
+    };
+    match underlying.get_kind() {
+      TypeKind::Record | TypeKind::Enum => underlying.get_declaration().and_then(|x| x.get_name()).is_some_and(|x| x != name),
+      TypeKind::FunctionPrototype | TypeKind::FunctionNoPrototype | TypeKind::Unexposed => false,
+      _ => true,
+    }
+  }
+
+  fn type_path(&self, decl: &Entity) -> TokenStream {
+    if let Some(path) = self.builder.existing_type(decl) {
+      return path;
+    }
+    let prefix = self.module_prefix(decl);
+    let name = format_ident!("{}", decl.get_name().unwrap());
+    quote!(#prefix #name)
+  }
+
+  fn cross_reference(&mut self, e: &Entity, symbol: Option<&str>) {
+    if !self.builder.symbols || e.get_name().is_none() {
+      return;
+    }
+    let path = match symbol {
+      Some(_) => None,
+      None => Some(self.type_path(e).to_string().replace(' ', "")),
+    };
+    self.references.push(CrossReference {
+      name: qualified_name(e),
+      usr: e.get_usr().map_or_else(String::new, |x| x.0),
+      path,
+      symbol: symbol.map(|x| x.to_string()),
+    });
+  }
+
+  fn fail(&self, error: Error) {
+    self.error.borrow_mut().get_or_insert(error);
+  }
+
+  // The symbol `e` links to, failing when it has none.
+  fn symbol(&self, e: &Entity) -> Option<String> {
+    let name = match e.get_kind() {
+      EntityKind::Destructor => e.get_mangled_names().and_then(|x| x.into_iter().next()),
+      _ => e.get_mangled_name(),
+    };
+    match name {
+      Some(x) => Some(link_name(&x, &self.triple)),
+      None => {
+        self.fail(Error::MissingMangledName { name: e.get_display_name().unwrap_or_default(), location: location(e) });
+        None
+      },
+    }
+  }
+
+  fn vector_type(&self, ty: &Type) -> TokenStream {
+    let elem = ty.get_element_type().unwrap().get_canonical_type();
+    let lanes = ty.get_size().unwrap();
+    let size = ty.get_sizeof().unwrap();
+    let bits = elem.get_sizeof().unwrap() * 8;
+
+    let (prefix, neon) = match elem.get_kind() {
+      TypeKind::Float | TypeKind::Double => ("f", "float"),
+      _ if elem.is_signed_integer() => ("i", "int"),
+      _ => ("u", "uint"),
+    };
+    let name = format!("Simd{}{}x{}", prefix.to_uppercase(), bits, lanes);
+
+    let mut arches = Vec::new();
+    let x86 = match (size, prefix, bits) {
+      (16, "f", 32) => Some("__m128"),
+      (16, "f", 64) => Some("__m128d"),
+      (16, _, _) => Some("__m128i"),
+      (32, "f", 32) => Some("__m256"),
+      (32, "f", 64) => Some("__m256d"),
+      (32, _, _) => Some("__m256i"),
+      _ => None,
+    };
+    if let Some(x86) = x86 {
+      arches.push(("x86".to_string(), x86.to_string()));
+      arches.push(("x86_64".to_string(), x86.to_string()));
+    }
+    // There's no stable half precision lane type to use.
+    let half = prefix == "f" && bits == 16;
+    if (size == 8 || size == 16) && !half {
+      arches.push(("aarch64".to_string(), format!("{}{}x{}_t", neon, bits, lanes)));
+    }
+
+    let mut helpers = self.helpers.borrow_mut();
+    if !helpers.iter().any(|x| matches!(x, Item::Vector(x) if x.name == name)) {
+      helpers.push(Item::Vector(ItemVector {
+        name: name.clone(),
+        elem: if half { "u16".to_string() } else { format!("{}{}", prefix, bits) },
+        lanes,
+        align: ty.get_alignof().unwrap(),
+        arches,
+      }));
+    }
+
+    let name = format_ident!("{}", name);
+    quote!(#name)
+  }
+
+  // What the method with the given qualified name returns in Rust instead of
+  // the pointer or reference it returns, as annotated.
+  fn returns(&self, e: &Entity, name: &str) -> Returns {
+    let annotated = |names: &[String]| names.iter().any(|f| f == name || name.rsplit("::").next() == Some(f));
+    let ty = e.get_result_type().unwrap().get_canonical_type();
+    let pointee = match ty.get_kind() {
+      TypeKind::Pointer | TypeKind::LValueReference => ty.get_pointee_type().unwrap(),
+      _ => return Returns::Raw,
+    };
+
+    let record = pointee.get_canonical_type().get_kind() == TypeKind::Record;
+    if ty.get_kind() == TypeKind::Pointer && record && annotated(&self.builder.owned_returns) {
+      let tokens = self.map_type(&pointee);
+      let cpp = pointee.get_canonical_type().get_display_name();
+      let cpp = cpp.trim_start_matches("const ").to_string();
+
+      let mut helpers = self.helpers.borrow_mut();
+      let index = match helpers.iter().position(|x| matches!(x, Item::CxxBox(_))) {
+        Some(x) => x,
+        None => {
+          helpers.push(Item::CxxBox(ItemCxxBox { elements: Vec::new() }));
+          helpers.len() - 1
+        },
+      };
+      if let Item::CxxBox(owner) = &mut helpers[index] {
+        if !owner.elements.iter().any(|x| x.cpp == cpp) {
+          owner.elements.push(BoxElement {
+            elem: tokens.clone(),
+            delete_symbol: self.glue_symbol(&cpp, "delete"),
+            cpp,
+          });
+        }
+      }
+      Returns::Owned(tokens)
+    } else if annotated(&self.builder.borrowed_returns) {
+      Returns::Borrowed {
+        pointee: self.map_type(&pointee),
+        mutable: !pointee.is_const_qualified() && !e.is_const_method(),
+        nullable: ty.get_kind() == TypeKind::Pointer,
+        non_null: ty.get_kind() == TypeKind::LValueReference && self.builder.borrow_references,
+      }
+    } else {
+      Returns::Raw
+    }
+  }
+
+  fn string_type(&self, ty: &Type) -> TokenStream {
+    let mut helpers = self.helpers.borrow_mut();
+    if !helpers.iter().any(|x| matches!(x, Item::CxxString(_))) {
+      helpers.push(Item::CxxString(ItemCxxString {
+        size: ty.get_sizeof().unwrap(),
+        align: ty.get_alignof().unwrap(),
+        data_symbol: self.glue_symbol("std::string", "data"),
+        len_symbol: self.glue_symbol("std::string", "size"),
+        new_symbol: self.glue_symbol("std::string", "new"),
+        drop_symbol: self.glue_symbol("std::string", "drop"),
+      }));
+    }
+
+    quote!(CxxString)
+  }
+
+  fn cxx_vector_type(&self, ty: &Type) -> TokenStream {
+    // `std::vector<bool>` packs its elements into bits, so has no slice to
+    // give out.
+    let elem = vector_element(ty).unwrap();
+    if elem.get_canonical_type().get_kind() == TypeKind::Bool {
+      let location = ty.get_declaration().map_or_else(|| self.location.borrow().clone(), |x| location(&x));
+      self.fail(Error::UnsupportedType { ty: ty.get_display_name(), location });
+      return quote!(());
+    }
+    let tokens = self.map_type(&elem);
+    let cpp = ty.get_display_name();
+
+    let mut helpers = self.helpers.borrow_mut();
+    let index = match helpers.iter().position(|x| matches!(x, Item::CxxVector(_))) {
+      Some(x) => x,
+      None => {
+        helpers.push(Item::CxxVector(ItemCxxVector {
+          size: ty.get_sizeof().unwrap(),
+          align: ty.get_alignof().unwrap(),
+          elements: Vec::new(),
+        }));
+        helpers.len() - 1
+      },
+    };
+    if let Item::CxxVector(vector) = &mut helpers[index] {
+      if !vector.elements.iter().any(|x| x.elem.to_string() == tokens.to_string()) {
+        vector.elements.push(VectorElement {
+          elem: tokens.clone(),
+          cpp: cpp.clone(),
+          len_symbol: self.glue_symbol(&cpp, "size"),
+          data_symbol: self.glue_symbol(&cpp, "data"),
+          push_symbol: self.glue_symbol(&cpp, "push_back"),
+          new_symbol: self.glue_symbol(&cpp, "new"),
+          drop_symbol: self.glue_symbol(&cpp, "drop"),
+        });
+      }
+    }
+
+    quote!(CxxVector<#tokens>)
+  }
+
+  // The closure taken for a `std::function` with the function type
+  // `signature`, whose arguments and result have to be passed as they are.
+  fn cxx_function_type(&self, signature: &Type) -> TokenStream {
+    let result = signature.get_result_type().unwrap();
+    let mut types = signature.get_argument_types().unwrap_or_default().into_iter().chain(Some(result));
+    if signature.is_variadic() || types.any(|x| library_value(&x) || function_signature(&x).is_some()) {
+      self.fail(Error::UnsupportedType { ty: signature.get_display_name(), location: self.location.borrow().clone() });
+      return quote!(());
+    }
+    let args = signature.get_argument_types().unwrap_or_default().iter().map(|x| self.map_type(x)).collect();
+    let ret = if result.get_kind() == TypeKind::Void { quote!(()) } else { self.map_type(&result) };
+    let signature = FunctionSignature { args, ret };
+    let ty = signature.ty();
+
+    let mut helpers = self.helpers.borrow_mut();
+    let index = match helpers.iter().position(|x| matches!(x, Item::CxxFunction(_))) {
+      Some(x) => x,
+      None => {
+        helpers.push(Item::CxxFunction(ItemCxxFunction { signatures: Vec::new() }));
+        helpers.len() - 1
+      },
+    };
+    if let Item::CxxFunction(function) = &mut helpers[index] {
+      if !function.signatures.iter().any(|x| x.ty().to_string() == ty.to_string()) {
+        function.signatures.push(signature);
+      }
+    }
+
+    ty
+  }
+
+  fn long_double_type(&self, ty: &Type) -> TokenStream {
+    let mut helpers = self.helpers.borrow_mut();
+    if !helpers.iter().any(|x| matches!(x, Item::LongDouble(_))) {
+      helpers.push(Item::LongDouble(ItemLongDouble {
+        size: ty.get_sizeof().unwrap(),
+        align: ty.get_alignof().unwrap(),
+      }));
+    }
+    quote!(LongDouble)
+  }
+
+  // Fails for the types the bindings can't pass by value, like the bytes
+  // of a `long double`.
+  fn check_passable(&self, ty: &Type) {
+    let root = ty.get_canonical_type();
+    if root.get_kind() == TypeKind::LongDouble && root.get_sizeof() != Ok(8) {
+      self.fail(Error::UnsupportedType { ty: ty.get_display_name(), location: self.location.borrow().clone() });
+    }
+  }
+
+  fn float_type(&self, name: &str, half_name: &str, brain: bool) -> TokenStream {
+    if self.builder.half_crate {
+      let name = format_ident!("{}", half_name);
+      return quote!(half::#name);
+    }
+
+    let mut helpers = self.helpers.borrow_mut();
+    if !helpers.iter().any(|x| matches!(x, Item::Float(x) if x.name == name)) {
+      helpers.push(Item::Float(ItemFloat {
+        name: name.to_string(),
+        brain,
+      }));
+    }
+
+    let name = format_ident!("{}", name);
+    quote!(#name)
+  }
+
+  // How a class implements `Debug`, or `None` to derive it. Formatting
+  // with a method the class doesn't have falls back on the terse form.
+  fn debug_impl(&mut self, e: &Entity, class: &str) -> Option<DebugImpl> {
+    let style = self.builder.debug_styles.iter().rev()
+      .find(|(pattern, _)| matches_name(e, pattern))
+      .map_or(&self.builder.default_debug_style, |x| &x.1);
+
+    let body = match style {
+      DebugStyle::Derive => return None,
+      DebugStyle::Terse => None,
+      DebugStyle::Method(method) => {
+        let found = e.get_children().iter().any(|x| {
+          x.get_kind() == EntityKind::Method
+            && x.get_name().as_ref() == Some(method)
+            && x.is_const_method()
+            && x.get_arguments().is_some_and(|x| x.is_empty())
+        });
+        if found { Some(format!("std::string x(self->{}());", method)) } else { None }
+      },
+      DebugStyle::Stream => Some("std::ostringstream s; s << *self; std::string x = s.str();".to_string()),
+    };
+
+    let symbol = body.map(|body| {
+      let symbol = self.glue_symbol(class, "debug");
+      self.glue_fn(
+        format!("char* {}(const {}* self)", symbol, class),
+        format!("{} char* out = (char*)malloc(x.size() + 1); if (out) memcpy(out, x.c_str(), x.size() + 1); return out;", body),
+      );
+      symbol
+    });
+    Some(DebugImpl { symbol })
+  }
+
+  fn enum_style(&self, e: &Entity) -> EnumStyle {
+    // Enums marked as flags are meant to be combined, so they get the
+    // operators unless configured otherwise.
+    let flags = e.get_definition().unwrap_or(*e).get_children().iter().any(|x| x.get_kind() == EntityKind::FlagEnum);
+    let default = if flags { EnumStyle::Bitflags } else { self.builder.default_enum_style };
+
+    self.builder.enum_styles.iter().rev()
+      .find(|(pattern, _)| matches_name(e, pattern))
+      .map_or(default, |x| x.1)
+  }
+
+  fn intern(&self, x: &str) -> std::rc::Rc<str> {
+    let mut strings = self.strings.borrow_mut();
+    match strings.get(x) {
+      Some(x) => x.clone(),
+      None => {
+        let x: std::rc::Rc<str> = x.into();
+        strings.insert(x.clone());
+        x
+      },
+    }
+  }
+
+  // The lines of `e`'s doc comment.
+  fn comments(&self, e: &Entity) -> Vec<std::rc::Rc<str>> {
+    e.get_comment().map_or(Vec::new(), |x| x.split('\n').map(|x| self.intern(x)).collect())
+  }
+
+  // Runs `f` on a record's fields, unless the record is already being
+  // looked through further up, e.g. through a template argument referring
+  // back to it. The cycle then doesn't decide anything, so `cyclic` should
+  // be the neutral answer.
+  fn resolve<T>(&self, decl: &Entity, cyclic: T, f: impl FnOnce() -> T) -> T {
+    let usr = match decl.get_usr() {
+      Some(x) => x.0,
+      None => return f(),
+    };
+    if self.resolving.borrow().contains(&usr) {
+      return cyclic;
+    }
+
+    self.resolving.borrow_mut().push(usr);
+    let result = f();
+    self.resolving.borrow_mut().pop();
+    result
+  }
+
+  // Whether `ty` is, or is an array of, a class only C++ owns, which has no
+  // `Default` to derive for what holds it.
+  fn no_drop(&self, ty: &Type) -> bool {
+    let root = ty.get_canonical_type();
+    match root.get_kind() {
+      TypeKind::Record => root.get_declaration().and_then(|x| x.get_name()).is_some_and(|x| self.builder.no_drop.contains(&x)),
+      TypeKind::ConstantArray | TypeKind::IncompleteArray => self.no_drop(&root.get_element_type().unwrap()),
+      _ => false,
+    }
+  }
+
+  fn is_copy(&self, ty: &Type) -> bool {
+    let root = ty.get_canonical_type();
+    match root.get_kind() {
+      TypeKind::Record => {
+        let decl = root.get_declaration().unwrap();
+        let decl = decl.get_definition().unwrap_or(decl);
+        root.is_pod()
+          && !self.builder.opaque_types.iter().any(|x| matches_name(&decl, x))
+          && !self.no_drop(&root)
+          && self.resolve(&decl, true, || {
+            decl.get_children().iter()
+              .filter(|x| x.get_kind() == EntityKind::FieldDecl || x.get_kind() == EntityKind::BaseSpecifier)
+              .all(|x| self.is_copy(&x.get_type().unwrap()))
+          })
+      },
+      TypeKind::ConstantArray | TypeKind::IncompleteArray => self.is_copy(&root.get_element_type().unwrap()),
+      _ => root.is_pod(),
+    }
+  }
+
+  // Whether values of `ty` can be compared field by field: `None` if not at
+  // all, `Some(false)` if only partially because of floating point values.
+  fn structural_eq(&self, ty: &Type) -> Option<bool> {
+    let root = ty.get_canonical_type();
+    match root.get_kind() {
+      TypeKind::Record => {
+        let decl = root.get_declaration().unwrap();
+        let decl = decl.get_definition().unwrap_or(decl);
+        if decl.get_kind() == EntityKind::UnionDecl || !self.is_copy(&root) {
+          return None;
+        }
+
+        self.resolve(&decl, Some(true), || {
+          let mut total = true;
+          for child in decl.get_children() {
+            match child.get_kind() {
+              EntityKind::FieldDecl if child.get_accessibility() == Some(Accessibility::Public) => {
+                total &= self.structural_eq(&child.get_type().unwrap())?;
+              },
+              EntityKind::FieldDecl => return None,
+              EntityKind::BaseSpecifier => total &= self.structural_eq(&child.get_type().unwrap())?,
+              EntityKind::Method if child.get_name().as_deref() == Some("operator==") => return None,
+              _ => {},
+            }
+          }
+          Some(total)
+        })
+      },
+      TypeKind::ConstantArray | TypeKind::IncompleteArray => self.structural_eq(&root.get_element_type().unwrap()),
+      TypeKind::Float | TypeKind::Double | TypeKind::LongDouble | TypeKind::Half | TypeKind::Float16 => Some(false),
+      TypeKind::Vector => None,
+      _ => Some(true),
+    }
+  }
+
+  // Pointers with known nullability become references when passed in, and
+  // `NonNull` when returned since there is nothing to borrow from.
+  fn map_pointer(&self, ty: &Type, nullability: Option<Nullability>, borrowed: bool) -> TokenStream {
+    let pointee = match (ty.get_canonical_type().get_pointee_type(), nullability) {
+      (Some(pointee), Some(Nullability::NonNull)) | (Some(pointee), Some(Nullability::Nullable)) => pointee,
+      _ => return self.map_type(ty),
+    };
+
+    // Function pointers are always optional.
+    if let TypeKind::FunctionPrototype | TypeKind::FunctionNoPrototype = pointee.get_kind() {
+      return self.map_type(ty);
+    }
+
+    let tokens = self.map_type(&pointee);
+    let pointer = if !borrowed {
+      quote!(std::ptr::NonNull<#tokens>)
+    } else if pointee.is_const_qualified() {
+      quote!(&#tokens)
+    } else {
+      quote!(&mut #tokens)
+    };
+
+    if nullability == Some(Nullability::Nullable) {
+      quote!(Option<#pointer>)
+    } else {
+      pointer
+    }
+  }
+
+  // Lvalue references are never null, so they're mapped like non-null
+  // pointers when borrowing them is enabled.
+  fn reference_nullability(&self, ty: &Type) -> Option<Nullability> {
+    let reference = ty.get_canonical_type().get_kind() == TypeKind::LValueReference;
+    if reference && self.builder.borrow_references { Some(Nullability::NonNull) } else { None }
+  }
+
+  fn map_ret(&self, func: &Entity) -> TokenStream {
+    let ty = func.get_result_type().unwrap();
+    self.check_passable(&ty);
+    let nullability = self.reference_nullability(&ty).or_else(|| return_nullability(func));
+    self.map_pointer(&ty, nullability, false)
+  }
+
+  fn map_arg(&self, i: usize, arg: &Entity) -> Arg {
+    let ty = arg.get_type().unwrap();
+    self.check_passable(&ty);
+    let nullability = self.reference_nullability(&ty).or_else(|| nullability(arg));
+    Arg(Some(arg.get_display_name().unwrap_or(format!("a{}", i))), self.map_pointer(&ty, nullability, true))
+  }
+
+  fn map_raw_arg(&self, i: usize, arg: &Entity) -> Arg {
+    self.check_passable(&arg.get_type().unwrap());
+    Arg(Some(arg.get_display_name().unwrap_or(format!("a{}", i))), self.map_type(&arg.get_type().unwrap()))
+  }
+
+  // Checks of the arguments a function expects, for debug builds to make
+  // before calling it: the simple conditions of the asserts its inline
+  // definition starts with, and those given to `Builder::precondition`.
+  fn preconditions(&self, func: &Entity, name: &str, args: &[Arg], outs: &[(usize, TokenStream)], slices: &[SliceParam]) -> Vec<TokenStream> {
+    // Outputs aren't in scope in the wrapper, and slices aren't pointers.
+    let inputs = args.iter().enumerate()
+      .filter(|(i, _)| !outs.iter().any(|x| x.0 == *i) && !slices.iter().any(|x| x.pointer == *i || x.length == *i))
+      .map(|x| x.1)
+      .collect::<Vec<_>>();
+
+    let mut checks = Vec::new();
+    if func.is_definition() {
+      for condition in leading_asserts(func) {
+        checks.extend(translate_condition(&condition, &inputs).unwrap_or_default());
+      }
+    }
+    for (function, condition) in self.preconditions {
+      if function == name || name.rsplit("::").next() == Some(function) {
+        checks.push(condition.clone());
+      }
+    }
+    checks
+  }
+
+  // The output parameters of the function with the given qualified name, as
+  // the index of the argument and the type it points to. Their arguments are
+  // passed as raw pointers to the storage the wrapper allocates.
+  fn out_params(&self, func: &Entity, name: &str, args: &mut [Arg]) -> Vec<(usize, TokenStream)> {
+    let mut outs = Vec::new();
+    for (i, arg) in func.get_arguments().unwrap().iter().enumerate() {
+      let param = match arg.get_name() {
+        Some(x) => x,
+        None => continue,
+      };
+
+      let ty = arg.get_type().unwrap().get_canonical_type();
+      let pointee = match ty.get_kind() {
+        TypeKind::Pointer | TypeKind::LValueReference => ty.get_pointee_type().unwrap(),
+        _ => continue,
+      };
+      let writable = !pointee.is_const_qualified() && !matches!(
+        pointee.get_kind(),
+        TypeKind::Void | TypeKind::CharS | TypeKind::CharU | TypeKind::FunctionPrototype | TypeKind::FunctionNoPrototype
+      );
+      if !writable {
+        continue;
+      }
+
+      let annotated = self.builder.out_params.iter().any(|(f, p)| *p == param && (f == name || name.rsplit("::").next() == Some(f)));
+      let detected = self.builder.detect_out_params && (param.starts_with("out") || param.ends_with("_out"));
+      let out = self.builder.callbacks.as_ref()
+        .and_then(|x| x.is_out_param(name, &param))
+        .unwrap_or(annotated || detected);
+      if out {
+        let tokens = self.map_type(&pointee);
+        args[i].1 = quote!(*mut #tokens);
+        outs.push((i, tokens));
+      }
+    }
+    outs
+  }
+
+  // The pointer parameters of the function with the given qualified name
+  // directly followed by their length, annotated or detected by the name of
+  // the length. Untyped buffers are taken as bytes.
+  fn slice_params(&self, func: &Entity, name: &str, outs: &[(usize, TokenStream)]) -> Vec<SliceParam> {
+    let args = func.get_arguments().unwrap();
+    let mut slices: Vec<SliceParam> = Vec::new();
+    for (i, pair) in args.windows(2).enumerate() {
+      if outs.iter().any(|x| x.0 == i || x.0 == i + 1) || slices.last().is_some_and(|x| x.length == i) {
+        continue;
+      }
+
+      let (pointer, length) = match (pair[0].get_name(), pair[1].get_name()) {
+        (Some(pointer), Some(length)) => (pointer, length),
+        _ => continue,
+      };
+      let ty = pair[0].get_type().unwrap().get_canonical_type();
+      if ty.get_kind() != TypeKind::Pointer || !pair[1].get_type().unwrap().get_canonical_type().is_integer() {
+        continue;
+      }
+      let pointee = ty.get_pointee_type().unwrap();
+      if matches!(pointee.get_kind(), TypeKind::FunctionPrototype | TypeKind::FunctionNoPrototype) {
+        continue;
+      }
+
+      let annotated = self.builder.slice_params.iter().any(|(f, p, l)| {
+        *p == pointer && *l == length && (f == name || name.rsplit("::").next() == Some(f))
+      });
+      let detected = self.builder.detect_slice_params && {
+        let length = length.to_lowercase();
+        length == "n" || ["len", "length", "size", "count"].iter().any(|x| length.ends_with(x))
+      };
+      if !annotated && !detected {
+        continue;
+      }
+
+      slices.push(SliceParam {
+        pointer: i,
+        length: i + 1,
+        elem: if pointee.get_kind() == TypeKind::Void { quote!(u8) } else { self.map_type(&pointee) },
+        mutable: !pointee.is_const_qualified(),
+      });
+    }
+    slices
+  }
+
+  // The value meaning success if the function returns a configured error
+  // type.
+  fn error_ok(&self, func: &Entity) -> Option<TokenStream> {
+    let ty = func.get_result_type()?;
+    let matches = |name: &str| ty.get_declaration().is_some_and(|x| matches_name(&x, name)) || ty.get_display_name() == name;
+    self.builder.error_types.iter().rev()
+      .find(|(name, _)| matches(name))
+      .map(|(_, ok)| TokenStream::from_str(ok).unwrap())
+  }
+
+  // The arguments of `func` taking a `const wchar_t*`, when those get a
+  // wrapper converting from `OsStr`s.
+  fn wide_args(&self, func: &Entity) -> Vec<usize> {
+    if !self.builder.wide_strings || self.error_ok(func).is_some() {
+      return Vec::new();
+    }
+    let wide = func.get_arguments().unwrap().iter().enumerate().filter_map(|(i, arg)| {
+      let ty = arg.get_type().unwrap().get_canonical_type();
+      let pointee = ty.get_pointee_type().filter(|_| ty.get_kind() == TypeKind::Pointer)?;
+      if pointee.get_kind() == TypeKind::WChar && pointee.is_const_qualified() { Some((i, pointee)) } else { None }
+    }).collect::<Vec<_>>();
+
+    if let Some((_, pointee)) = wide.first() {
+      let ty = self.map_type(pointee);
+      let mut helpers = self.helpers.borrow_mut();
+      if !helpers.iter().any(|x| matches!(x, Item::Wide(_))) {
+        helpers.push(Item::Wide(ItemWide { ty }));
+      }
+    }
+    wide.into_iter().map(|x| x.0).collect()
+  }
+
+  fn map_args(&self, args: &[Entity]) -> Vec<Arg> {
+    args.iter().enumerate().map(|(i, arg)| self.map_arg(i, arg)).collect()
+  }
+
+  fn process_children(&mut self, e: Entity, c: &Context) -> Vec<Item> {
+    let mut items = Vec::new();
+    for child in e.get_children() {
+      items.extend(self.process_entity(child, c))
+    }
+    items
+  }
+
+  fn process_entity(&mut self, e: Entity, c: &Context) -> Vec<Item> {
+    let outer = self.location.replace(location(&e));
+    let items = self.process_kind(e, c);
+    self.location.replace(outer);
+    items
+  }
+
+  fn process_kind(&mut self, e: Entity, c: &Context) -> Vec<Item> {
+    // Blocklisted items are left for the user to define.
+    match e.get_kind() {
+      EntityKind::Namespace | EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl |
+      EntityKind::EnumDecl | EntityKind::FunctionDecl | EntityKind::VarDecl | EntityKind::TypedefDecl |
+      EntityKind::TypeAliasDecl if self.builder.blocklisted(&e) => {
+        return Vec::new();
+      },
+      EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl |
+      EntityKind::EnumDecl | EntityKind::TypedefDecl | EntityKind::TypeAliasDecl if self.builder.existing_type(&e).is_some() => {
+        return Vec::new();
+      },
+      _ => {},
+    }
+
+    if let Some(reachable) = &self.reachable {
+      match e.get_kind() {
+        EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl |
+        EntityKind::EnumDecl | EntityKind::FunctionDecl | EntityKind::VarDecl if !reachable.contains(&e) => {
+          // Bound once, at the first declaration.
+          if e.get_kind() != EntityKind::EnumDecl && reachable.is_opaque(&e) && e == e.get_canonical_entity() {
+            return vec![Item::Opaque(ItemOpaque {
+              name: e.get_name().unwrap(),
+              size: 0,
+              align: 1,
+              comments: self.comments(&e),
+            })];
+          }
+          return Vec::new();
+        },
+        _ => {},
+      }
+    }
+
+    match e.get_kind() {
+      EntityKind::TranslationUnit => {
+        self.process_children(e, c)
+      },
+
+      EntityKind::Namespace => {
+        let mut c = c.clone();
+        c.ns.push(e.get_name().unwrap());
+        let items = self.process_children(e, &c);
+        if items.is_empty() && self.reachable.is_some() {
+          return Vec::new();
+        }
+
+        vec![Item::Mod(ItemMod {
+          name: e.get_name().unwrap(),
+          items,
+          comments: self.comments(&e),
+        })]
+      }
+
+      // `using ns::func;` binds the functions again under the name it makes
+      // visible here.
+      EntityKind::UsingDeclaration => {
+        let targets = using_targets(&e).into_iter().filter(|x| x.get_kind() == EntityKind::FunctionDecl);
+        targets.flat_map(|x| self.process_entity(x, c)).collect()
+      },
+
+      EntityKind::FunctionDecl => {
+        let mut symbol = match self.symbol(&e) {
+          Some(x) => x,
+          None => return Vec::new(),
+        };
+
+        // Inline and internal linkage functions may not have a symbol to link
+        // to, so they're called through the glue instead.
+        let mut args = self.map_args(&e.get_arguments().unwrap());
+        let mut shim = Shim::default();
+        let glued = self.needs_shim(&e) || e.is_inline_function() || e.get_linkage() == Some(Linkage::Internal);
+        if self.needs_shim(&e) {
+          let spell = |x: &Type| x.get_display_name();
+          (symbol, shim) = self.shim_glue(&e, None, &c.qualify(&e.get_name().unwrap()), &spell, &mut args);
+        } else if glued {
+          let args = e.get_arguments().unwrap();
+          symbol = self.glue_symbol(&c.qualify(&e.get_name().unwrap()), &format!("call {}", stable_key(&e)));
+          self.glue_fn(
+            format!(
+              "{ret} {temp}({args})",
+              ret=e.get_result_type().unwrap().get_display_name(),
+              temp=symbol,
+              args=args.iter().enumerate().map(|(i, arg)| declare(&arg.get_type().unwrap().get_display_name(), &format!("a{}", i))).collect::<Vec<_>>().join(", "),
+            ),
+            format!(
+              "{checks}return {name}({arg_names});",
+              name=c.qualify(&e.get_name().unwrap()),
+              checks=null_checks(&args),
+              arg_names=(0..args.len()).map(|i| format!("a{}", i)).collect::<Vec<_>>().join(", "),
+            ),
+          );
+        }
+
+        let outs = self.out_params(&e, &c.qualify(&e.get_name().unwrap()), &mut args);
+        let slices = self.slice_params(&e, &c.qualify(&e.get_name().unwrap()), &outs);
+        self.cross_reference(&e, Some(&symbol));
+        vec![Item::Fn(ItemFn {
+          name: e.get_name().unwrap(),
+          symbol,
+          ret: self.map_ret(&e),
+          abi: if glued { None } else { abi(&e.get_type().unwrap()) },
+          safe: self.is_safe(&c.qualify(&e.get_name().unwrap())),
+          fuzz: self.builder.fuzz.as_ref().filter(|_| outs.is_empty() && slices.is_empty() && shim == Shim::default()).and_then(|_| fuzz_args(&e.get_arguments().unwrap())),
+          comments: self.function_comments(&e),
+          preconditions: self.preconditions(&e, &c.qualify(&e.get_name().unwrap()), &args, &outs, &slices),
+          wide: if outs.is_empty() && slices.is_empty() && shim == Shim::default() { self.wide_args(&e) } else { Vec::new() },
+          args,
+          outs,
+          slices,
+          error: self.error_ok(&e),
+          shim,
+        })]
+      },
+
+      EntityKind::VarDecl => {
+        let ty = e.get_type().unwrap();
+        let mut comments = self.comments(&e);
+
+        let value = if ty.is_const_qualified() {
+          match e.evaluate() {
+            Some(EvaluationResult::SignedInteger(x)) => Some(Literal::i64_unsuffixed(x).to_token_stream()),
+            Some(EvaluationResult::UnsignedInteger(x)) => Some(Literal::u64_unsuffixed(x).to_token_stream()),
+            Some(EvaluationResult::Float(x)) => Some(Literal::f64_unsuffixed(x).to_token_stream()),
+            _ => None,
+          }
+        } else {
+          None
+        };
+
+        if let Some(value) = value {
+          return vec![Item::Const(ItemConst {
+            name: e.get_name().unwrap(),
+            ty: self.map_type(&ty),
+            value,
+            comments,
+          })];
+        }
+
+        // Thread locals have no portable symbol, the glue returns the address
+        // of the calling thread's instance instead. So it does for variables
+        // imported from a DLL, only reachable through the import table.
+        let thread_local = e.get_tls_kind().is_some();
+        let imported = e.get_children().iter().any(|x| x.get_kind() == EntityKind::DllImport);
+        let keywords = e.get_range().map_or(Vec::new(), |x| x.tokenize().iter().map(|x| x.get_spelling()).collect());
+        if !thread_local && !imported && !keywords.iter().any(|x| x == "inline" || x == "constexpr") {
+          return Vec::new();
+        }
+
+        if thread_local {
+          comments.push(self.intern("/// The address differs between threads and is only valid on the calling one."));
+        }
+
+        let symbol = self.glue_symbol(&c.qualify(&e.get_name().unwrap()), &format!("address {}", stable_key(&e)));
+
+        self.glue_fn(
+          format!("{}* {}()", ty.get_display_name(), symbol),
+          format!("return &{};", c.qualify(&e.get_name().unwrap())),
+        );
+
+        self.cross_reference(&e, Some(&symbol));
+        let tokens = self.map_type(&ty);
+        vec![Item::Var(ItemVar {
+          name: e.get_name().unwrap(),
+          symbol,
+          ty: if ty.is_const_qualified() {
+            quote!(*const #tokens)
+          } else {
+            quote!(*mut #tokens)
+          },
+          comments,
+        })]
+      },
+
+      // Opaque declarations with a fixed underlying type can be used without
+      // ever being defined, and then have no known enumerators. A Rust enum
+      // can't represent them.
+      EntityKind::EnumDecl if e.get_definition().is_none() && e == e.get_canonical_entity() => {
+        let underlying = e.get_enum_underlying_type().unwrap().get_canonical_type();
+        let repr = format_ident!("{}{}", if underlying.is_signed_integer() { "i" } else { "u" }, underlying.get_sizeof().unwrap() * 8);
+        let style = match self.enum_style(&e) {
+          EnumStyle::Rust => EnumStyle::NewType,
+          x => x,
+        };
+
+        self.cross_reference(&e, None);
+        vec![Item::Enum(ItemEnum {
+          name: e.get_name(),
+          style,
+          repr: quote!(#repr),
+          variants: Vec::new(),
+          comments: self.comments(&e),
+        })]
+      },
+
+      EntityKind::EnumDecl if e.is_definition() => {
+        let underlying = e.get_enum_underlying_type().unwrap().get_canonical_type();
+        let signed = underlying.is_signed_integer();
+        let repr = format_ident!("{}{}", if signed { "i" } else { "u" }, underlying.get_sizeof().unwrap() * 8);
+
+        let variants = e.get_children().into_iter().filter(|x| x.get_kind() == EntityKind::EnumConstantDecl).map(|child| {
+          let (value, unsigned_value) = child.get_enum_constant_value().unwrap();
+          Enumerator {
+            name: child.get_name().unwrap(),
+            value: if signed {
+              Literal::i64_unsuffixed(value).to_token_stream()
+            } else {
+              Literal::u64_unsuffixed(unsigned_value).to_token_stream()
+            },
+            comments: self.comments(&child),
+          }
+        }).collect();
+
+        self.cross_reference(&e, None);
+        vec![Item::Enum(ItemEnum {
+          name: e.get_name(),
+          style: self.enum_style(&e),
+          repr: quote!(#repr),
+          variants,
+          comments: self.comments(&e),
+        })]
+      },
+
+      // Forward declarations are bound where the type is defined, or as an
+      // opaque stub at the first declaration if it isn't defined anywhere.
+      EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl if !e.is_definition() => {
+        if e.get_definition().is_some() || e != e.get_canonical_entity() {
+          return Vec::new();
+        }
+
+        vec![Item::Opaque(ItemOpaque {
+          name: e.get_name().unwrap(),
+          size: 0,
+          align: 1,
+          comments: self.comments(&e),
+        })]
+      },
+
+      EntityKind::TypedefDecl | EntityKind::TypeAliasDecl if self.alias_bound(&e) => {
+        let ty = self.map_type(&e.get_typedef_underlying_type().unwrap());
+        self.cross_reference(&e, None);
+        vec![Item::Alias(ItemAlias {
+          name: e.get_name().unwrap(),
+          ty,
+          comments: self.comments(&e),
+        })]
+      },
+
+      EntityKind::UnionDecl => {
+        let fields = e.get_children().into_iter().filter(|x| x.get_kind() == EntityKind::FieldDecl).map(|child| {
+          let ty = child.get_type().unwrap();
+          let tokens = self.map_type(&ty);
+          let tokens = if ty.get_canonical_type().get_kind() == TypeKind::Record {
+            quote!(std::mem::ManuallyDrop<#tokens>)
+          } else {
+            tokens
+          };
+
+          Field(child.get_accessibility().unwrap() == Accessibility::Public, child.get_name().unwrap(), tokens)
+        }).collect();
+
+        self.cross_reference(&e, None);
+        vec![Item::Union(ItemUnion {
+          name: e.get_name().unwrap(),
+          fields,
+          copy: self.is_copy(&e.get_type().unwrap()),
+          comments: self.comments(&e),
+        })]
+      },
+
+      EntityKind::ClassDecl | EntityKind::StructDecl if self.builder.opaque_types.iter().any(|x| matches_name(&e, x)) => {
+        let ty = e.get_type().unwrap();
+        vec![Item::Opaque(ItemOpaque {
+          name: e.get_name().unwrap(),
+          size: ty.get_sizeof().unwrap(),
+          align: ty.get_alignof().unwrap(),
+          comments: self.comments(&e),
+        })]
+      },
+
+      EntityKind::ClassDecl | EntityKind::StructDecl => {
+        let mut strukt = ItemStruct {
+          name: e.get_name().unwrap(),
+          comments: self.comments(&e),
+          fields: Vec::new(),
+          methods: Vec::new(),
+          static_methods: Vec::new(),
+          tagged_unions: Vec::new(),
+          subclass: None,
+          interface: None,
+          boxed: None,
+          polymorphic: false,
+          downcasts: Vec::new(),
+          type_info: None,
+          overload_sets: Vec::new(),
+          ctor_builder: None,
+          str_constructor: None,
+          callback_setters: Vec::new(),
+          slice_accessor: None,
+          bases: Vec::new(),
+          copy: self.is_copy(&e.get_type().unwrap()),
+          zeroed_default: false,
+          vtable: has_vtable(&e.get_type().unwrap()),
+          aggregate_constructor: false,
+          no_drop: self.builder.no_drop.contains(&e.get_name().unwrap()),
+          align: None,
+          eq: if self.builder.derive_eq { self.structural_eq(&e.get_type().unwrap()) } else { None },
+          debug: None,
+          layout: None,
+          serde: None,
+          constructors: Vec::new(),
+          destructor: None,
+        };
+
+        let subclassed = self.builder.subclasses.contains(&strukt.name);
+        let boxed = self.builder.boxed_types.contains(&strukt.name) && !strukt.no_drop;
+        let mut instance_methods = Vec::new();
+        let mut field_decls = Vec::new();
+        let mut nested = Vec::new();
+        let mut signals = false;
+
+        self.process_bases(&mut strukt, &e);
+        // Shared by all the methods instead of copied into each.
+        let class_name: std::rc::Rc<str> = strukt.name.as_str().into();
+    
+        for child in e.get_children() {
+          if child.get_kind() != EntityKind::FieldDecl && self.builder.blocklisted(&child) {
+            continue;
+          }
+
+          // Signals and the members declared by `Q_OBJECT` are only defined
+          // in moc output.
+          if self.builder.qt {
+            if child.get_kind() == EntityKind::AccessSpecifier {
+              signals = child.get_children().iter().any(|x| {
+                x.get_kind() == EntityKind::AnnotateAttr && x.get_display_name().as_deref() == Some("qt_signal")
+              });
+            }
+
+            if signals || child.get_name().is_some_and(|x| QT_MOC_MEMBERS.contains(&x.as_str())) {
+              continue;
+            }
+          }
+
+          let accessible = match child.get_accessibility() {
+            Some(Accessibility::Public) => true,
+            Some(Accessibility::Protected) => subclassed,
+            _ => false,
+          };
+
+          match child.get_kind() {
+            EntityKind::Constructor | EntityKind::Destructor | EntityKind::Method | EntityKind::UsingDeclaration if !accessible => {},
+            EntityKind::Constructor | EntityKind::Destructor if strukt.no_drop => {},
+
+            // `using Base::name;` makes the base's overloads members of this
+            // class. They're called through the glue, which adjusts `this`.
+            EntityKind::UsingDeclaration => {
+              let class = c.qualify(&strukt.name);
+              for target in using_targets(&child).into_iter().filter(|x| x.get_kind() == EntityKind::Method) {
+                let name = target.get_name().unwrap();
+                let qualified = format!("{}::{}", class, name);
+
+                let spell = |x: &Type| x.get_display_name();
+                let mut args = self.map_args(&target.get_arguments().unwrap());
+                let mut shim = Shim::default();
+                if target.is_static_method() {
+                  let symbol = if self.needs_shim(&target) {
+                    let symbol;
+                    (symbol, shim) = self.shim_glue(&target, None, &qualified, &spell, &mut args);
+                    symbol
+                  } else {
+                    match self.symbol(&target) {
+                      Some(x) => x,
+                      None => continue,
+                    }
+                  };
+                  let name = self.builder.callbacks.as_ref()
+                    .and_then(|x| x.static_method_name(&class, &name))
+                    .unwrap_or_else(|| self.builder.static_method_format.replace("{}", &name));
+
+                  self.cross_reference(&target, Some(&symbol));
+                  strukt.static_methods.push(StaticMethod {
+                    name,
+                    symbol,
+                    preconditions: self.preconditions(&target, &qualified, &args, &[], &[]),
+                    args,
+                    ret: self.map_ret(&target),
+                    abi: if shim == Shim::default() { abi(&target.get_type().unwrap()) } else { None },
+                    safe: self.is_safe(&qualified),
+                    shim,
+                    comments: self.function_comments(&target),
+                  });
+                } else {
+                  let symbol = if self.needs_shim(&target) {
+                    let symbol;
+                    (symbol, shim) = self.shim_glue(&target, Some(&class), &format!("self->{}", name), &spell, &mut args);
+                    symbol
+                  } else {
+                    self.dispatch_glue(&class, &target)
+                  };
+                  let outs = self.out_params(&target, &qualified, &mut args);
+                  let slices = self.slice_params(&target, &qualified, &outs);
+                  self.cross_reference(&target, Some(&symbol));
+                  strukt.methods.push(Method {
+                    class: class_name.clone(),
+                    name,
+                    symbol,
+                    preconditions: self.preconditions(&target, &qualified, &args, &outs, &slices),
+                    args,
+                    outs,
+                    slices,
+                    error: self.error_ok(&target),
+                    shim,
+                    ret: self.map_ret(&target),
+                    returns: self.returns(&target, &qualified),
+                    abi: None,
+                    safe: self.is_safe(&qualified),
+                    strict: self.builder.strict_provenance,
+                    constant: target.is_const_method(),
+                    comments: self.function_comments(&target),
+                  });
+                }
+              }
+            },
+
+            EntityKind::FieldDecl => {
+              strukt.zeroed_default |= has_large_array(&child.get_type().unwrap()) || self.no_drop(&child.get_type().unwrap());
+              strukt.fields.push(Field(child.get_accessibility().unwrap() == Accessibility::Public, child.get_name().unwrap(), self.map_type(&child.get_type().unwrap())));
+              field_decls.push(child);
+
+              let class = strukt.name.clone();
+              let name = child.get_name().unwrap();
+              for tagged in self.builder.tagged_unions.iter().filter(|x| x.class == class && x.field == name) {
+                let union = child.get_type().unwrap().get_canonical_type().get_declaration().unwrap();
+                let members = union.get_children().into_iter().filter(|x| x.get_kind() == EntityKind::FieldDecl).collect::<Vec<_>>();
+
+                strukt.tagged_unions.push(TaggedUnion {
+                  class: class.clone(),
+                  tag: tagged.tag.clone(),
+                  field: name.clone(),
+                  variants: tagged.variants.iter().map(|(value, variant)| {
+                    let member = members.iter().find(|x| x.get_name().as_ref() == Some(variant))
+                      .unwrap_or_else(|| panic!("union {}::{} has no member {}", class, name, variant));
+                    let ty = member.get_type().unwrap();
+
+                    Variant {
+                      name: variant.clone(),
+                      value: *value,
+                      ty: self.map_type(&ty),
+                      manually_drop: ty.get_canonical_type().get_kind() == TypeKind::Record,
+                    }
+                  }).collect(),
+                });
+              }
+            },
+
+            EntityKind::Constructor if boxed => {
+              let class = c.qualify(&strukt.name);
+              strukt.boxed = Some(self.process_boxed(&class, &strukt.name, &child.get_arguments().unwrap()));
+            },
+
+            EntityKind::Destructor if boxed => {},
+
+            EntityKind::Constructor => {
+              let symbol = match self.symbol(&child) {
+                Some(x) => x,
+                None => continue,
+              };
+
+              self.cross_reference(&child, Some(&symbol));
+              strukt.constructors.push(Constructor {
+                name: child.get_name().unwrap(),
+                method: constructor_name(&child).to_string(),
+                symbol,
+                args: self.map_args(&child.get_arguments().unwrap()),
+                abi: abi(&child.get_type().unwrap()),
+                safe: false,
+                strict: self.builder.strict_provenance,
+                comments: self.function_comments(&child),
+              });
+
+              let args = child.get_arguments().unwrap();
+              if self.builder.constructor_builders && args.iter().any(|x| default_argument(x).is_some()) {
+                let class = c.qualify(&strukt.name);
+                strukt.ctor_builder = Some(self.process_ctor_builder(&class, &strukt.name, &args));
+              }
+
+              if self.builder.str_constructors && strukt.str_constructor.is_none() && args.len() == 1 {
+                if let Some(string) = string_kind(&args[0].get_type().unwrap()) {
+                  let class = c.qualify(&strukt.name);
+                  strukt.str_constructor = Some(self.process_str_constructor(&class, &strukt.name, string));
+                }
+              }
+            },
+
+            EntityKind::Destructor => {
+              let symbol = match self.symbol(&child) {
+                Some(x) => x,
+                None => continue,
+              };
+
+              self.cross_reference(&child, Some(&symbol));
+              strukt.destructor = Some(Destructor {
+                name: e.get_name().unwrap(),
+                symbol,
+                abi: abi(&child.get_type().unwrap()),
+                strict: self.builder.strict_provenance,
+                comments: self.comments(&child),
+              });
+            },
+
+            EntityKind::Method => {
+              // Virtual methods are called through the glue, which dispatches
+              // to the override of the object's dynamic type.
+              let virtual_method = child.is_virtual_method();
+              let mut args = self.map_args(&child.get_arguments().unwrap());
+              let mut shim = Shim::default();
+              let shimmed = self.needs_shim(&child);
+              let symbol = if shimmed {
+                let class = c.qualify(&strukt.name);
+                let spell = |x: &Type| x.get_display_name();
+                let symbol;
+                (symbol, shim) = if child.is_static_method() {
+                  self.shim_glue(&child, None, &format!("{}::{}", class, child.get_name().unwrap()), &spell, &mut args)
+                } else {
+                  self.shim_glue(&child, Some(&class), &format!("self->{}", child.get_name().unwrap()), &spell, &mut args)
+                };
+                symbol
+              } else if virtual_method {
+                let class = c.qualify(&strukt.name);
+                self.dispatch_glue(&class, &child)
+              } else {
+                match self.symbol(&child) {
+                  Some(x) => x,
+                  None => continue,
+                }
+              };
+              self.cross_reference(&child, Some(&symbol));
+
+              if child.is_static_method() {
+                let name = child.get_name().unwrap();
+                let qualified = c.qualify(&e.get_name().unwrap());
+                let name = self.builder.callbacks.as_ref()
+                  .and_then(|x| x.static_method_name(&qualified, &name))
+                  .unwrap_or_else(|| self.builder.static_method_format.replace("{}", &name));
+
+                let function = format!("{}::{}", qualified, child.get_name().unwrap());
+                strukt.static_methods.push(StaticMethod {
+                  name,
+                  symbol,
+                  preconditions: self.preconditions(&child, &function, &args, &[], &[]),
+                  args,
+                  ret: self.map_ret(&child),
+                  abi: if shimmed { None } else { abi(&child.get_type().unwrap()) },
+                  safe: self.is_safe(&function),
+                  shim,
+                  comments: self.function_comments(&child),
+                });
+              } else {
+                instance_methods.push(child);
+
+                let qualified = format!("{}::{}", c.qualify(&e.get_name().unwrap()), child.get_name().unwrap());
+                let outs = self.out_params(&child, &qualified, &mut args);
+                let slices = self.slice_params(&child, &qualified, &outs);
+                strukt.methods.push(Method {
+                  class: class_name.clone(),
+                  name: child.get_name().unwrap(),
+                  symbol,
+                  preconditions: self.preconditions(&child, &qualified, &args, &outs, &slices),
+                  args,
+                  outs,
+                  slices,
+                  error: self.error_ok(&child),
+                  shim,
+                  ret: self.map_ret(&child),
+                  returns: self.returns(&child, &qualified),
+                  abi: if virtual_method || shimmed { None } else { abi(&child.get_type().unwrap()) },
+                  safe: self.is_safe(&format!("{}::{}", c.qualify(&e.get_name().unwrap()), child.get_name().unwrap())),
+                  strict: self.builder.strict_provenance,
+                  constant: child.is_const_method(),
+                  comments: self.function_comments(&child),
+                });
+              }
+            },
+
+            // Nested types go in a module named after the class, as they
+            // would for a namespace.
+            EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl | EntityKind::EnumDecl
+              if child.get_accessibility() == Some(Accessibility::Public) && child.get_name().is_some() =>
+            {
+              let mut c = c.clone();
+              c.ns.push(strukt.name.clone());
+              nested.extend(self.process_entity(child, &c));
+            },
+
+            _ => {},
+          }
+        }
+
+        if self.builder.unify_overloads {
+          unify_overloads(&mut strukt);
+        }
+
+        let scope = format!("{}::", c.qualify(&strukt.name));
+        let callbacks = self.builder.callbacks.as_deref();
+        rename_overloads(&scope, callbacks, strukt.constructors.iter_mut().filter(|x| x.method == "new").map(|x| (&mut x.method, &x.args[..])).collect());
+        rename_overloads(&scope, callbacks, strukt.methods.iter_mut().map(|x| (&mut x.name, &x.args[..])).collect());
+        rename_overloads(&scope, callbacks, strukt.static_methods.iter_mut().map(|x| (&mut x.name, &x.args[..])).collect());
+
+        if boxed && strukt.boxed.is_none() {
+          let class = c.qualify(&strukt.name);
+          strukt.boxed = Some(self.process_boxed(&class, &strukt.name, &[]));
+        }
+
+        strukt.debug = self.debug_impl(&e, &c.qualify(&strukt.name));
+
+        // Deserializing would leave the vtable pointer null.
+        if !strukt.vtable && self.builder.serde_derives.iter().any(|x| matches_name(&e, x)) {
+          let class = qualified_name(&e);
+          strukt.serde = Some(Serde {
+            rename_all: self.builder.serde_rename_all.clone(),
+            skip: self.builder.serde_skips.iter().filter_map(|x| match x.rsplit_once("::") {
+              Some((owner, field)) if owner == strukt.name || owner == class => Some(field.to_string()),
+              Some(_) => None,
+              None => Some(x.clone()),
+            }).collect(),
+          });
+        }
+
+        if self.builder.layout_tests {
+          let ty = e.get_type().unwrap();
+          strukt.layout = Some(Layout {
+            size: ty.get_sizeof().unwrap(),
+            align: ty.get_alignof().unwrap(),
+            offsets: field_decls.iter()
+              .filter(|x| !x.is_bit_field())
+              .filter_map(|x| Some((x.get_name()?, x.get_offset_of_field().ok()? / 8)))
+              .collect(),
+          });
+        }
+
+        if is_polymorphic(&e) {
+          strukt.polymorphic = true;
+
+          let class = c.qualify(&strukt.name);
+          let symbol = self.glue_symbol(&class, "type_info");
+          let static_symbol = self.glue_symbol(&class, "static type_info");
+
+          self.glue_fn(format!("const char* {}(const {}* self)", symbol, class), "return typeid(*self).name();".to_string());
+          self.glue_fn(format!("const char* {}()", static_symbol), format!("return typeid({}).name();", class));
+
+          strukt.type_info = Some(TypeInfo {
+            class: strukt.name.clone(),
+            qualified_name: class.clone(),
+            symbol,
+            static_symbol,
+          });
+
+          for base in public_ancestors(&e).into_iter().filter(is_polymorphic) {
+            let symbol = self.glue_symbol(&class, &format!("downcast {}", qualified_name(&base)));
+
+            self.glue_fn(
+              format!("{}* {}({}* base)", class, symbol, qualified_name(&base)),
+              format!("return dynamic_cast<{}*>(base);", class),
+            );
+
+            strukt.downcasts.push(Downcast {
+              class: strukt.name.clone(),
+              base: base.get_name().unwrap(),
+              path: self.module_prefix(&base),
+              symbol,
+            });
+          }
+        }
+
+        if subclassed {
+          let virtuals = instance_methods.iter().filter(|x| x.is_virtual_method()).cloned().collect::<Vec<_>>();
+          strukt.subclass = Some(self.process_subclass(e, &virtuals, c));
+        }
+
+        if strukt.fields.iter().all(|x| x.1 == "_vtable") && !instance_methods.is_empty() && instance_methods.iter().all(|x| x.is_virtual_method()) {
+          let class = c.qualify(&strukt.name);
+          strukt.interface = Some(Interface {
+            class: strukt.name.clone(),
+            methods: instance_methods.iter().filter(|x| x.get_accessibility() == Some(Accessibility::Public)).map(|method| {
+              InterfaceMethod {
+                name: method.get_name().unwrap(),
+                symbol: self.dispatch_glue(&class, method),
+                args: self.map_args(&method.get_arguments().unwrap()),
+                ret: self.map_ret(method),
+              }
+            }).collect(),
+          });
+        }
+
+        self.process_callback_setters(&mut strukt, &field_decls);
+        self.process_slice_accessor(&mut strukt, &e, &instance_methods);
+
+        // Aggregates can be built field by field without calling C++.
+        strukt.aggregate_constructor = self.builder.aggregate_constructors
+          && !strukt.fields.is_empty()
+          && strukt.fields.iter().all(|x| x.0)
+          && strukt.bases.is_empty()
+          && !strukt.polymorphic
+          && !field_decls.iter().any(|x| x.is_bit_field())
+          && !e.get_children().iter().any(|x| x.get_kind() == EntityKind::Constructor)
+          && !strukt.methods.iter().any(|x| x.name == "new")
+          && !strukt.overload_sets.iter().any(|x| x.name == "new");
+        resolve_static_names(&mut strukt);
+
+        self.cross_reference(&e, None);
+        let mut items = vec![Item::Struct(Box::new(strukt))];
+        if !nested.is_empty() {
+          items.push(Item::Mod(ItemMod {
+            name: e.get_name().unwrap(),
+            items: nested,
+            comments: Vec::new(),
+          }));
+        }
+        items
+      }
+
+      _ => Vec::new(),
+    }
+  }
+
+  fn function_comments(&self, e: &Entity) -> Vec<std::rc::Rc<str>> {
+    let mut comments = self.comments(e);
+    if self.builder.restrict_docs {
+      for (i, arg) in e.get_arguments().unwrap().iter().enumerate() {
+        if arg.get_type().unwrap().get_canonical_type().is_restrict_qualified() {
+          comments.push(format!("/// `{}` is `restrict` qualified and must not alias any other argument.", arg.get_display_name().unwrap_or(format!("a{}", i))).into());
+        }
+      }
+    }
+    comments
+  }
+
+  fn is_safe(&self, name: &str) -> bool {
+    self.builder.callbacks.as_ref().and_then(|x| x.is_safe(name)).unwrap_or(false)
+  }
+
+  // The symbol of a glue function for the class or function `scope`, named
+  // after it and hashed from what it's for and the crate so that it's stable
+  // between builds and doesn't collide with the glue of other crates or
+  // classes. The rare collision is rehashed. They all start with
+  // `__blackbird_`, for excluding them from what a library exports.
+  fn glue_symbol(&self, scope: &str, key: &str) -> String {
+    let package = std::env::var("CARGO_PKG_NAME").unwrap_or_default();
+    let name = scope.split(|x: char| !x.is_ascii_alphanumeric()).filter(|x| !x.is_empty()).collect::<Vec<_>>().join("_");
+    let mut used = self.glue_symbols.borrow_mut();
+    let mut i = 0;
+    loop {
+      let symbol = format!("__blackbird_{}_{:x}", name, fnv1a(&format!("{} {} {} {}", package, scope, key, i)));
+      if used.insert(symbol.clone()) {
+        return symbol;
+      }
+      i += 1;
+    }
+  }
+
+  // Adds an `extern "C"` function to the glue, declaring it in the shim
+  // header as well.
+  fn glue_fn(&mut self, signature: String, body: String) {
+    self.glue += &format!("extern \"C\" {{ {} {{ {} }} }}", signature, body);
+    self.glue_header += &format!("{};\n", signature);
+  }
+
+  fn dispatch_glue(&mut self, class: &str, method: &Entity) -> String {
+    let symbol = self.glue_symbol(class, &format!("dispatch {}", stable_key(method)));
+    let args = method.get_arguments().unwrap();
+
+    self.glue_fn(
+      format!(
+        "{ret} {temp}({class}* self{args})",
+        ret=method.get_result_type().unwrap().get_display_name(),
+        temp=symbol,
+        class=class,
+        args=args.iter().enumerate().map(|(i, arg)| format!(", {}", declare(&arg.get_type().unwrap().get_display_name(), &format!("a{}", i)))).collect::<String>(),
+      ),
+      format!(
+        "{checks}return self->{name}({arg_names});",
+        checks=null_checks(&args),
+        name=method.get_name().unwrap(),
+        arg_names=(0..args.len()).map(|i| format!("a{}", i)).collect::<Vec<_>>().join(", "),
+      ),
+    );
+
+    symbol
+  }
+
+  // Whether calls to `func` go through a shim catching exceptions.
+  fn catches(&self, func: &Entity) -> bool {
+    self.builder.catch_exceptions && !matches!(
+      func.get_exception_specification(),
+      Some(ExceptionSpecification::BasicNoexcept) | Some(ExceptionSpecification::DynamicNone)
+    )
+  }
+
+  // Whether `func` has to be called through a shim, to catch what it
+  // throws or because it takes or returns `std::string`s or `std::vector`s,
+  // which can't cross `extern "C"` by value.
+  // MSVC passes the pointer to what methods return by value after `this`,
+  // rather than first as C does, so those are called through a C shim too.
+  fn needs_shim(&self, func: &Entity) -> bool {
+    let converted = |x: &Type| library_value(x) || function_signature(x).is_some() || (string_kind(x) == Some(true) && !self.builder.cxx_string);
+    let result = func.get_result_type().unwrap();
+    let msvc_record = triple_abi(&self.triple).2.as_deref() == Some("msvc")
+      && func.get_kind() == EntityKind::Method
+      && !func.is_static_method()
+      && result.get_canonical_type().get_kind() == TypeKind::Record;
+    self.catches(func)
+      || library_value(&result)
+      || msvc_record
+      || func.get_arguments().unwrap().iter().any(|x| converted(&x.get_type().unwrap()))
+  }
+
+  // Adds a shim calling `func` by the name `callee`, on an object of class
+  // `this` for methods, with `spell` giving the C++ spelling of types. The
+  // `args` it takes as `std::string`s are changed to how the shim takes
+  // them. Results the shim can't return are written to `__ret`, and what it
+  // catches to `__what`, as a copy of the message for
+  // `CxxException::from_raw` to free. References are returned as pointers.
+  fn shim_glue(&mut self, func: &Entity, this: Option<&str>, callee: &str, spell: &dyn Fn(&Type) -> String, args: &mut [Arg]) -> (String, Shim) {
+    let symbol = self.glue_symbol(this.map_or(callee, |x| x.trim_start_matches("const ")), &format!("shim {} {}", callee, stable_key(func)));
+    let mut shim = Shim {
+      catch: self.catches(func),
+      indirect: false,
+      strings: !self.builder.cxx_string,
+    };
+
+    let mut params = this.map(|x| format!("{}* self", x)).into_iter().collect::<Vec<_>>();
+    let mut values = Vec::new();
+    let func_args = func.get_arguments().unwrap();
+    for (i, arg) in func_args.iter().enumerate() {
+      let ty = arg.get_type().unwrap();
+      let by_value = ty.get_canonical_type().get_kind() == TypeKind::Record;
+      let function = function_signature(&ty);
+      match string_kind(&ty) {
+        Some(true) if shim.strings => {
+          params.push(format!("blackbird_str a{}", i));
+          values.push(format!("std::string(a{0}.data, a{0}.len)", i));
+          args[i].1 = quote!(CxxStr<'_>);
+        },
+        Some(true) if by_value => {
+          params.push(format!("const std::string* a{}", i));
+          values.push(format!("*a{}", i));
+          args[i].1 = quote!(*const CxxString);
+        },
+        _ if by_value && vector_element(&ty).is_some() => {
+          params.push(format!("const {}* a{}", spell(&ty), i));
+          values.push(format!("*a{}", i));
+          let vector = &args[i].1;
+          args[i].1 = quote!(*const #vector);
+        },
+        _ if function.is_some() => {
+          params.push(format!("blackbird_fn a{}", i));
+          values.push(format!("blackbird_function<std::decay<{}>::type>::make(a{})", spell(&ty), i));
+          args[i].1 = self.cxx_function_type(&function.unwrap());
+        },
+        _ => {
+          params.push(declare(&spell(&ty), &format!("a{}", i)));
+          values.push(format!("a{}", i));
+        },
+      }
+    }
+    if args.iter().any(|x| is_cxx_str(&x.1)) && !self.glue.contains("struct blackbird_str ") {
+      let definition = "struct blackbird_str { const char* data; size_t len; };\n";
+      self.glue.insert_str(0, definition);
+      self.glue_header.insert_str(0, definition);
+    }
+    if args.iter().any(|x| x.1.to_string().starts_with("CxxFunction <")) && !self.glue.contains("struct blackbird_fn ") {
+      // The closure is shared by the copies of the `std::function`, the
+      // last one dropping it.
+      let definition = "struct blackbird_fn { void* data; void* call; void (*drop)(void*); };\n";
+      self.glue.insert_str(0, "#include <functional>\n#include <memory>\n#include <type_traits>\n\
+        template <typename F> struct blackbird_function;\n\
+        template <typename R, typename... A> struct blackbird_function<std::function<R(A...)>> {\n\
+        static std::function<R(A...)> make(blackbird_fn f) {\n\
+        std::shared_ptr<void> data(f.data, f.drop);\n\
+        R (*call)(void*, A...) = (R (*)(void*, A...))f.call;\n\
+        return [data, call](A... a) -> R { return call(data.get(), std::forward<A>(a)...); };\n\
+        }\n\
+        };\n");
+      self.glue.insert_str(0, definition);
+      self.glue_header.insert_str(0, definition);
+    }
+
+    let call = format!("{}({})", callee, values.join(", "));
+    let result = func.get_result_type().unwrap();
+    let ret = spell(&result);
+    let store = match result.get_canonical_type().get_kind() {
+      TypeKind::Void => format!("{};", call),
+      TypeKind::LValueReference | TypeKind::RValueReference if shim.catch => {
+        params.push(format!("{}** __ret", ret.trim_end_matches('&').trim_end()));
+        format!("auto&& ret = {}; *__ret = &ret;", call)
+      },
+      _ if shim.catch || library_value(&result) => {
+        params.push(format!("{}* __ret", ret));
+        format!("new ((void*)__ret) {}({});", ret, call)
+      },
+      _ => format!("return {};", call),
+    };
+    shim.indirect = params.last().is_some_and(|x| x.ends_with(" __ret"));
+
+    let checks = null_checks(&func_args);
+    if shim.catch {
+      params.push("char** __what".to_string());
+      self.glue_fn(
+        format!("int {}({})", symbol, params.join(", ")),
+        format!(
+          "{checks}try {{ {store} return 0; }} \
+          catch (const std::exception& e) {{ *__what = blackbird_what(e.what()); return 1; }} \
+          catch (...) {{ *__what = blackbird_what(\"unknown exception\"); return 2; }}",
+          checks=checks,
+          store=store,
+        ),
+      );
+
+      let mut helpers = self.helpers.borrow_mut();
+      if !helpers.iter().any(|x| matches!(x, Item::Exception(_))) {
+        helpers.push(Item::Exception(ItemException));
+      }
+    } else {
+      let ret = if shim.indirect { "void" } else { &ret };
+      self.glue_fn(format!("{} {}({})", ret, symbol, params.join(", ")), format!("{}{}", checks, store));
+    }
+
+    (symbol, shim)
+  }
+
+  fn process_ctor_builder(&mut self, class: &str, name: &str, args: &[Entity]) -> CtorBuilder {
+    let first = args.iter().position(|x| default_argument(x).is_some()).unwrap();
+
+    let symbols = (first..=args.len()).map(|count| {
+      let symbol = self.glue_symbol(class, &format!("constructor {}", count));
+
+      self.glue_fn(
+        format!(
+          "void {temp}({class}* self{args})",
+          temp=symbol,
+          class=class,
+          args=args[..count].iter().enumerate().map(|(i, arg)| format!(", {}", declare(&arg.get_type().unwrap().get_display_name(), &format!("a{}", i)))).collect::<String>(),
+        ),
+        format!(
+          "{checks}new (self) {class}({arg_names});",
+          checks=null_checks(&args[..count]),
+          class=class,
+          arg_names=(0..count).map(|i| format!("a{}", i)).collect::<Vec<_>>().join(", "),
+        ),
+      );
+
+      symbol
+    }).collect();
+
+    CtorBuilder {
+      class: name.to_string(),
+      // The builder stores its arguments, so they stay raw pointers.
+      required: args[..first].iter().enumerate().map(|(i, arg)| self.map_raw_arg(i, arg)).collect(),
+      optional: args[first..].iter().enumerate().map(|(i, arg)| {
+        (self.map_raw_arg(first + i, arg), default_value(arg, self.map_type(&arg.get_type().unwrap())))
+      }).collect(),
+      symbols,
+      strict: self.builder.strict_provenance,
+    }
+  }
+
+  // Binds an instantiation of a class template from the typedef of it added
+  // to the header. clang doesn't expose the members of instantiations, so
+  // those of the template are bound with their parameters substituted, and
+  // called through the glue which instantiates them.
+  fn process_instantiation(&mut self, typedef: Entity, spelling: &str) -> Option<Item> {
+    let ty = typedef.get_typedef_underlying_type()?.get_canonical_type();
+    let template = match ty.get_declaration().and_then(|x| x.get_template()) {
+      Some(x) => x,
+      None => {
+        self.fail(Error::UnsupportedType { ty: spelling.to_string(), location: location(&typedef) });
+        return None;
+      },
+    };
+
+    let name = instance_name(spelling);
+    let params = template.get_children().into_iter()
+      .filter(|x| matches!(
+        x.get_kind(),
+        EntityKind::TemplateTypeParameter | EntityKind::NonTypeTemplateParameter | EntityKind::TemplateTemplateParameter
+      ))
+      .map(|x| x.get_name().unwrap_or_default())
+      .collect::<Vec<_>>();
+    let spelled = template_arguments(spelling);
+    let args = ty.get_template_argument_types().unwrap_or_default().iter().map(|x| x.map(|x| self.map_type(&x))).collect();
+    *self.instance.borrow_mut() = Some(Instance {
+      usr: template.get_usr()?.0,
+      args,
+      name: name.clone(),
+    });
+
+    let cpp = |x: &Type| substitute(&x.get_display_name(), &params, &spelled);
+    let glue_args = |args: &[Entity]| {
+      args.iter().enumerate().map(|(i, arg)| format!(", {} a{}", cpp(&arg.get_type().unwrap()), i)).collect::<String>()
+    };
+    let arg_names = |args: &[Entity]| (0..args.len()).map(|i| format!("a{}", i)).collect::<Vec<_>>().join(", ");
+
+    let copy = self.is_copy(&ty);
+    let size = ty.get_sizeof().unwrap_or(0);
+    let mut strukt = ItemStruct {
+      name: name.clone(),
+      comments: self.comments(&template),
+      fields: vec![Field(false, "_opaque".to_string(), quote!([u8; #size]))],
+      methods: Vec::new(),
+      static_methods: Vec::new(),
+      tagged_unions: Vec::new(),
+      subclass: None,
+      interface: None,
+      boxed: None,
+      polymorphic: false,
+      downcasts: Vec::new(),
+      type_info: None,
+      overload_sets: Vec::new(),
+      ctor_builder: None,
+      str_constructor: None,
+      callback_setters: Vec::new(),
+      slice_accessor: None,
+      bases: Vec::new(),
+      copy,
+      zeroed_default: size > 32,
+      vtable: has_vtable(&ty),
+      aggregate_constructor: false,
+      no_drop: false,
+      align: Some(ty.get_alignof().unwrap_or(1)),
+      eq: None,
+      debug: None,
+      layout: None,
+      serde: None,
+      constructors: Vec::new(),
+      destructor: None,
+    };
+    let class_name: std::rc::Rc<str> = name.as_str().into();
+
+    // Instantiating every member up front catches the ones that don't
+    // compile for these arguments here rather than at their first use.
+    self.glue += &format!("template class {};\n", spelling);
+    self.glue += &format!("typedef {} {};\n", spelling, name);
+    self.glue_header += &format!("typedef {} {};\n", spelling, name);
+
+    for child in template.get_children() {
+      if child.get_accessibility() != Some(Accessibility::Public) {
+        continue;
+      }
+
+      let qualified = format!("{}::{}", name, child.get_name().unwrap_or_default());
+      let args = child.get_arguments().unwrap_or_default();
+      match child.get_kind() {
+        EntityKind::Constructor => {
+          let symbol = self.glue_symbol(&name, &format!("constructor {}", stable_key(&child)));
+          self.glue_fn(
+            format!("void {}({}* self{})", symbol, name, glue_args(&args)),
+            format!("{}new (self) {}({});", null_checks(&args), name, arg_names(&args)),
+          );
+
+          strukt.constructors.push(Constructor {
+            name: name.clone(),
+            method: constructor_name(&child).to_string(),
+            symbol,
+            args: self.map_args(&args),
+            abi: None,
+            safe: false,
+            strict: self.builder.strict_provenance,
+            comments: self.function_comments(&child),
+          });
+        },
+
+        EntityKind::Destructor if !copy => {
+          let symbol = self.glue_symbol(&name, "destructor");
+          self.glue_fn(format!("void {}({}* self)", symbol, name), format!("self->~{}();", name));
+
+          strukt.destructor = Some(Destructor {
+            name: name.clone(),
+            symbol,
+            abi: None,
+            strict: self.builder.strict_provenance,
+            comments: self.comments(&child),
+          });
+        },
+
+        EntityKind::Method if !child.get_name().unwrap().starts_with("operator") => {
+          let method = child.get_name().unwrap();
+          let ret = cpp(&child.get_result_type().unwrap());
+          let symbol = self.glue_symbol(&name, &format!("method {}", stable_key(&child)));
+
+          let mut rust_args = self.map_args(&args);
+          let mut shim = Shim::default();
+
+          if child.is_static_method() {
+            let symbol = if self.needs_shim(&child) {
+              let symbol;
+              (symbol, shim) = self.shim_glue(&child, None, &format!("{}::{}", name, method), &cpp, &mut rust_args);
+              symbol
+            } else {
+              self.glue_fn(
+                format!("{} {}({})", ret, symbol, glue_args(&args).trim_start_matches(", ")),
+                format!("{}return {}::{}({});", null_checks(&args), name, method, arg_names(&args)),
+              );
+              symbol
+            };
+
+            strukt.static_methods.push(StaticMethod {
+              name: self.builder.static_method_format.replace("{}", &method),
+              symbol,
+              preconditions: self.preconditions(&child, &qualified, &rust_args, &[], &[]),
+              args: rust_args,
+              ret: self.map_ret(&child),
+              abi: None,
+              safe: self.is_safe(&qualified),
+              shim,
+              comments: self.function_comments(&child),
+            });
+          } else {
+            let this = if child.is_const_method() { "const " } else { "" };
+            let symbol = if self.needs_shim(&child) {
+              let symbol;
+              (symbol, shim) = self.shim_glue(&child, Some(&format!("{}{}", this, name)), &format!("self->{}", method), &cpp, &mut rust_args);
+              symbol
+            } else {
+              self.glue_fn(
+                format!("{} {}({}{}* self{})", ret, symbol, this, name, glue_args(&args)),
+                format!("{}return self->{}({});", null_checks(&args), method, arg_names(&args)),
+              );
+              symbol
+            };
+
+            let outs = self.out_params(&child, &qualified, &mut rust_args);
+            let slices = self.slice_params(&child, &qualified, &outs);
+            strukt.methods.push(Method {
+              class: class_name.clone(),
+              name: method,
+              symbol,
+              preconditions: self.preconditions(&child, &qualified, &rust_args, &outs, &slices),
+              args: rust_args,
+              outs,
+              slices,
+              error: self.error_ok(&child),
+              shim,
+              ret: self.map_ret(&child),
+              returns: self.returns(&child, &qualified),
+              abi: None,
+              safe: self.is_safe(&qualified),
+              strict: self.builder.strict_provenance,
+              constant: child.is_const_method(),
+              comments: self.function_comments(&child),
+            });
+          }
+        },
+
+        _ => {},
+      }
+    }
+
+    *self.instance.borrow_mut() = None;
+
+    let callbacks = self.builder.callbacks.as_deref();
+    rename_overloads(&format!("{}::", name), callbacks, strukt.constructors.iter_mut().filter(|x| x.method == "new").map(|x| (&mut x.method, &x.args[..])).collect());
+    rename_overloads(&format!("{}::", name), callbacks, strukt.methods.iter_mut().map(|x| (&mut x.name, &x.args[..])).collect());
+    rename_overloads(&format!("{}::", name), callbacks, strukt.static_methods.iter_mut().map(|x| (&mut x.name, &x.args[..])).collect());
+    resolve_static_names(&mut strukt);
+    Some(Item::Struct(Box::new(strukt)))
+  }
+
+  // Embeds the bases as the first fields, in order, as the C++ ABIs lay them
+  // out. Virtual bases are somewhere only the vtable knows, so they're left
+  // out.
+  //
+  // The first polymorphic base shares its vtable pointer with the class, so
+  // it goes first. A polymorphic class without one starts with its own.
+  fn process_bases(&self, strukt: &mut ItemStruct, e: &Entity) {
+    fn declaration<'tu>(x: &Entity<'tu>) -> Option<Entity<'tu>> {
+      x.get_type().unwrap().get_canonical_type().get_declaration().map(|x| x.get_definition().unwrap_or(x))
+    }
+
+    let specifiers = e.get_children().into_iter().filter(|x| x.get_kind() == EntityKind::BaseSpecifier).collect::<Vec<_>>();
+    let mut bases = specifiers.iter().filter(|x| !x.is_virtual_base()).cloned().collect::<Vec<_>>();
+    match bases.iter().position(|x| declaration(x).is_some_and(|x| is_polymorphic(&x))) {
+      Some(i) => {
+        let primary = bases.remove(i);
+        bases.insert(0, primary);
+      },
+      None if is_polymorphic(e) || specifiers.iter().any(|x| x.is_virtual_base()) => {
+        strukt.fields.push(Field(false, "_vtable".to_string(), quote!(*const std::os::raw::c_void)));
+      },
+      None => {},
+    }
+
+    let mut deref = true;
+    for (i, child) in bases.into_iter().enumerate() {
+      let ty = child.get_type().unwrap();
+      let decl = match declaration(&child) {
+        Some(x) => x,
+        None => continue,
+      };
+
+      let field = if is_empty_class(&decl) {
+        // Only the first base is at the start of the class whether or not
+        // it's empty.
+        if i > 0 {
+          continue;
+        }
+        None
+      } else {
+        let name = if i == 0 { "_base".to_string() } else { format!("_base{}", i) };
+        strukt.zeroed_default |= self.no_drop(&ty);
+        strukt.fields.push(Field(false, name.clone(), self.map_type(&ty)));
+        Some(name)
+      };
+
+      let public = child.get_accessibility() == Some(Accessibility::Public);
+      strukt.bases.push(BaseClass {
+        class: strukt.name.clone(),
+        ty: self.map_type(&ty),
+        field,
+        public,
+        deref: public && deref,
+      });
+      deref &= !public;
+    }
+  }
+
+  fn process_callback_setters(&self, strukt: &mut ItemStruct, fields: &[Entity]) {
+    let public = fields.iter().filter(|x| x.get_accessibility() == Some(Accessibility::Public)).collect::<Vec<_>>();
+
+    // The context is the one `void*` field, or the one named like it.
+    let contexts = public.iter().filter(|x| is_void_pointer(&x.get_type().unwrap())).collect::<Vec<_>>();
+    let context_field = contexts.iter()
+      .find(|x| {
+        let name = x.get_name().unwrap().to_lowercase();
+        ["data", "ctx", "context", "user"].iter().any(|y| name.contains(y))
+      })
+      .or(if contexts.len() == 1 { contexts.first() } else { None })
+      .map(|x| x.get_name().unwrap());
+
+    for field in public {
+      let ty = field.get_type().unwrap().get_canonical_type();
+      let pointee = match ty.get_pointee_type() {
+        Some(x) if ty.get_kind() == TypeKind::Pointer && x.get_kind() == TypeKind::FunctionPrototype && !x.is_variadic() => x,
+        _ => continue,
+      };
+      let arg_types = pointee.get_argument_types().unwrap_or_default();
+      let context = match arg_types.iter().position(is_void_pointer) {
+        Some(x) => x,
+        None => continue,
+      };
+
+      let name = field.get_name().unwrap();
+      let setter = format!("set_{}", name);
+      let taken = strukt.methods.iter().any(|x| x.name == setter)
+        || strukt.static_methods.iter().any(|x| x.name == setter)
+        || strukt.overload_sets.iter().any(|x| x.name == setter);
+      if taken {
+        continue;
+      }
+
+      strukt.callback_setters.push(CallbackSetter {
+        class: strukt.name.clone(),
+        field: name,
+        abi: abi(&pointee).unwrap_or_else(|| "C".to_string()),
+        args: arg_types.iter().map(|x| self.map_type(x)).collect(),
+        context,
+        ret: self.map_type(&pointee.get_result_type().unwrap()),
+        context_field: context_field.clone(),
+      });
+    }
+  }
+
+  fn process_slice_accessor(&self, strukt: &mut ItemStruct, e: &Entity, methods: &[Entity]) {
+    let pair = self.builder.slice_accessors.iter()
+      .find(|x| matches_name(e, &x.0))
+      .map(|x| (x.1.as_str(), x.2.as_str()))
+      .or(if self.builder.detect_slice_accessors { Some(("data", "size")) } else { None });
+    let (data, size) = match pair {
+      Some(x) => x,
+      None => return,
+    };
+    let taken = strukt.methods.iter().any(|x| x.name == "as_slice")
+      || strukt.static_methods.iter().any(|x| x.name == "as_slice")
+      || strukt.overload_sets.iter().any(|x| x.name == "as_slice");
+    if taken {
+      return;
+    }
+
+    // Only const methods without arguments, called directly.
+    let find = |name: &str| methods.iter().find(|x| {
+      x.get_name().as_deref() == Some(name)
+        && x.is_const_method()
+        && !x.is_virtual_method()
+        && x.get_arguments().is_some_and(|x| x.is_empty())
+        && !self.needs_shim(x)
+    });
+    let (data_method, size_method) = match (find(data), find(size)) {
+      (Some(x), Some(y)) => (x, y),
+      _ => return,
+    };
+    let result = data_method.get_result_type().unwrap().get_canonical_type();
+    let elem = match result.get_pointee_type() {
+      Some(x) if result.get_kind() == TypeKind::Pointer && x.is_const_qualified() => x,
+      _ => return,
+    };
+    let len = size_method.get_result_type().unwrap().get_canonical_type();
+    if !len.is_integer() || len.get_kind() == TypeKind::Bool {
+      return;
+    }
+    if let (Some(data_symbol), Some(size_symbol)) = (self.symbol(data_method), self.symbol(size_method)) {
+      strukt.slice_accessor = Some(SliceAccessor {
+        class: strukt.name.clone(),
+        data: data.to_string(),
+        data_symbol,
+        size: size.to_string(),
+        size_symbol,
+        elem: self.map_type(&elem),
+        len: self.map_type(&len),
+        abi: abi(&data_method.get_type().unwrap()),
+      });
+    }
+  }
+
+  fn process_str_constructor(&mut self, class: &str, name: &str, string: bool) -> StrConstructor {
+    let symbol = self.glue_symbol(class, "str constructor");
+    let body = if string {
+      format!("new (self) {}(std::string(data, len));", class)
+    } else {
+      format!("(void)len; new (self) {}(data);", class)
+    };
+    self.glue_fn(format!("void {}({}* self, const char* data, size_t len)", symbol, class), body);
+
+    StrConstructor {
+      class: name.to_string(),
+      symbol,
+      string,
+      strict: self.builder.strict_provenance,
+    }
+  }
+
+  fn process_boxed(&mut self, class: &str, name: &str, args: &[Entity]) -> BoxedConstructor {
+    let new_symbol = self.glue_symbol(class, "boxed new");
+    let delete_symbol = self.glue_symbol(class, "boxed delete");
+
+    self.glue_fn(
+      format!(
+        "{class}* {new}({args})",
+        class=class,
+        new=new_symbol,
+        args=args.iter().enumerate().map(|(i, arg)| declare(&arg.get_type().unwrap().get_display_name(), &format!("a{}", i))).collect::<Vec<_>>().join(", "),
+      ),
+      format!(
+        "{checks}return new {class}({arg_names});",
+        checks=null_checks(args),
+        class=class,
+        arg_names=(0..args.len()).map(|i| format!("a{}", i)).collect::<Vec<_>>().join(", "),
+      ),
+    );
+    self.glue_fn(format!("void {}({}* self)", delete_symbol, class), "delete self;".to_string());
+
+    BoxedConstructor {
+      class: name.to_string(),
+      new_symbol,
+      delete_symbol,
+      args: self.map_args(args),
+    }
+  }
+
+  fn process_subclass(&mut self, e: Entity, virtuals: &[Entity], c: &Context) -> Subclass {
+    let class = c.qualify(&e.get_name().unwrap());
+    let shim = format!("__blackbird_{}", class.replace("::", "_"));
+
+    let mut pointers = String::new();
+    let mut overrides = String::new();
+    for method in virtuals {
+      let args = method.get_arguments().unwrap();
+      let arg_names = (0..args.len()).map(|i| format!("a{}", i)).collect::<Vec<_>>();
+
+      pointers += &format!(
+        "{ret} (*{name})(void*, {class}*{args}); ",
+        ret=method.get_result_type().unwrap().get_display_name(),
+        name=method.get_name().unwrap(),
+        class=class,
+        args=args.iter().map(|arg| format!(", {}", arg.get_type().unwrap().get_display_name())).collect::<String>(),
+      );
+
+      let fallback = if method.is_pure_virtual_method() {
+        "std::abort();".to_string()
+      } else {
+        format!("return {}::{}({});", class, method.get_name().unwrap(), arg_names.join(", "))
+      };
+
+      overrides += &format!(
+        "{ret} {name}({args}){constness} override {{ if (v.{name}) return v.{name}(v.user_data, const_cast<{class}*>(static_cast<const {class}*>(this)){arg_names}); {fallback} }} ",
+        ret=method.get_result_type().unwrap().get_display_name(),
+        name=method.get_name().unwrap(),
+        args=args.iter().zip(&arg_names).map(|(arg, name)| {
+          declare(&arg.get_type().unwrap().get_display_name(), name)
+        }).collect::<Vec<_>>().join(", "),
+        constness=if method.is_const_method() { " const" } else { "" },
+        class=class,
+        arg_names=arg_names.iter().map(|x| format!(", {}", x)).collect::<String>(),
+        fallback=fallback,
+      );
+    }
+
+    let new_symbol = self.glue_symbol(&class, "subclass new");
+    let delete_symbol = self.glue_symbol(&class, "subclass delete");
+
+    let table = format!("struct {shim}_virtuals {{ void* user_data; {pointers}}};", shim=shim, pointers=pointers);
+    self.glue += &format!(
+      "extern \"C\" {{ {table} }} \
+      class {shim} : public {class} {{ public: {shim}_virtuals v; {shim}(const {shim}_virtuals* v) : v(*v) {{}} {overrides}}}; ",
+      table=table,
+      shim=shim,
+      class=class,
+      overrides=overrides,
+    );
+    self.glue_header += &format!("{}\n", table);
+    self.glue_fn(format!("{}* {}(const {}_virtuals* v)", class, new_symbol, shim), format!("return new {}(v);", shim));
+    self.glue_fn(format!("void {}({}* self)", delete_symbol, class), format!("delete static_cast<{}*>(self);", shim));
+
+    Subclass {
+      class: e.get_name().unwrap(),
+      new_symbol,
+      delete_symbol,
+      virtuals: virtuals.iter().map(|method| Virtual {
+        name: method.get_name().unwrap(),
+        args: self.map_args(&method.get_arguments().unwrap()),
+        ret: self.map_ret(method),
+      }).collect(),
+    }
+  }
+}
+
+// Merges the modules of namespaces reopened, possibly in different headers,
+// and drops redeclarations bound the same way as an earlier declaration.
+fn merge_duplicates(items: Vec<Item>) -> Vec<Item> {
+  let mut merged: Vec<Item> = Vec::new();
+  let mut seen = HashSet::new();
+  for item in items {
+    match item {
+      Item::Mod(item) => match merged.iter_mut().find(|x| matches!(x, Item::Mod(x) if x.name == item.name)) {
+        Some(Item::Mod(x)) => {
+          x.items.extend(item.items);
+          if x.comments.is_empty() {
+            x.comments = item.comments;
+          }
+        },
+        _ => merged.push(Item::Mod(item)),
+      },
+      item => {
+        if seen.insert(format!("{:?}", item)) {
+          merged.push(item);
+        }
+      },
+    }
+  }
+  for item in &mut merged {
+    if let Item::Mod(item) = item {
+      item.items = merge_duplicates(std::mem::take(&mut item.items));
+    }
+  }
+  merged
+}
+
+fn merge_targets(sets: Vec<(String, Vec<Item>)>) -> Vec<Item> {
+  let count = sets.len();
+  let mut keys = Vec::new();
+  let mut found: HashMap<String, Vec<(String, Item)>> = HashMap::new();
+
+  for (target, items) in sets {
+    for item in items {
+      // Modules are matched by name and merged recursively, everything else
+      // has to be identical across targets to be shared.
+      let key = match &item {
+        Item::Mod(item) => format!("mod {}", item.name),
+        _ => format!("{:?}", item),
+      };
+
+      if !found.contains_key(&key) {
+        keys.push(key.clone());
+      }
+      found.entry(key).or_default().push((target.clone(), item));
+    }
+  }
+
+  let mut items = Vec::new();
+  let mut groups: Vec<ItemCfg> = Vec::new();
+  for key in keys {
+    let entries = found.remove(&key).unwrap();
+    let mut targets: Vec<String> = entries.iter().map(|x| x.0.clone()).collect();
+    targets.dedup();
+
+    let item = if let (_, Item::Mod(_)) = &entries[0] {
+      let mut name = String::new();
+      let mut comments = Vec::new();
+      let mut sets = Vec::new();
+      for (target, item) in entries {
+        if let Item::Mod(item) = item {
+          name = item.name;
+          comments = item.comments;
+          sets.push((target, item.items));
+        }
+      }
+
+      Item::Mod(ItemMod {
+        name,
+        items: merge_targets(sets),
+        comments,
+      })
+    } else {
+      entries.into_iter().next().unwrap().1
+    };
+
+    if targets.len() == count {
+      items.push(item);
+    } else if let Some(group) = groups.iter_mut().find(|x| x.targets == targets) {
+      group.items.push(item);
+    } else {
+      groups.push(ItemCfg {
+        targets,
+        items: vec![item],
+      });
+    }
+  }
+
+  items.extend(groups.into_iter().map(Item::Cfg));
+  items
+}
+
+#[derive(Debug, Clone)]
+struct Target {
+  os: String,
+  args: Vec<String>,
+}
+
+/// How a C++ enum is represented in the generated bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumStyle {
+  /// A `#[repr]` Rust enum. Receiving a value without a matching variant
+  /// from C++ is undefined behavior.
+  Rust,
+  /// A `#[repr(transparent)]` newtype with an associated const per
+  /// enumerator.
+  NewType,
+  /// A module containing a `Type` alias and a const per enumerator.
+  ModuleConsts,
+  /// Like `NewType`, with bitwise operators implemented.
+  Bitflags,
+}
+
+/// How `char`, `signed char` and `unsigned char` are mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharStyle {
+  /// `c_char`, `c_schar` and `c_uchar`. Whether `c_char` is signed depends
+  /// on the Rust target.
+  CChar,
+  /// `i8` for `char`, and `i8` and `u8` for the others.
+  Signed,
+  /// `u8` for `char`, and `i8` and `u8` for the others.
+  Unsigned,
+  /// `i8` or `u8` for `char`, as the target the header was parsed for
+  /// treats it, and `i8` and `u8` for the others.
+  Target,
+}
+
+/// How `Debug` is implemented for a class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugStyle {
+  /// Derived, printing every field, raw pointers included.
+  Derive,
+  /// Just the name, as `Foo { .. }`.
+  Terse,
+  /// The string returned by the named const method taking no arguments,
+  /// like `toString`. Anything `std::string` can be constructed from will
+  /// do. Classes without the method are printed tersely.
+  Method(String),
+  /// What `operator<<` writes to a `std::ostream`.
+  Stream,
+}
+
+/// How the generated glue includes the bound header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncludeStyle {
+  /// `#include "/path/to/header.h"`, the path as given to the builder.
+  Absolute,
+  /// `#include "header.h"`, with the header's directory passed via `-I`.
+  Relative,
+  /// `#include <header.h>`, with the header's directory passed via `-I`.
+  Angle,
+}
+
+/// An error that stopped bindings from being generated.
+#[derive(Debug)]
+pub enum Error {
+  /// Clang couldn't be loaded or couldn't parse the header.
+  Parse {
+    header: PathBuf,
+    message: String,
+  },
+  /// A type with no Rust equivalent, at `file:line:column`.
+  UnsupportedType {
+    ty: String,
+    location: String,
+  },
+  /// A function with no symbol to link to, at `file:line:column`.
+  MissingMangledName {
+    name: String,
+    location: String,
+  },
+  /// The header was parsed for `triple`, where the C type `ty` is `clang`
+  /// bytes, but the Rust target being built for makes it `rust` bytes.
+  TargetMismatch {
+    triple: String,
+    ty: String,
+    clang: usize,
+    rust: usize,
+  },
+  /// Reading or writing `path` failed.
+  Io {
+    path: PathBuf,
+    error: std::io::Error,
+  },
+  /// `library`, given to `verify_symbols`, doesn't define `symbols`, each
+  /// followed by the name it's bound as.
+  MissingSymbols {
+    library: PathBuf,
+    symbols: Vec<String>,
+  },
+  /// The glue compiler targets `machine`, but the header was parsed for
+  /// `triple`.
+  CompilerMismatch {
+    compiler: PathBuf,
+    machine: String,
+    triple: String,
+  },
+  /// A header whose path can't be written in an `#include`.
+  InvalidInclude {
+    path: PathBuf,
+  },
+  /// A string given as a Rust path that doesn't parse as one.
+  InvalidPath {
+    path: String,
+  },
+  /// A method signature given to `define_trait` that doesn't parse as one
+  /// taking `self`.
+  InvalidSignature {
+    signature: String,
+  },
+  /// A condition given to `precondition` that isn't a Rust expression.
+  InvalidCondition {
+    condition: String,
+  },
+  /// A glob given to `headers_from_dir` that doesn't parse.
+  InvalidPattern {
+    pattern: String,
+    message: String,
+  },
+  /// Output was to go in `OUT_DIR`, which isn't set outside build scripts.
+  MissingOutDir,
+}
+
+impl std::fmt::Display for Error {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Error::Parse { header, message } => write!(f, "failed to parse {}: {}", header.display(), message),
+      Error::UnsupportedType { ty, location } => write!(f, "{}: unsupported type `{}`", location, ty),
+      Error::MissingMangledName { name, location } => write!(f, "{}: `{}` has no mangled name", location, name),
+      Error::TargetMismatch { triple, ty, clang, rust } => write!(
+        f,
+        "the header was parsed for {}, where `{}` is {} bytes, but it's {} bytes for the Rust target; \
+        pass a matching --target in the clang arguments",
+        triple, ty, clang, rust,
+      ),
+      Error::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+      Error::MissingSymbols { library, symbols } => {
+        write!(f, "{} does not define the symbols of:", library.display())?;
+        for symbol in symbols {
+          write!(f, "\n  {}", symbol)?;
+        }
+        Ok(())
+      },
+      Error::CompilerMismatch { compiler, machine, triple } => write!(
+        f,
+        "the glue compiler {} targets {}, but the header was parsed for {}; \
+        pass a matching --target in the clang arguments or choose another compiler",
+        compiler.display(), machine, triple,
+      ),
+      Error::InvalidInclude { path } => write!(f, "{} can't be named in an #include", path.display()),
+      Error::InvalidPath { path } => write!(f, "`{}` isn't a Rust path", path),
+      Error::InvalidSignature { signature } => write!(f, "`{}` isn't a method signature taking self", signature),
+      Error::InvalidCondition { condition } => write!(f, "`{}` isn't a Rust expression", condition),
+      Error::InvalidPattern { pattern, message } => write!(f, "invalid pattern `{}`: {}", pattern, message),
+      Error::MissingOutDir => write!(f, "OUT_DIR isn't set; run from a build script or use write_to"),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Io { error, .. } => Some(error),
+      _ => None,
+    }
+  }
+}
+
+// The lines of the comment a header starts with, without the comment
+// markers, either a run of `//` lines or a single `/* */` block.
+fn license_banner(source: &str) -> Vec<String> {
+  let source = source.trim_start();
+  let lines = if let Some(rest) = source.strip_prefix("/*") {
+    match rest.find("*/") {
+      Some(end) => rest[..end].lines().map(|x| {
+        let x = x.trim();
+        x.strip_prefix('*').map_or(x, |x| x.strip_prefix(' ').unwrap_or(x))
+      }).collect::<Vec<_>>(),
+      None => Vec::new(),
+    }
+  } else {
+    source.lines().map(str::trim).take_while(|x| x.starts_with("//"))
+      .map(|x| { let x = x.trim_start_matches('/'); x.strip_prefix(' ').unwrap_or(x) })
+      .collect()
+  };
+  let start = lines.iter().position(|x| !x.is_empty()).unwrap_or(lines.len());
+  let end = lines.iter().rposition(|x| !x.is_empty()).map_or(start, |x| x + 1);
+  lines[start..end].iter().map(|x| x.trim_end().to_string()).collect()
+}
+
+fn io_error(path: &std::path::Path) -> impl FnOnce(std::io::Error) -> Error {
+  let path = path.to_path_buf();
+  move |error| Error::Io { path, error }
+}
+
+// `file:line:column` of an entity, for errors.
+fn location(e: &Entity) -> String {
+  match e.get_location().map(|x| x.get_file_location()) {
+    Some(x) => format!(
+      "{}:{}:{}",
+      x.file.map_or_else(|| "<unknown>".to_string(), |x| x.get_path().display().to_string()),
+      x.line,
+      x.column,
+    ),
+    None => "<unknown>".to_string(),
+  }
+}
+
+pub trait Callbacks: std::fmt::Debug {
+  /// Overrides whether the function or method with the given qualified name
+  /// (`ns::Class::method`) is exposed as a safe `fn`. Returning `None` keeps
+  /// the default of generating an `unsafe fn`.
+  fn is_safe(&self, _item: &str) -> Option<bool> {
+    None
+  }
+
+  /// Overrides the Rust name of the static member function `name` of the
+  /// class with the given qualified name. Returning `None` keeps the name
+  /// given by `Builder::static_method_format`.
+  fn static_method_name(&self, _class: &str, _name: &str) -> Option<String> {
+    None
+  }
+
+  /// Overrides whether the parameter `param` of the function or method with
+  /// the given qualified name is an output, returned by the bindings instead
+  /// of taken. Only non-const pointers and references can be outputs.
+  fn is_out_param(&self, _function: &str, _param: &str) -> Option<bool> {
+    None
+  }
+
+  /// Overrides the Rust name of an overload of the function or method with
+  /// the given qualified name, where overloads are bound separately. `index`
+  /// counts the overloads in declaration order. Returning `None` keeps the
+  /// name of the first and suffixes the others with their arity or argument
+  /// types.
+  fn overload_name(&self, _function: &str, _index: usize) -> Option<String> {
+    None
+  }
+}
+
+#[derive(Debug, Clone)]
+struct TaggedUnionConfig {
+  class: String,
+  tag: String,
+  field: String,
+  variants: Vec<(i64, String)>,
+}
+
+// What a header generates, before it's written anywhere.
+// What parsing a header for one target produces, with the symbols of the
+// functions the glue defines.
+struct Parsed {
+  items: Vec<Item>,
+  glue: String,
+  glue_header: String,
+  triple: String,
+  glue_symbols: HashSet<String>,
+}
+
+struct Generated {
+  bindings: String,
+  glue: String,
+  glue_header: String,
+  // The glue functions alone, which the name of the library they're
+  // compiled into is hashed from.
+  functions: String,
+  triple: String,
+  include_dirs: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Builder {
+  header: PathBuf,
+  headers: Vec<PathBuf>,
+  targets: Vec<Target>,
+  tagged_unions: Vec<TaggedUnionConfig>,
+  subclasses: Vec<String>,
+  callbacks: Option<Box<dyn Callbacks>>,
+  boxed_types: Vec<String>,
+  no_drop: Vec<String>,
+  unify_overloads: bool,
+  constructor_builders: bool,
+  prelude: bool,
+  default_enum_style: EnumStyle,
+  char_style: CharStyle,
+  enum_styles: Vec<(String, EnumStyle)>,
+  opaque_types: Vec<String>,
+  derive_eq: bool,
+  restrict_docs: bool,
+  half_crate: bool,
+  qt: bool,
+  allowlist: Vec<(AllowKind, String)>,
+  blocklist: Vec<String>,
+  existing_types: Vec<(String, String)>,
+  instantiations: Vec<String>,
+  clang_args: Vec<String>,
+  timings: bool,
+  strict_provenance: bool,
+  fuzz: Option<(PathBuf, String)>,
+  header_dir: Option<(PathBuf, String)>,
+  verify_symbols: Option<PathBuf>,
+  #[cfg(feature = "ir")]
+  ir: Option<PathBuf>,
+  include_style: IncludeStyle,
+  include_rewrites: Vec<(PathBuf, String)>,
+  include_dirs: Vec<PathBuf>,
+  glue_name: Option<String>,
+  glue_compilers: Vec<(Option<String>, PathBuf)>,
+  module_name: Option<String>,
+  static_method_format: String,
+  str_constructors: bool,
+  type_aliases: bool,
+  aggregate_constructors: bool,
+  slice_accessors: Vec<(String, String, String)>,
+  detect_slice_accessors: bool,
+  out_params: Vec<(String, String)>,
+  slice_params: Vec<(String, String, String)>,
+  detect_slice_params: bool,
+  preconditions: Vec<(String, String)>,
+  detect_out_params: bool,
+  borrow_references: bool,
+  layout_tests: bool,
+  serde_derives: Vec<String>,
+  serde_rename_all: Option<String>,
+  serde_skips: Vec<String>,
+  license_banner: bool,
+  safe_api: bool,
+  owned_returns: Vec<String>,
+  borrowed_returns: Vec<String>,
+  error_types: Vec<(String, String)>,
+  catch_exceptions: bool,
+  cxx_string: bool,
+  module_path: Option<String>,
+  symbols: bool,
+  wide_strings: bool,
+  traits: Vec<(String, Vec<String>)>,
+  out_file: Option<PathBuf>,
+  glue_file: Option<PathBuf>,
+  default_debug_style: DebugStyle,
+  debug_styles: Vec<(String, DebugStyle)>,
+  used: Vec<String>,
+  used_in: Vec<PathBuf>,
+  dynamic_loading: bool,
+}
+
+impl Builder {
+  pub fn new<P: Into<PathBuf>>(header: P) -> Builder {
+    Builder {
+      header: header.into(),
+      headers: Vec::new(),
+      targets: Vec::new(),
+      tagged_unions: Vec::new(),
+      subclasses: Vec::new(),
+      callbacks: None,
+      boxed_types: Vec::new(),
+      no_drop: Vec::new(),
+      unify_overloads: false,
+      constructor_builders: false,
+      prelude: false,
+      default_enum_style: EnumStyle::NewType,
+      char_style: CharStyle::CChar,
+      enum_styles: Vec::new(),
+      opaque_types: Vec::new(),
+      derive_eq: false,
+      restrict_docs: false,
+      half_crate: false,
+      qt: false,
+      allowlist: Vec::new(),
+      blocklist: Vec::new(),
+      existing_types: Vec::new(),
+      instantiations: Vec::new(),
+      clang_args: Vec::new(),
+      timings: false,
+      strict_provenance: false,
+      fuzz: None,
+      verify_symbols: None,
+      #[cfg(feature = "ir")]
+      ir: None,
+      include_style: IncludeStyle::Absolute,
+      include_rewrites: Vec::new(),
+      include_dirs: Vec::new(),
+      glue_name: None,
+      glue_compilers: Vec::new(),
+      module_name: None,
+      static_method_format: "{}".to_string(),
+      str_constructors: false,
+      type_aliases: false,
+      aggregate_constructors: false,
+      slice_accessors: Vec::new(),
+      detect_slice_accessors: false,
+      out_params: Vec::new(),
+      slice_params: Vec::new(),
+      detect_slice_params: false,
+      preconditions: Vec::new(),
+      detect_out_params: false,
+      borrow_references: false,
+      layout_tests: false,
+      serde_derives: Vec::new(),
+      serde_rename_all: None,
+      serde_skips: Vec::new(),
+      license_banner: false,
+      safe_api: false,
+      owned_returns: Vec::new(),
+      borrowed_returns: Vec::new(),
+      error_types: Vec::new(),
+      catch_exceptions: false,
+      cxx_string: false,
+      module_path: None,
+      symbols: false,
+      wide_strings: false,
+      traits: Vec::new(),
+      out_file: None,
+      glue_file: None,
+      default_debug_style: DebugStyle::Derive,
+      debug_styles: Vec::new(),
+      used: Vec::new(),
+      used_in: Vec::new(),
+      dynamic_loading: false,
+      header_dir: None,
+    }
+  }
+
+  /// Binds every header under `dir` matching the glob `pattern`, through an
+  /// umbrella header that includes them in path order. The directory is
+  /// scanned and the umbrella written beside the bindings when generating.
+  pub fn headers_from_dir<P: Into<PathBuf>, S: Into<String>>(dir: P, pattern: S) -> Builder {
+    let dir = dir.into();
+    let mut builder = Builder::new(dir.join("umbrella.h"));
+    builder.header_dir = Some((dir, pattern.into()));
+    builder
+  }
+
+  /// Adds another header to bind along with the first. They're parsed
+  /// together, as if included one after the other, and the glue includes
+  /// each of them.
+  pub fn header<P: Into<PathBuf>>(mut self, header: P) -> Builder {
+    self.headers.push(header.into());
+    self
+  }
+
+  /// Adds a target to generate for, parsed with the given extra clang
+  /// arguments (`--target=...`, `-D...`). Items that differ between targets
+  /// are emitted under `#[cfg(target_os = "<os>")]`.
+  pub fn target<S: Into<String>>(mut self, os: S, args: &[&str]) -> Builder {
+    self.targets.push(Target {
+      os: os.into(),
+      args: args.iter().map(|x| x.to_string()).collect(),
+    });
+    self
+  }
+
+  /// Marks the union field `field` of `class` as discriminated by the field
+  /// `tag`, where each `(value, member)` pair names the union member that is
+  /// active when the tag equals `value`. Generates safe `as_<member>()`
+  /// accessors and a `From<&Class>` conversion into a Rust enum.
+  pub fn tagged_union<S: Into<String>>(mut self, class: S, tag: S, field: S, variants: &[(i64, &str)]) -> Builder {
+    self.tagged_unions.push(TaggedUnionConfig {
+      class: class.into(),
+      tag: tag.into(),
+      field: field.into(),
+      variants: variants.iter().map(|(value, member)| (*value, member.to_string())).collect(),
+    });
+    self
+  }
+
+  /// Registers `class` for subclassing from Rust. A C++ shim deriving from
+  /// it is generated in glue, forwarding every virtual method to the
+  /// function pointers of a `<Class>Virtuals` table, and protected members of
+  /// the class are bound alongside the public ones.
+  pub fn subclass<S: Into<String>>(mut self, class: S) -> Builder {
+    self.subclasses.push(class.into());
+    self
+  }
+
+  /// Constructs `class` on the C++ heap instead of in place: `new` is
+  /// replaced by `new_boxed`, returning a `<Class>Box` owner whose pointee
+  /// never moves and is destroyed through glue.
+  pub fn heap_allocated<S: Into<String>>(mut self, class: S) -> Builder {
+    self.boxed_types.push(class.into());
+    self
+  }
+
+  /// Leaves `class` to be owned by C++, for singletons and objects something
+  /// else destroys. It's not constructed nor dropped by the bindings, only
+  /// borrowed from the pointers C++ gives out with `from_ptr`.
+  pub fn no_drop<S: Into<String>>(mut self, class: S) -> Builder {
+    self.no_drop.push(class.into());
+    self
+  }
+
+  /// Binds each set of overloaded methods as one generic method taking
+  /// `impl Into<<Class><Method>Args>`, an enum with a variant per overload,
+  /// instead of one method per overload.
+  pub fn unify_overloads(mut self, doit: bool) -> Builder {
+    self.unify_overloads = doit;
+    self
+  }
+
+  /// Generates a `<Class>Builder` for constructors with defaulted
+  /// parameters, with a setter for each defaulted parameter and a `build()`
+  /// that leaves unset trailing parameters to their C++ defaults.
+  pub fn constructor_builders(mut self, doit: bool) -> Builder {
+    self.constructor_builders = doit;
+    self
+  }
+
+  /// Emits a `prelude` module re-exporting every generated type, owner
+  /// wrapper and helper trait, so they can be imported with
+  /// `use bindings::prelude::*`.
+  pub fn prelude(mut self, doit: bool) -> Builder {
+    self.prelude = doit;
+    self
+  }
+
+  /// Sets the representation used for enums without a per-enum override.
+  /// Defaults to `EnumStyle::NewType`.
+  pub fn default_enum_style(mut self, style: EnumStyle) -> Builder {
+    self.default_enum_style = style;
+    self
+  }
+
+  /// Sets how the character types are mapped, in signatures and fields
+  /// alike. Defaults to `CharStyle::CChar`.
+  pub fn char_style(mut self, style: CharStyle) -> Builder {
+    self.char_style = style;
+    self
+  }
+
+  /// Sets the representation of the enum with the given plain or qualified
+  /// name.
+  pub fn enum_style<S: Into<String>>(mut self, name: S, style: EnumStyle) -> Builder {
+    self.enum_styles.push((name.into(), style));
+    self
+  }
+
+  /// Sets how `Debug` is implemented for classes without a per-class
+  /// override. Defaults to `DebugStyle::Derive`.
+  pub fn default_debug_style(mut self, style: DebugStyle) -> Builder {
+    self.default_debug_style = style;
+    self
+  }
+
+  /// Sets how `Debug` is implemented for the class with the given plain or
+  /// qualified name.
+  pub fn debug_style<S: Into<String>>(mut self, name: S, style: DebugStyle) -> Builder {
+    self.debug_styles.push((name.into(), style));
+    self
+  }
+
+  pub fn rustified_enum<S: Into<String>>(self, name: S) -> Builder {
+    self.enum_style(name, EnumStyle::Rust)
+  }
+
+  pub fn newtype_enum<S: Into<String>>(self, name: S) -> Builder {
+    self.enum_style(name, EnumStyle::NewType)
+  }
+
+  pub fn constified_enum_module<S: Into<String>>(self, name: S) -> Builder {
+    self.enum_style(name, EnumStyle::ModuleConsts)
+  }
+
+  pub fn bitfield_enum<S: Into<String>>(self, name: S) -> Builder {
+    self.enum_style(name, EnumStyle::Bitflags)
+  }
+
+  /// Binds the class with the given plain or qualified name as an opaque
+  /// blob of the right size and alignment, without fields or methods.
+  pub fn opaque_type<S: Into<String>>(mut self, name: S) -> Builder {
+    self.opaque_types.push(name.into());
+    self
+  }
+
+  /// Derives `PartialEq` for POD structs with only public fields and no
+  /// user-defined `operator==`, and `Eq` and `Hash` when they contain no
+  /// floating point fields.
+  pub fn derive_eq(mut self, doit: bool) -> Builder {
+    self.derive_eq = doit;
+    self
+  }
+
+  /// Notes the aliasing contract of `restrict` qualified pointer arguments
+  /// in the documentation of the generated functions.
+  pub fn restrict_docs(mut self, doit: bool) -> Builder {
+    self.restrict_docs = doit;
+    self
+  }
+
+  /// Maps half precision floats to the types of the `half` crate instead of
+  /// the generated `Float16` and `BFloat16`.
+  pub fn half_crate(mut self, doit: bool) -> Builder {
+    self.half_crate = doit;
+    self
+  }
+
+  /// Binds the plain C++ surface of classes using Qt's `Q_OBJECT`, skipping
+  /// signals and other members only moc would define.
+  pub fn qt(mut self, doit: bool) -> Builder {
+    self.qt = doit;
+    self
+  }
+
+  /// Only binds the items with the given plain or qualified name and what
+  /// they use. Classes only used behind pointers are left opaque, so the rest
+  /// of the header is never walked.
+  ///
+  /// This and every other option taking a name also take a glob, matched
+  /// against both the plain and the qualified name: `*` matches any run of
+  /// characters, `::` included, so `ns::*` matches everything in `ns` and
+  /// the namespaces in it, `?` matches one character and `[abc]` or `[a-z]`
+  /// one of a set. A name is always matched exactly too, and ones naming an
+  /// operator, like `Vec::operator*`, are only matched exactly.
+  pub fn allowlist<S: Into<String>>(mut self, name: S) -> Builder {
+    self.allowlist.push((AllowKind::Any, name.into()));
+    self
+  }
+
+  /// Like `allowlist`, matching only classes, structs, unions, enums and
+  /// typedefs.
+  pub fn allowlist_type<S: Into<String>>(mut self, name: S) -> Builder {
+    self.allowlist.push((AllowKind::Type, name.into()));
+    self
+  }
+
+  /// Like `allowlist`, matching only free functions.
+  pub fn allowlist_function<S: Into<String>>(mut self, name: S) -> Builder {
+    self.allowlist.push((AllowKind::Function, name.into()));
+    self
+  }
+
+  /// Like `allowlist`, matching everything declared in the namespaces with
+  /// the given name.
+  pub fn allowlist_namespace<S: Into<String>>(mut self, name: S) -> Builder {
+    self.allowlist.push((AllowKind::Namespace, name.into()));
+    self
+  }
+
+  /// Narrows the allowlist to the items the Rust code in `path`, a file or a
+  /// directory searched for `.rs` files, refers to by name, and what they
+  /// use. Referring to something new and rebuilding binds it, so only what's
+  /// used is generated and compiled. Point it at the code using the
+  /// bindings, not at checked-in bindings, which name everything.
+  pub fn allowlist_used_in<P: Into<PathBuf>>(mut self, path: P) -> Builder {
+    self.used_in.push(path.into());
+    self
+  }
+
+  /// Like `allowlist_used_in`, with the names used given directly.
+  pub fn allowlist_used(mut self, names: &[&str]) -> Builder {
+    self.used.extend(names.iter().map(|x| x.to_string()));
+    self
+  }
+
+  /// Never binds the items, members or namespaces with the given plain or
+  /// qualified name, or glob, even when allowlisted. Anything using a
+  /// blocklisted type expects it to be defined next to the bindings.
+  pub fn blocklist_item<S: Into<String>>(mut self, name: S) -> Builder {
+    self.blocklist.push(name.into());
+    self
+  }
+
+  /// Refers to the class or enum with the given plain or qualified name, or
+  /// glob, by the Rust path `path` instead of binding it, like a type bindgen
+  /// already generated in a `-sys` crate. It has to have the same layout.
+  pub fn use_existing_type<S: Into<String>, P: Into<String>>(mut self, name: S, path: P) -> Builder {
+    self.existing_types.push((name.into(), path.into()));
+    self
+  }
+
+  /// Binds the instantiation of a class template with the given arguments,
+  /// like `SmallVec<int, 8>`, as `SmallVec_int_8`. Its public members are
+  /// called through the glue, which explicitly instantiates the template.
+  ///
+  /// A template instantiated for several values of its only parameter, when
+  /// that's an integer, is also bound as a generic struct, `FixedBuffer<16>`
+  /// and `FixedBuffer<32>` for `FixedBuffer_16` and `FixedBuffer_32`. It has
+  /// the members whose signatures don't differ between them, is as big as
+  /// the biggest and converts from each with `From`. Fields and arguments
+  /// still use the instantiations, which have their exact layout.
+  pub fn instantiate_template<S: Into<String>>(mut self, ty: S) -> Builder {
+    self.instantiations.push(ty.into());
+    self
+  }
+
+  /// Passes an argument to clang when parsing the header, after those of
+  /// the target being generated for. Headers are parsed as C++11 unless a
+  /// `-std=` is given, which along with any `-D` and `-I` also applies when
+  /// compiling the glue.
+  pub fn clang_arg<S: Into<String>>(mut self, arg: S) -> Builder {
+    self.clang_args.push(arg.into());
+    self
+  }
+
+  /// Like `clang_arg`, passing several.
+  pub fn clang_args<I: IntoIterator>(mut self, args: I) -> Builder where I::Item: Into<String> {
+    self.clang_args.extend(args.into_iter().map(Into::into));
+    self
+  }
+
+  /// Reports how long parsing, walking the AST, writing the bindings and
+  /// compiling the glue took as build script warnings. With the `tracing`
+  /// feature, each of these phases is also an `info` span named `blackbird`
+  /// with the phase as its `phase` field, whether this is set or not.
+  pub fn timings(mut self, doit: bool) -> Builder {
+    self.timings = doit;
+    self
+  }
+
+  /// Constructs objects in uninitialized memory instead of zeroed memory
+  /// and takes pointers without intermediate references, so the
+  /// bindings can be checked under Miri.
+  pub fn strict_provenance(mut self, doit: bool) -> Builder {
+    self.strict_provenance = doit;
+    self
+  }
+
+  /// Writes a `cargo-fuzz` target into `dir` for every free function taking
+  /// only scalars and byte buffers followed by their length. `module` is the
+  /// path the harnesses reach the bindings at, e.g. `my_sys::ffi`.
+  pub fn fuzz_harnesses<P: Into<PathBuf>, S: Into<String>>(mut self, dir: P, module: S) -> Builder {
+    self.fuzz = Some((dir.into(), module.into()));
+    self
+  }
+
+  /// Checks that the given library or archive defines every symbol the
+  /// bindings link to, failing generation with a list of the missing ones.
+  pub fn verify_symbols<P: Into<PathBuf>>(mut self, library: P) -> Builder {
+    self.verify_symbols = Some(library.into());
+    self
+  }
+
+  /// Writes the IR to `path` as versioned JSON, for tools that inspect the
+  /// bound API. The `schema` member holds the format version. Needs the
+  /// `ir` feature.
+  #[cfg(feature = "ir")]
+  pub fn ir<P: Into<PathBuf>>(mut self, path: P) -> Builder {
+    self.ir = Some(path.into());
+    self
+  }
+
+  /// Sets how the glue includes the header. Paths that can't be spelled in
+  /// an `#include`, such as ones containing quotes or that aren't UTF-8, are
+  /// always included relative to their directory.
+  pub fn include_style(mut self, style: IncludeStyle) -> Builder {
+    self.include_style = style;
+    self
+  }
+
+  /// Includes the header from the glue by a rewritten path when it lies
+  /// under `prefix`, replacing the prefix with `replacement`, e.g. turning
+  /// `/src/mylib/include/mylib/api.h` into `<mylib/api.h>` with a prefix of
+  /// `/src/mylib/include` and an empty replacement. The first matching rule
+  /// is used, with the delimiters of the include style.
+  pub fn rewrite_include<P: Into<PathBuf>, S: Into<String>>(mut self, prefix: P, replacement: S) -> Builder {
+    self.include_rewrites.push((prefix.into(), replacement.into()));
+    self
+  }
+
+  /// Adds a directory to the include path the glue is compiled with.
+  pub fn include_dir<P: Into<PathBuf>>(mut self, dir: P) -> Builder {
+    self.include_dirs.push(dir.into());
+    self
+  }
+
+  /// Sets the name of the static library the glue is compiled into. By
+  /// default it's derived from the crate name and a hash of the glue, so
+  /// crates using blackbird don't collide in the same link.
+  pub fn glue_name<S: Into<String>>(mut self, name: S) -> Builder {
+    self.glue_name = Some(name.into());
+    self
+  }
+
+  /// Compiles the glue with the given C++ compiler instead of the one `cc`
+  /// picks from the environment. The compiler has to target the same ABI
+  /// the header was parsed for, which is checked before compiling.
+  pub fn glue_compiler<P: Into<PathBuf>>(mut self, compiler: P) -> Builder {
+    self.glue_compilers.push((None, compiler.into()));
+    self
+  }
+
+  /// Like `glue_compiler`, for builds targeting the given Rust target
+  /// triple only. Takes precedence over `glue_compiler`.
+  pub fn glue_compiler_for<S: Into<String>, P: Into<PathBuf>>(mut self, target: S, compiler: P) -> Builder {
+    self.glue_compilers.push((Some(target.into()), compiler.into()));
+    self
+  }
+
+  /// Wraps everything generated, including items outside any namespace, in
+  /// a `pub mod` of the given name.
+  pub fn module_name<S: Into<String>>(mut self, name: S) -> Builder {
+    self.module_name = Some(name.into());
+    self
+  }
+
+  /// Names static member functions after `format`, with `{}` standing for
+  /// the C++ name, e.g. `new_from_{}`. Defaults to the C++ name. Names that
+  /// collide with another member of the class get a `_static` suffix.
+  pub fn static_method_format<S: Into<String>>(mut self, format: S) -> Builder {
+    self.static_method_format = format.into();
+    self
+  }
+
+  /// Implements `From<&str>` for classes with a constructor taking a
+  /// `std::string`, and `TryFrom<&str>` for ones taking a `const char*`,
+  /// failing on interior nuls. The constructor is trusted to be safe to
+  /// call with any string.
+  pub fn str_constructors(mut self, doit: bool) -> Builder {
+    self.str_constructors = doit;
+    self
+  }
+
+  /// Binds `typedef`s and `using` aliases outside of classes as `pub type`
+  /// aliases, and refers to them by name in signatures rather than by the
+  /// type they stand for.
+  pub fn type_aliases(mut self, doit: bool) -> Builder {
+    self.type_aliases = doit;
+    self
+  }
+
+  /// Adds `new`, taking each field in order, to classes without
+  /// constructors, bases or virtual methods whose fields are all public,
+  /// building them in Rust without calling C++.
+  pub fn aggregate_constructors(mut self, doit: bool) -> Builder {
+    self.aggregate_constructors = doit;
+    self
+  }
+
+  /// Adds `as_slice` to classes with const `data()` and `size()` methods,
+  /// returning the elements the one points to as a slice as long as the
+  /// other says. The pair is trusted to describe valid elements for as
+  /// long as the object is borrowed.
+  pub fn detect_slice_accessors(mut self, doit: bool) -> Builder {
+    self.detect_slice_accessors = doit;
+    self
+  }
+
+  /// Adds `as_slice` to the classes matching `class`, plain or qualified,
+  /// from the methods named `data` and `size` rather than `data()` and
+  /// `size()`, e.g. `slice_accessor("Buffer", "bytes", "length")`.
+  pub fn slice_accessor<S: Into<String>, T: Into<String>, U: Into<String>>(mut self, class: S, data: T, size: U) -> Builder {
+    self.slice_accessors.push((class.into(), data.into(), size.into()));
+    self
+  }
+
+  /// Treats the parameter `param` of the function or method with the given
+  /// plain or qualified name as an output: the bindings allocate what it
+  /// points to and return it after the result, as `(ret, out)`.
+  pub fn out_param<S: Into<String>, T: Into<String>>(mut self, function: S, param: T) -> Builder {
+    self.out_params.push((function.into(), param.into()));
+    self
+  }
+
+  /// Takes the parameter `pointer` of the function or method with the given
+  /// plain or qualified name, and the `length` parameter directly following
+  /// it, as one `&[T]`, or `&mut [T]` if what it points to isn't const.
+  /// `void` buffers are taken as `&[u8]`.
+  pub fn slice_param<S: Into<String>, T: Into<String>, U: Into<String>>(mut self, function: S, pointer: T, length: U) -> Builder {
+    self.slice_params.push((function.into(), pointer.into(), length.into()));
+    self
+  }
+
+  /// Treats pointer parameters directly followed by an integer named `n` or
+  /// ending in `len`, `length`, `size` or `count` as slices, like
+  /// `slice_param`.
+  pub fn detect_slice_params(mut self, doit: bool) -> Builder {
+    self.detect_slice_params = doit;
+    self
+  }
+
+  /// Checks `condition`, a Rust expression over the parameters, in debug
+  /// builds before calling the function or method with the given plain or
+  /// qualified name. Simple `assert`s and gsl `Expects` leading inline
+  /// definitions are checked without being given.
+  pub fn precondition<S: Into<String>, T: Into<String>>(mut self, function: S, condition: T) -> Builder {
+    self.preconditions.push((function.into(), condition.into()));
+    self
+  }
+
+  /// Treats non-const pointer and reference parameters named `out...` or
+  /// `..._out` as outputs, like `out_param`.
+  pub fn detect_out_params(mut self, doit: bool) -> Builder {
+    self.detect_out_params = doit;
+    self
+  }
+
+  /// Takes lvalue reference parameters as `&T` or `&mut T` and returns
+  /// lvalue references as `NonNull<T>`, instead of raw pointers.
+  pub fn borrow_references(mut self, doit: bool) -> Builder {
+    self.borrow_references = doit;
+    self
+  }
+
+  /// Checks the size and alignment of every class bound by its fields, and
+  /// the offsets of the fields, against those clang gives, with `assert!`s
+  /// evaluated at compile time when the crate including the bindings is
+  /// built for tests. Needs Rust 1.77 for `offset_of!`.
+  pub fn layout_tests(mut self, doit: bool) -> Builder {
+    self.layout_tests = doit;
+    self
+  }
+
+  /// Derives `serde::Serialize` and `serde::Deserialize` for the structs
+  /// whose plain or qualified name matches the glob `pattern`, skipping
+  /// their pointer fields, which deserialize as null. Classes holding a
+  /// vtable pointer are left out. The crate including the bindings needs
+  /// `serde` with its `derive` feature.
+  pub fn serde_derive<S: Into<String>>(mut self, pattern: S) -> Builder {
+    self.serde_derives.push(pattern.into());
+    self
+  }
+
+  /// Renames the fields of the structs deriving `serde` traits by a rule
+  /// of `#[serde(rename_all)]`, e.g. `"camelCase"`.
+  pub fn serde_rename_all<S: Into<String>>(mut self, rule: S) -> Builder {
+    self.serde_rename_all = Some(rule.into());
+    self
+  }
+
+  /// Skips the field with the given name, or `Class::field` for one class,
+  /// when serializing the structs deriving `serde` traits, deserializing
+  /// it as its default.
+  pub fn serde_skip<S: Into<String>>(mut self, field: S) -> Builder {
+    self.serde_skips.push(field.into());
+    self
+  }
+
+  /// Copies the comment at the top of the first header, usually its
+  /// license, to the top of the bindings and the glue, followed by a line
+  /// saying which headers they were generated from and by which version.
+  pub fn license_banner(mut self, doit: bool) -> Builder {
+    self.license_banner = doit;
+    self
+  }
+
+  /// Makes the functions, constructors and methods whose Rust signatures
+  /// only take and return plain values safe to call, trusting them to be
+  /// sound for any of those values. The functions and methods taking or
+  /// returning raw pointers move to a `raw` module next to them, the
+  /// methods of a class `Foo` as the trait `raw::FooRaw`.
+  pub fn safe_api(mut self, doit: bool) -> Builder {
+    self.safe_api = doit;
+    self
+  }
+
+  /// Returns the pointer the method with the given plain or qualified name
+  /// returns, to a class allocated with `new`, as an `Option<CxxBox<T>>`
+  /// deleting it when dropped.
+  pub fn returns_owned<S: Into<String>>(mut self, method: S) -> Builder {
+    self.owned_returns.push(method.into());
+    self
+  }
+
+  /// Returns the pointer or reference the method with the given plain or
+  /// qualified name returns as a reference borrowing from the object it's
+  /// called on, an `Option` for pointers. It's `&mut` when neither the
+  /// method nor what it returns is const.
+  pub fn returns_borrowed<S: Into<String>>(mut self, method: S) -> Builder {
+    self.borrowed_returns.push(method.into());
+    self
+  }
+
+  /// Returns the status type with the given plain or qualified name as the
+  /// error of a `Result`, `ok` being the Rust expression of its success
+  /// value, e.g. `error_type("Status", "Status::Ok")`. Output parameters
+  /// become the value of the `Result`.
+  pub fn error_type<S: Into<String>, T: Into<String>>(mut self, ty: S, ok: T) -> Builder {
+    self.error_types.push((ty.into(), ok.into()));
+    self
+  }
+
+  /// Calls functions and methods through glue catching what they throw,
+  /// which would otherwise unwind into Rust, and returns it as the error of
+  /// a `Result<T, CxxException>`. Those declared `noexcept` are called
+  /// directly as before.
+  pub fn catch_exceptions(mut self, doit: bool) -> Builder {
+    self.catch_exceptions = doit;
+    self
+  }
+
+  /// Passes `std::string` parameters and returns through as the opaque
+  /// `CxxString` rather than converting them to `&str` and `String`.
+  pub fn cxx_string(mut self, doit: bool) -> Builder {
+    self.cxx_string = doit;
+    self
+  }
+
+  /// Sets the path the bindings are included at, such as `crate::bindings`,
+  /// for types to be referred to by their full path. By default types in
+  /// other namespaces are referred to from the root of the bindings.
+  pub fn module_path<S: Into<String>>(mut self, path: S) -> Builder {
+    self.module_path = Some(path.into());
+    self
+  }
+
+  /// Emits a `symbols` module listing the qualified C++ name and USR of each
+  /// bound item, along with its Rust path and the symbol it links to.
+  pub fn symbols(mut self, doit: bool) -> Builder {
+    self.symbols = doit;
+    self
+  }
+
+  /// Adds a wrapper suffixed `_os` to functions taking `const wchar_t*`,
+  /// taking anything that converts to an `OsStr`, like `&str`, instead. The
+  /// strings are converted to UTF-16 or UTF-32 to match `wchar_t`, and nul
+  /// terminated.
+  pub fn wide_strings(mut self, doit: bool) -> Builder {
+    self.wide_strings = doit;
+    self
+  }
+
+  /// Defines a trait with the given methods, written the way the bindings
+  /// spell them (`fn area(&self) -> f64`) with `Self` standing for the
+  /// class, and implements it for every class with methods matching all of
+  /// them. Argument names don't need to match. C++ classes sharing an
+  /// interface but not a base can then be used generically.
+  pub fn define_trait<S: Into<String>>(mut self, name: S, methods: &[&str]) -> Builder {
+    self.traits.push((name.into(), methods.iter().map(|x| x.to_string()).collect()));
+    self
+  }
+
+  /// Resolves what the bindings call in the library when it's loaded at
+  /// runtime, by `library::Library::open(path)` and `install`, instead of
+  /// linking to it. Calls the glue makes, like those of virtual methods,
+  /// shims and template members, still link to it when compiled.
+  pub fn dynamic_loading(mut self, doit: bool) -> Builder {
+    self.dynamic_loading = doit;
+    self
+  }
+
+  /// Writes the bindings to `path` rather than `bindings.rs` in `OUT_DIR`.
+  pub fn out_file<P: Into<PathBuf>>(mut self, path: P) -> Builder {
+    self.out_file = Some(path.into());
+    self
+  }
+
+  /// Writes the glue to `path` rather than `glue.cc` in `OUT_DIR`. Its
+  /// header is written beside it, with the extension changed to `.h`.
+  pub fn glue_file<P: Into<PathBuf>>(mut self, path: P) -> Builder {
+    self.glue_file = Some(path.into());
+    self
+  }
+
+  pub fn callbacks(mut self, callbacks: Box<dyn Callbacks>) -> Builder {
+    self.callbacks = Some(callbacks);
+    self
+  }
+
+  // The identifiers the Rust code using the bindings refers to, when the
+  // allowlist is narrowed to them.
+  fn used_names(&self) -> Result<Option<HashSet<String>>, Error> {
+    if self.used.is_empty() && self.used_in.is_empty() {
+      return Ok(None);
+    }
+
+    let mut names = self.used.iter().cloned().collect::<HashSet<_>>();
+    for path in &self.used_in {
+      println!("cargo:rerun-if-changed={}", path.display());
+      let mut files = Vec::new();
+      if path.is_dir() {
+        rust_files(path, &mut files)?;
+      } else {
+        files.push(path.clone());
+      }
+      for file in files {
+        let source = fs::read_to_string(&file).map_err(io_error(&file))?;
+        rust_identifiers(&source, &mut names);
+      }
+    }
+    Ok(Some(names))
+  }
+
+  // The `#include` of a header in the glue, and the directory to search for
+  // it when it isn't found by the path it's included by.
+  fn include(&self, header: &Path) -> Result<(String, Option<PathBuf>), Error> {
+    let (open, close) = if self.include_style == IncludeStyle::Angle { ('<', '>') } else { ('"', '"') };
+
+    // Rewritten paths are meant to be portable, so always use forward
+    // slashes.
+    let rewritten = self.include_rewrites.iter().find_map(|(prefix, replacement)| {
+      let rest = header.strip_prefix(prefix).ok()?;
+      let mut parts = Vec::new();
+      if !replacement.is_empty() {
+        parts.push(replacement.trim_end_matches('/'));
+      }
+      for part in rest.components() {
+        parts.push(part.as_os_str().to_str()?);
+      }
+      Some(parts.join("/"))
+    });
+    if let Some(path) = rewritten {
+      if path.contains(&[close, '\n'][..]) {
+        return Err(Error::InvalidInclude { path: PathBuf::from(path) });
+      }
+      return Ok((format!("#include {}{}{}", open, path, close), None));
+    }
+
+    // Header names have no escapes, so the only thing that can be done about
+    // a Windows separator is to use a forward slash, which every toolchain
+    // there accepts.
+    let absolute = header.to_str().filter(|x| !x.contains(&['"', '\n'][..]));
+    if let (IncludeStyle::Absolute, Some(path)) = (self.include_style, absolute) {
+      let path = if cfg!(windows) { path.replace('\\', "/") } else { path.to_string() };
+      return Ok((format!("#include \"{}\"", path), None));
+    }
+
+    let name = header.file_name()
+      .and_then(|x| x.to_str())
+      .filter(|x| !x.contains(&[close, '\n'][..]))
+      .ok_or_else(|| Error::InvalidInclude { path: header.to_path_buf() })?;
+    let dir = match header.parent() {
+      Some(x) if !x.as_os_str().is_empty() => x.to_path_buf(),
+      _ => PathBuf::from("."),
+    };
+    Ok((format!("#include {}{}{}", open, name, close), Some(dir)))
+  }
+
+  fn blocklisted(&self, e: &Entity) -> bool {
+    self.blocklist.iter().any(|x| matches_name(e, x))
+  }
+
+  // The path given to `use_existing_type` for `e`, if any. The paths are
+  // checked before parsing, by `check_paths`.
+  fn existing_type(&self, e: &Entity) -> Option<TokenStream> {
+    let (_, path) = self.existing_types.iter().rev().find(|x| matches_name(e, &x.0))?;
+    TokenStream::from_str(path).ok()
+  }
+
+  // Checks the Rust paths given to `use_existing_type` and `module_path`.
+  fn check_paths(&self) -> Result<(), Error> {
+    for path in self.existing_types.iter().map(|x| &x.1).chain(&self.module_path) {
+      match TokenStream::from_str(path) {
+        Ok(x) if !x.is_empty() => {},
+        _ => return Err(Error::InvalidPath { path: path.clone() }),
+      }
+    }
+    Ok(())
+  }
+
+  // Checks the sizes of C types against those of the Rust target when
+  // `check_sizes` is set, for parses meant for the target being built for.
+  fn parse(&self, index: &Index, extra_args: &[String], check_sizes: bool, traits: &[Trait], preconditions: &[(String, TokenStream)], timings: &mut Timings) -> Result<Parsed, Error> {
+    // Pragmas don't affect the bindings beyond layout, which clang already
+    // accounts for, so any it doesn't know or ignores shouldn't be reported.
+    let mut args = vec![
+      "-std=c++11".to_string(),
+      "-Wno-unknown-pragmas".to_string(),
+      "-Wno-ignored-pragmas".to_string(),
+      "-Wno-pragma-once-outside-header".to_string(),
+    ];
+    if self.qt {
+      args.push(QT_ANNOTATE.to_string());
+      args.extend(QT_DEFINES[1..].iter().map(|x| x.to_string()));
+    }
+    args.extend(extra_args.iter().cloned());
+    args.extend(self.clang_args.iter().cloned());
+
+    // Several headers are parsed through one that isn't on disk, including
+    // each of them by its absolute path.
+    let (input, mut contents) = if self.headers.is_empty() {
+      (self.header.clone(), None)
+    } else {
+      let mut contents = String::new();
+      for header in Some(&self.header).into_iter().chain(&self.headers) {
+        let path = fs::canonicalize(header).map_err(io_error(header))?;
+        let path = path.to_str()
+          .filter(|x| !x.contains(&['"', '\n'][..]))
+          .ok_or_else(|| Error::InvalidInclude { path: header.clone() })?;
+        let path = if cfg!(windows) { path.trim_start_matches(r"\\?\").replace('\\', "/") } else { path.to_string() };
+        contents += &format!("#include \"{}\"\n", path);
+      }
+      (self.header.with_file_name("__blackbird_headers.h"), Some(contents))
+    };
+
+    // Instantiations are named by a typedef appended to the header, as clang
+    // only instantiates templates where they're used, and so are the C types
+    // whose sizes are checked.
+    let rust_sizes = if check_sizes { rust_c_sizes() } else { None };
+    let mut unsaved = Vec::new();
+    if !self.instantiations.is_empty() || rust_sizes.is_some() {
+      let contents = match &mut contents {
+        Some(x) => x,
+        None => contents.insert(fs::read_to_string(&self.header).map_err(io_error(&self.header))?),
+      };
+      for (i, instantiation) in self.instantiations.iter().enumerate() {
+        *contents += &format!("\ntypedef {} __blackbird_instantiation_{};\n", instantiation, i);
+      }
+      for (i, (ty, _)) in rust_sizes.iter().flatten().enumerate() {
+        *contents += &format!("\ntypedef {} __blackbird_size_{};\n", ty, i);
+      }
+    }
+    if let Some(contents) = contents {
+      unsaved.push(Unsaved::new(&input, contents));
+    }
+
+    let tu = timings.time("parsing", || index.parser(&input).arguments(&args).unsaved(&unsaved).parse())
+      .map_err(|x| Error::Parse { header: self.header.clone(), message: x.to_string() })?;
+    let entity = tu.get_entity();
+    let triple = tu.get_target().triple;
+
+    for (i, (ty, rust)) in rust_sizes.iter().flatten().enumerate() {
+      let name = format!("__blackbird_size_{}", i);
+      let typedef = entity.get_children().into_iter().find(|x| x.get_name().as_ref() == Some(&name));
+      let clang = typedef.and_then(|x| x.get_typedef_underlying_type()).and_then(|x| x.get_sizeof().ok());
+      if let Some(clang) = clang.filter(|x| x != rust) {
+        return Err(Error::TargetMismatch { triple, ty: ty.to_string(), clang, rust: *rust });
+      }
+    }
+
+    let mut state = State {
+      builder: self,
+      triple: triple.clone(),
+      glue: String::new(),
+      glue_header: String::new(),
+      helpers: Default::default(),
+      resolving: Default::default(),
+      location: Default::default(),
+      error: Default::default(),
+      instance: Default::default(),
+      references: Vec::new(),
+      glue_symbols: Default::default(),
+      strings: Default::default(),
+      preconditions,
+      reachable: None,
+    };
+
+    let used = self.used_names()?;
+    if !self.allowlist.is_empty() || used.is_some() {
+      let used = used.unwrap_or_default();
+      state.reachable = Some(timings.time("finding allowlisted items", || Reachable::new(entity, &self.allowlist, &used, &self.blocklist)));
+    }
+
+    let items = timings.time("walking the AST", || state.process_entity(entity, &Context {
+      ns: Vec::new(),
+    }));
+    let mut items = merge_duplicates(items);
+
+    // Instantiations of a template differing in one integer argument are
+    // also bound as a generic struct over it, built up alongside the indices
+    // of its instantiations.
+    let mut generics: Vec<(ItemGeneric, Vec<usize>)> = Vec::new();
+    for (i, instantiation) in self.instantiations.iter().enumerate() {
+      let name = format!("__blackbird_instantiation_{}", i);
+      let typedef = entity.get_children().into_iter().find(|x| x.get_name().as_ref() == Some(&name));
+      let item = match typedef.and_then(|x| state.process_instantiation(x, instantiation)) {
+        Some(x) => x,
+        None => continue,
+      };
+
+      let value = match &template_arguments(instantiation)[..] {
+        [x] => x.trim_end_matches(['u', 'U', 'l', 'L']).parse::<u64>().ok(),
+        _ => None,
+      };
+      if let (Some(value), Some(param), Item::Struct(strukt)) = (value, typedef.and_then(integer_parameter), &item) {
+        let template = instantiation[..instantiation.find('<').unwrap()].trim();
+        let name = template.rsplit("::").next().unwrap().to_string();
+        let ty = typedef.unwrap().get_typedef_underlying_type().unwrap();
+        let i = match generics.iter().position(|x| x.0.name == name) {
+          Some(i) => i,
+          None => {
+            generics.push((ItemGeneric {
+              name,
+              param,
+              instances: Vec::new(),
+              size: 0,
+              align: 1,
+              copy: true,
+              constructors: Vec::new(),
+              methods: Vec::new(),
+            }, Vec::new()));
+            generics.len() - 1
+          },
+        };
+        let (generic, indices) = &mut generics[i];
+        generic.instances.push((value, strukt.name.clone()));
+        generic.size = generic.size.max(ty.get_sizeof().unwrap_or(0));
+        generic.align = generic.align.max(ty.get_alignof().unwrap_or(1));
+        generic.copy &= strukt.copy;
+        indices.push(items.len());
+      }
+      items.push(item);
+    }
+    for (mut generic, indices) in generics {
+      if indices.len() < 2 {
+        continue;
+      }
+      let structs = indices.iter()
+        .filter_map(|x| match &items[*x] {
+          Item::Struct(item) => Some(&**item),
+          _ => None,
+        })
+        .collect::<Vec<_>>();
+      (generic.constructors, generic.methods) = generic_members(&structs);
+      items.push(Item::Generic(generic));
+    }
+
+    // The accessors CxxString, CxxVector and CxxBox need, once they turned
+    // out to be used.
+    let strings: Vec<_> = state.helpers.borrow().iter().filter_map(|x| match x {
+      Item::CxxString(x) => Some(x.clone()),
+      _ => None,
+    }).collect();
+    let vectors: Vec<_> = state.helpers.borrow().iter().filter_map(|x| match x {
+      Item::CxxVector(x) => Some(x.elements.clone()),
+      _ => None,
+    }).flatten().collect();
+    for vector in &vectors {
+      let cpp = &vector.cpp;
+      state.glue_fn(format!("size_t {}(const {}* self)", vector.len_symbol, cpp), "return self->size();".into());
+      state.glue_fn(format!("const {1}::value_type* {0}(const {1}* self)", vector.data_symbol, cpp), "return self->data();".into());
+      state.glue_fn(format!("void {0}({1}* self, const {1}::value_type* x)", vector.push_symbol, cpp), "self->push_back(*x);".into());
+      state.glue_fn(format!("void {}({}* self)", vector.new_symbol, cpp), format!("new ((void*)self) {}();", cpp));
+      state.glue_fn(format!("void {}({}* self)", vector.drop_symbol, cpp), "self->~vector();".into());
+    }
+    let boxes: Vec<_> = state.helpers.borrow().iter().filter_map(|x| match x {
+      Item::CxxBox(x) => Some(x.elements.clone()),
+      _ => None,
+    }).flatten().collect();
+    for element in boxes {
+      state.glue_fn(format!("void {}({}* self)", element.delete_symbol, element.cpp), "delete self;".into());
+    }
+    for string in strings {
+      state.glue_fn(format!("const char* {}(const std::string* self)", string.data_symbol), "return self->data();".into());
+      state.glue_fn(format!("size_t {}(const std::string* self)", string.len_symbol), "return self->size();".into());
+      state.glue_fn(format!("void {}(std::string* self)", string.new_symbol), "new ((void*)self) std::string();".into());
+      state.glue_fn(format!("void {}(std::string* self)", string.drop_symbol), "self->~basic_string();".into());
+    }
+
+    // Nothing refers to the AST anymore.
+    drop(tu);
+
+    if let Some(error) = state.error.into_inner() {
+      return Err(error);
+    }
+
+    if self.safe_api {
+      items = split_raw(items, true);
+    }
+
+    let mut helpers = state.helpers.into_inner();
+    helpers.extend(order_items(sanitize_modules(items, "", self.callbacks.as_deref())));
+
+    if self.symbols {
+      let root = self.module_path.as_ref().map_or_else(String::new, |x| format!("{}::", x));
+      let mut names = Vec::new();
+      link_names(&helpers, &root, &mut names);
+      let mut entries = state.references.into_iter().filter_map(|x| {
+        let path = match &x.symbol {
+          Some(symbol) => names.iter().find(|(y, _)| y == symbol)?.1.clone(),
+          None => x.path?,
+        };
+        Some(SymbolEntry { name: x.name, usr: x.usr, path, link_name: x.symbol })
+      }).collect::<Vec<_>>();
+      // Classes declared more than once are bound once.
+      let mut seen = HashSet::new();
+      entries.retain(|x| seen.insert((x.usr.clone(), x.path.clone())));
+      helpers.push(Item::Symbols(ItemSymbols { entries }));
+    }
+    for (name, methods) in traits {
+      let mut impls = Vec::new();
+      trait_impls(&helpers, &[], &quote!(), methods, &mut impls);
+      helpers.push(Item::Trait(ItemTrait { name: name.clone(), methods: methods.clone(), impls }));
+    }
+    Ok(Parsed {
+      items: helpers,
+      glue: state.glue,
+      glue_header: state.glue_header,
+      triple,
+      glue_symbols: state.glue_symbols.into_inner(),
+    })
+  }
+
+  pub fn generate(mut self) -> Result<(), Error> {
+    self = self.scan_header_dir(None)?;
+    let mut timings = Timings::default();
+    let (glue, Generated { functions, triple, include_dirs, .. }) = self.write(None, &mut timings)?;
+
+    let glue_name = self.glue_name.clone().unwrap_or_else(|| {
+      let package = std::env::var("CARGO_PKG_NAME").unwrap_or_default().replace('-', "_");
+      format!("{}_glue_{:016x}", package, fnv1a(&functions))
+    });
+
+    let mut build = cc::Build::new();
+    let target = std::env::var("TARGET").ok();
+    let compiler = self.glue_compilers.iter().rev()
+      .find(|x| x.0.is_some() && x.0 == target)
+      .or_else(|| self.glue_compilers.iter().rev().find(|x| x.0.is_none()));
+    if let Some((_, compiler)) = compiler {
+      build.compiler(compiler);
+      if !triple.is_empty() {
+        check_compiler_target(&build, &triple)?;
+      }
+    }
+    for dir in include_dirs.iter().chain(&self.include_dirs) {
+      build.include(dir);
+    }
+    for arg in &self.clang_args {
+      if arg.starts_with("-std=") || (arg.len() > 2 && (arg.starts_with("-D") || arg.starts_with("-I"))) {
+        build.flag(arg);
+      }
+    }
+    if !self.targets.is_empty() {
+      if let Ok(os) = std::env::var("CARGO_CFG_TARGET_OS") {
+        build.define(&format!("BLACKBIRD_TARGET_{}", os), None);
+      }
+    }
+
+    if self.qt {
+      for define in QT_DEFINES {
+        let (name, value) = define[2..].split_at(define.find('=').unwrap() - 2);
+        build.define(name, Some(&value[1..]));
+      }
+    }
+
+    timings.time("compiling glue", || {
+      build
+        .flag_if_supported("-Wno-unknown-pragmas")
+        .file(&glue)
+        .compile(&glue_name);
+    });
+
+    if self.timings {
+      timings.report();
+    }
+
+    Ok(())
+  }
+
+  /// Writes `bindings.rs`, `glue.h` and `glue.cc` to `dir` without compiling
+  /// the glue or needing to run from a build script, so that bindings can be
+  /// generated ahead of time and checked in. Whatever builds the crate is
+  /// then responsible for compiling `glue.cc`.
+  pub fn write_to<P: Into<PathBuf>>(mut self, dir: P) -> Result<(), Error> {
+    let dir = dir.into();
+    fs::create_dir_all(&dir).map_err(io_error(&dir))?;
+    self = self.scan_header_dir(Some(&dir))?;
+    let mut timings = Timings::default();
+    self.write(Some(&dir), &mut timings)?;
+    if self.timings {
+      timings.report();
+    }
+    Ok(())
+  }
+
+  /// Generates the bindings and the glue without writing them anywhere, for
+  /// callers that post-process them or put them somewhere themselves. The
+  /// umbrella of `headers_from_dir` still goes in `OUT_DIR`.
+  pub fn generate_to_string(mut self) -> Result<(String, String), Error> {
+    self = self.scan_header_dir(None)?;
+    let mut timings = Timings::default();
+    let generated = self.render(&mut timings)?;
+    if self.timings {
+      timings.report();
+    }
+    Ok((generated.bindings, generated.glue))
+  }
+
+  // Globs the directory given to `headers_from_dir`, if any, and writes the
+  // umbrella including what matched to `dir`, falling back on `OUT_DIR`.
+  // The headers are included relative to the directory, which is added to
+  // the include path.
+  fn scan_header_dir(mut self, dir: Option<&Path>) -> Result<Builder, Error> {
+    let (root, pattern) = match self.header_dir.take() {
+      Some(x) => x,
+      None => return Ok(self),
+    };
+    let root = fs::canonicalize(&root).map_err(io_error(&root))?;
+    let full = format!("{}/{}", glob::Pattern::escape(&root.to_string_lossy()), pattern);
+    let paths = glob::glob(&full).map_err(|x| Error::InvalidPattern { pattern: pattern.clone(), message: x.to_string() })?;
+    let mut headers = Vec::new();
+    for path in paths {
+      let path = path.map_err(|x| Error::Io { path: x.path().to_path_buf(), error: x.into_error() })?;
+      if path.is_file() {
+        headers.push(path);
+      }
+    }
+    headers.sort();
+
+    let mut umbrella = String::from("#pragma once\n");
+    for header in &headers {
+      let name = header.strip_prefix(&root).ok()
+        .and_then(|x| x.components().map(|x| x.as_os_str().to_str()).collect::<Option<Vec<_>>>())
+        .map(|x| x.join("/"))
+        .filter(|x| !x.contains(&['"', '\n'][..]))
+        .ok_or_else(|| Error::InvalidInclude { path: header.clone() })?;
+      umbrella += &format!("#include \"{}\"\n", name);
+    }
+
+    let dir = match dir {
+      Some(x) => x.to_path_buf(),
+      None => std::env::var_os("OUT_DIR").map(PathBuf::from).ok_or(Error::MissingOutDir)?,
+    };
+    let path = dir.join("umbrella.h");
+    fs::write(&path, umbrella).map_err(io_error(&path))?;
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    self.header = path;
+    self.clang_args.push(format!("-I{}", root.display()));
+    Ok(self)
+  }
+
+  // Writes out the bindings and glue, to the files they've been given or
+  // `dir`, falling back on `OUT_DIR`. Returns where the glue went.
+  fn write(&self, dir: Option<&Path>, timings: &mut Timings) -> Result<(PathBuf, Generated), Error> {
+    let generated = self.render(timings)?;
+    let dir = || match dir {
+      Some(x) => Ok(x.to_path_buf()),
+      None => std::env::var_os("OUT_DIR").map(PathBuf::from).ok_or(Error::MissingOutDir),
+    };
+
+    let path = match &self.out_file {
+      Some(x) => x.clone(),
+      None => dir()?.join("bindings.rs"),
+    };
+    fs::write(&path, &generated.bindings).map_err(io_error(&path))?;
+
+    let glue = match &self.glue_file {
+      Some(x) => x.clone(),
+      None => dir()?.join("glue.cc"),
+    };
+    fs::write(&glue, &generated.glue).map_err(io_error(&glue))?;
+    let path = glue.with_extension("h");
+    fs::write(&path, &generated.glue_header).map_err(io_error(&path))?;
+
+    Ok((glue, generated))
+  }
+
+  // Parses the header and generates the bindings and glue.
+  fn render(&self, timings: &mut Timings) -> Result<Generated, Error> {
+    self.check_paths()?;
+    let traits = self.traits.iter().map(|(name, methods)| {
+      Ok((name.clone(), methods.iter().map(|x| TraitMethod::new(x)).collect::<Result<_, _>>()?))
+    }).collect::<Result<Vec<Trait>, Error>>()?;
+    let preconditions = self.preconditions.iter().map(|(function, condition)| match TokenStream::from_str(condition) {
+      Ok(x) => Ok((function.clone(), x)),
+      Err(_) => Err(Error::InvalidCondition { condition: condition.clone() }),
+    }).collect::<Result<Vec<_>, Error>>()?;
+    let clang = Clang::new().map_err(|message| Error::Parse { header: self.header.clone(), message })?;
+
+    let index = Index::new(&clang, false, false);
+
+    let Parsed { items, glue, glue_header, triple, glue_symbols } = if self.targets.is_empty() {
+      self.parse(&index, &[], true, &traits, &preconditions, timings)?
+    } else {
+      let mut glue = String::new();
+      let mut glue_header = String::new();
+      let mut triple = String::new();
+      let mut glue_symbols = HashSet::new();
+      let mut sets = Vec::new();
+      let os = std::env::var("CARGO_CFG_TARGET_OS").ok();
+      for target in &self.targets {
+        let current = os.as_ref() == Some(&target.os);
+        let parsed = self.parse(&index, &target.args, current, &traits, &preconditions, timings)?;
+        if current {
+          triple = parsed.triple;
+        }
+        glue += &format!("#ifdef BLACKBIRD_TARGET_{}\n{}\n#endif\n", target.os, parsed.glue);
+        glue_header += &format!("#ifdef BLACKBIRD_TARGET_{}\n{}#endif\n", target.os, parsed.glue_header);
+        glue_symbols.extend(parsed.glue_symbols);
+        sets.push((target.os.clone(), parsed.items));
+      }
+      let items = timings.time("merging targets", || merge_targets(sets));
+      Parsed { items, glue, glue_header, triple, glue_symbols }
+    };
+
+    if let Some((dir, module)) = &self.fuzz {
+      let mut harnesses = Vec::new();
+      let module = TokenStream::from_str(module).map_err(|_| Error::InvalidPath { path: module.clone() })?;
+      fuzz_harnesses(&items, &module, &[], &mut harnesses);
+      fs::create_dir_all(dir).map_err(io_error(dir))?;
+      for (name, harness) in harnesses {
+        let path = dir.join(format!("{}.rs", name));
+        fs::write(&path, harness.to_string()).map_err(io_error(&path))?;
+      }
+    }
+
+    #[cfg(feature = "ir")]
+    if let Some(path) = &self.ir {
+      let json = serde_json::to_string(&ir::Document::new(&self.header.to_string_lossy(), &items)).map_err(|x| io_error(path)(x.into()))?;
+      fs::write(path, json + "\n").map_err(io_error(path))?;
+    }
+
+    if let Some(library) = &self.verify_symbols {
+      timings.time("verifying symbols", || verify_symbols(library, &items, &glue_symbols))?;
+    }
+
+    let banner = if self.license_banner {
+      let source = fs::read_to_string(&self.header).map_err(io_error(&self.header))?;
+      let headers = Some(&self.header).into_iter().chain(&self.headers)
+        .map(|x| x.file_name().map_or(x.as_os_str(), |x| x).to_string_lossy())
+        .collect::<Vec<_>>();
+      let mut banner = license_banner(&source).iter().map(|x| format!("//{}{}\n", if x.is_empty() { "" } else { " " }, x)).collect::<String>();
+      if !banner.is_empty() {
+        banner += "//\n";
+      }
+      banner + &format!("// Generated by blackbird from {} at version {}.\n\n", headers.join(", "), env!("CARGO_PKG_VERSION"))
+    } else {
+      String::new()
+    };
+
+    // Items are printed one at a time rather than collected into a single
+    // token stream, which gets expensive for large headers.
+    let bindings = timings.time("writing bindings", || {
+      let mut bindings = banner.clone();
+      if let Some(name) = &self.module_name {
+        let name = format_ident!("{}", name);
+        bindings += &format!("{} {{\n", quote!(pub mod #name));
+      }
+      if self.prelude {
+        bindings += &format!("{}\n", prelude(&items));
+      }
+      let mut symbols = Vec::new();
+      for item in items {
+        let tokens = item.into_token_stream();
+        let tokens = if self.dynamic_loading { dynamic_externs(tokens, &glue_symbols, &mut symbols) } else { tokens };
+        bindings += &format!("{}\n", tokens);
+      }
+      if self.dynamic_loading {
+        bindings += &format!("{}\n", library_module(&symbols));
+      }
+      if self.module_name.is_some() {
+        bindings += "}\n";
+      }
+      bindings
+    });
+
+    let mut include = Vec::new();
+    let mut include_dirs = Vec::new();
+    for header in Some(&self.header).into_iter().chain(&self.headers) {
+      let (line, dir) = self.include(header)?;
+      include.push(line);
+      if let Some(dir) = dir.filter(|x| !include_dirs.contains(x)) {
+        include_dirs.push(dir);
+      }
+    }
+    let include = include.join("\n");
+
+    // The same entry points the bindings link against, for C or hand-written
+    // C++ that wants to call them directly.
+    let glue_header = format!("{}#pragma once\n{}\nextern \"C\" {{\n{}}}\n", banner, include, glue_header);
+
+    // Messages of caught exceptions are copied out with `malloc`, for the
+    // bindings to `free` once they've been converted.
+    let catch = if self.catch_exceptions {
+      "#include <cstring>\n#include <exception>\n\
+      static char* blackbird_what(const char* what) { char* x = (char*)malloc(strlen(what) + 1); if (x) strcpy(x, what); return x; }\n"
+    } else {
+      ""
+    };
+
+    let debug = self.debug_styles.iter().map(|x| &x.1).chain(Some(&self.default_debug_style))
+      .any(|x| matches!(x, DebugStyle::Method(_) | DebugStyle::Stream));
+    let debug = if debug { "#include <cstring>\n#include <sstream>\n#include <string>\n" } else { "" };
+
+    Ok(Generated {
+      bindings,
+      glue: format!(
+        "{}#include <cstdlib>\n#include <new>\n#include <typeinfo>\n#include <cassert>\n{}{}{}\n{}",
+        banner,
+        catch,
+        debug,
+        include,
+        glue,
+      ),
+      glue_header,
+      functions: glue,
+      triple,
+      include_dirs,
+    })
+  }
+}
+
+pub fn generate<P: Into<PathBuf>>(path: P) -> Result<(), Error> {
+  Builder::new(path).generate()
+}
+
+/// End-to-end tests of binding configurations: a header is bound, compiled
+/// into a temporary crate along with the glue and the C++ defining it, and
+/// Rust code using the bindings is run.
+pub mod testing {
+  use super::Builder;
+  use rand::random;
+  use std::fs;
+  use std::process::Command;
+
+  /// A header snippet to bind and run Rust code against.
+  pub struct Test {
+    header: String,
+    source: String,
+    configure: Option<Box<dyn FnOnce(Builder) -> Builder>>,
+  }
+
+  impl Test {
+    pub fn new<S: Into<String>>(header: S) -> Test {
+      Test {
+        header: header.into(),
+        source: String::new(),
+        configure: None,
+      }
+    }
+
+    /// Sets the C++ defining what the header declares. It's compiled with
+    /// the header already included.
+    pub fn source<S: Into<String>>(mut self, source: S) -> Test {
+      self.source = source.into();
+      self
+    }
+
+    /// Configures the `Builder` the header is bound with.
+    pub fn configure<F: FnOnce(Builder) -> Builder + 'static>(mut self, configure: F) -> Test {
+      self.configure = Some(Box::new(configure));
+      self
+    }
+
+    /// Runs `code` as the body of `main` with the bindings in scope,
+    /// panicking with the output if anything fails to build or run.
+    ///
+    /// Everything is written to a temporary directory and compiled for the
+    /// host, without touching the environment, so tests can run in parallel.
+    pub fn run<S: AsRef<str>>(self, code: S) {
+      let dir = std::env::temp_dir().join(format!("blackbird-test-{:x}", random::<u64>()));
+      let out = dir.join("out");
+      fs::create_dir_all(&out).unwrap();
+      fs::create_dir_all(dir.join("src")).unwrap();
+      fs::write(dir.join("header.h"), &self.header).unwrap();
+      fs::write(dir.join("source.cc"), format!("#include \"header.h\"\n{}", self.source)).unwrap();
+
+      let output = Command::new("rustc").arg("-vV").output().expect("failed to run rustc");
+      let host = String::from_utf8_lossy(&output.stdout).lines()
+        .find_map(|x| x.strip_prefix("host: ").map(|x| x.to_string()))
+        .expect("rustc -vV printed no host");
+
+      let builder = Builder::new(dir.join("header.h"));
+      let builder = match self.configure {
+        Some(configure) => configure(builder),
+        None => builder,
+      };
+      builder.write_to(&out).expect("failed to generate bindings");
+
+      let build = || {
+        let mut build = cc::Build::new();
+        build
+          .cpp(true)
+          .target(&host)
+          .host(&host)
+          .opt_level(0)
+          .debug(false)
+          .include(&dir)
+          .out_dir(&out)
+          .cargo_metadata(false);
+        build
+      };
+      build()
+        .flag_if_supported("-Wno-unknown-pragmas")
+        .file(out.join("glue.cc"))
+        .compile("blackbird_test_glue");
+      build()
+        .file(dir.join("source.cc"))
+        .compile("blackbird_test_source");
+
+      let stdlib = if cfg!(target_os = "macos") {
+        "cargo:rustc-link-lib=c++"
+      } else if cfg!(target_env = "msvc") {
+        ""
+      } else {
+        "cargo:rustc-link-lib=stdc++"
+      };
+      fs::write(dir.join("Cargo.toml"), "[package]\nname = \"blackbird_test\"\nversion = \"0.0.0\"\nedition = \"2018\"\n\n[workspace]\n").unwrap();
+      fs::write(dir.join("build.rs"), format!(
+        "fn main() {{ println!({:?}); println!(\"cargo:rustc-link-lib=static=blackbird_test_glue\"); \
+        println!(\"cargo:rustc-link-lib=static=blackbird_test_source\"); println!({:?}); }}",
+        format!("cargo:rustc-link-search=native={}", out.display()),
+        stdlib,
+      )).unwrap();
+      fs::write(dir.join("src").join("main.rs"), format!(
+        "#![allow(dead_code, non_camel_case_types, non_snake_case, unused_imports)]\ninclude!({:?});\nfn main() {{\n{}\n}}\n",
+        out.join("bindings.rs"),
+        code.as_ref(),
+      )).unwrap();
+
+      let output = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .arg("run")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(dir.join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", dir.join("target"))
+        .output()
+        .unwrap();
+      if !output.status.success() {
+        panic!(
+          "test in {} failed:\n{}{}",
+          dir.display(),
+          String::from_utf8_lossy(&output.stdout),
+          String::from_utf8_lossy(&output.stderr),
+        );
+      }
+
+      fs::remove_dir_all(&dir).ok();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::matches_pattern;
+
+  #[test]
+  fn patterns() {
+    assert!(matches_pattern("ns::Widget", "ns::*"));
+    assert!(!matches_pattern("other::Widget", "ns::*"));
+    assert!(matches_pattern("Vec2", "Vec?"));
+    assert!(!matches_pattern("Vec23", "Vec?"));
+    assert!(matches_pattern("Vecb", "Vec[a-z]"));
+    assert!(!matches_pattern("VecB", "Vec[a-z]"));
+    assert!(matches_pattern("ns::Widget", "ns::Widget"));
+    assert!(!matches_pattern("ns::Widgets", "ns::Widget"));
+    assert!(matches_pattern("Vec::operator*", "Vec::operator*"));
+    assert!(!matches_pattern("Vec::operator*=", "Vec::operator*"));
+    assert!(matches_pattern("Vec::operator[]", "Vec::operator[]"));
+  }
 }
@@ -0,0 +1,15 @@
+use gen::testing::Test;
+
+#[test]
+fn free_function() {
+  Test::new("int add(int a, int b);")
+    .source("int add(int a, int b) { return a + b; }")
+    .run("assert_eq!(unsafe { add(2, 3) }, 5);");
+}
+
+#[test]
+fn method() {
+  Test::new("struct Counter { int count; void bump(); };")
+    .source("void Counter::bump() { count++; }")
+    .run("let mut x = Counter { count: 1 }; unsafe { x.bump() }; assert_eq!(x.count, 2);");
+}
@@ -0,0 +1,17 @@
+#pragma once
+#pragma warning(push)
+#pragma warning(disable: 4251 4275)
+#pragma comment(lib, "vendor.lib")
+#pragma region Exports
+#pragma detect_mismatch("vendor_abi", "2")
+
+struct Version {
+  int major;
+  int minor;
+};
+
+#pragma warning(suppress: 4100)
+int version_sum(Version v);
+
+#pragma endregion
+#pragma warning(pop)
@@ -0,0 +1,16 @@
+#pragma once
+#pragma omp declare target
+
+// Math kernels as an OpenMP offloading library would declare them.
+#pragma omp declare simd
+int scale(int x, int factor);
+
+#pragma omp declare reduction(sum : int : omp_out += omp_in)
+
+struct Accumulator {
+  int total;
+#pragma omp declare simd
+  void add(int x);
+};
+
+#pragma omp end declare target
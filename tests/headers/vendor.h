@@ -0,0 +1,26 @@
+#pragma once
+#pragma GCC diagnostic push
+#pragma GCC diagnostic ignored "-Wdeprecated-declarations"
+#pragma clang diagnostic push
+#pragma clang diagnostic ignored "-Wunused-parameter"
+#pragma mark - Queue
+#pragma GCC visibility push(default)
+#pragma ivdep
+#pragma nounroll
+#pragma intel optimization_level 3
+#pragma vendor_tool instrument(on)
+_Pragma("acme noinline")
+
+#define VENDOR_PRAGMA(x) _Pragma(#x)
+VENDOR_PRAGMA(acme section(".fast"))
+
+struct Queue {
+  int length;
+};
+
+#pragma GCC poison unsafe_push
+int queue_length(const Queue* queue);
+
+#pragma GCC visibility pop
+#pragma clang diagnostic pop
+#pragma GCC diagnostic pop
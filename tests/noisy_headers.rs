@@ -0,0 +1,30 @@
+//! Headers full of pragmas and other preprocessor noise that shouldn't
+//! change what gets bound.
+
+use gen::testing::Test;
+
+#[test]
+fn openmp() {
+  Test::new(include_str!("headers/omp.h"))
+    .source("int scale(int x, int factor) { return x * factor; }\nvoid Accumulator::add(int x) { total += x; }")
+    .run("
+      assert_eq!(unsafe { scale(3, 4) }, 12);
+      let mut x = Accumulator { total: 1 };
+      unsafe { x.add(2) };
+      assert_eq!(x.total, 3);
+    ");
+}
+
+#[test]
+fn msvc() {
+  Test::new(include_str!("headers/msvc.h"))
+    .source("int version_sum(Version v) { return v.major + v.minor; }")
+    .run("assert_eq!(unsafe { version_sum(Version { major: 1, minor: 2 }) }, 3);");
+}
+
+#[test]
+fn vendor() {
+  Test::new(include_str!("headers/vendor.h"))
+    .source("int queue_length(const Queue* queue) { return queue->length; }")
+    .run("assert_eq!(unsafe { queue_length(&Queue { length: 7 }) }, 7);");
+}
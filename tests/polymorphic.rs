@@ -0,0 +1,53 @@
+use gen::testing::Test;
+
+const HEADER: &str = "
+  struct Shape { virtual ~Shape(); int sides; };
+  struct Square : Shape { int side; };
+  struct Circle : Shape { int radius; };
+  Shape* make_shape(bool square);
+";
+
+const SOURCE: &str = "
+  Shape::~Shape() {}
+  Shape* make_shape(bool square) {
+    if (square) { Square* x = new Square; x->sides = 4; x->side = 2; return x; }
+    Circle* x = new Circle; x->sides = 0; x->radius = 3; return x;
+  }
+";
+
+#[test]
+fn downcast() {
+  Test::new(HEADER)
+    .source(SOURCE)
+    .run("
+      let square = unsafe { &mut *make_shape(true) };
+      assert_eq!(square.downcast_ref::<Square>().map(|x| x.side), Some(2));
+      assert!(square.downcast_ref::<Circle>().is_none());
+      let circle = unsafe { &mut *make_shape(false) };
+      assert_eq!(circle.downcast_mut::<Circle>().map(|x| x.radius), Some(3));
+      assert!(circle.downcast_mut::<Square>().is_none());
+    ");
+}
+
+#[test]
+fn type_name() {
+  Test::new(HEADER)
+    .source(SOURCE)
+    .run("
+      let square = unsafe { &*make_shape(true) };
+      assert_eq!(square.type_name(), Square::static_type_name());
+      assert_ne!(square.type_name(), Shape::static_type_name());
+      assert_eq!(Square::TYPE_NAME, \"Square\");
+    ");
+}
+
+#[test]
+fn constructor() {
+  Test::new("struct Counter { Counter(); virtual ~Counter(); virtual int get(); int count; };")
+    .source("Counter::Counter() : count(5) {}\nCounter::~Counter() {}\nint Counter::get() { return count; }")
+    .run("
+      let mut x = unsafe { Counter::new() };
+      assert_eq!(unsafe { x.get() }, 5);
+      assert_eq!(x.type_name(), Counter::static_type_name());
+    ");
+}
@@ -0,0 +1,45 @@
+//! Records that refer back to themselves, which generation has to finish on.
+
+use gen::testing::Test;
+
+#[test]
+fn self_referential() {
+  Test::new("struct Node { int value; Node* next; };\nint sum(const Node* node);")
+    .source("int sum(const Node* node) { return node ? node->value + sum(node->next) : 0; }")
+    .run("
+      let mut tail = Node { value: 2, next: std::ptr::null_mut() };
+      let head = Node { value: 1, next: &mut tail };
+      assert_eq!(unsafe { sum(&head) }, 3);
+    ");
+}
+
+#[test]
+fn mutually_recursive() {
+  Test::new("
+    struct Child;
+    struct Parent { Child* first; int children; };
+    struct Child { Parent* parent; Child* sibling; };
+    int count(const Child* child);
+  ")
+    .source("int count(const Child* child) { return child->parent->children; }")
+    .run("
+      let mut parent = Parent { first: std::ptr::null_mut(), children: 1 };
+      let mut child = Child { parent: &mut parent, sibling: std::ptr::null_mut() };
+      parent.first = &mut child;
+      assert_eq!(unsafe { count(&child) }, 1);
+    ");
+}
+
+#[test]
+fn through_template_argument() {
+  Test::new("
+    template <typename T> struct Box { T* value; };
+    struct Tree { int value; Box<Tree> left; Box<Tree> right; };
+    int value(const Tree* tree);
+  ")
+    .source("int value(const Tree* tree) { return tree->value; }")
+    .run("
+      let tree: Tree = unsafe { std::mem::zeroed() };
+      assert_eq!(unsafe { value(&tree) }, 0);
+    ");
+}